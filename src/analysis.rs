@@ -0,0 +1,854 @@
+use crate::{
+    import::{cell_name, row_label},
+    puzzle::{Cage, Constraint, Region, Solution, Variant},
+    state::GameState,
+};
+
+/// The deductions the solve path is made of, easiest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Technique {
+    FullHouse,
+    NakedSingle,
+    HiddenSingle,
+    /// Candidates no filling of a Killer cage uses.
+    CageCombination,
+    /// Candidates no filling of a thermometer, arrow or dot pair allows.
+    ConstraintRule,
+    LockedCandidates,
+    NakedPair,
+    HiddenPair,
+    /// No technique above applies, so the solution's digit is placed in a
+    /// cell with the fewest candidates.
+    Guess,
+}
+
+impl Technique {
+    pub const ALL: [Technique; 9] = [
+        Technique::FullHouse,
+        Technique::NakedSingle,
+        Technique::HiddenSingle,
+        Technique::CageCombination,
+        Technique::ConstraintRule,
+        Technique::LockedCandidates,
+        Technique::NakedPair,
+        Technique::HiddenPair,
+        Technique::Guess,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Technique::FullHouse => "Full house",
+            Technique::NakedSingle => "Naked single",
+            Technique::HiddenSingle => "Hidden single",
+            Technique::CageCombination => "Cage combination",
+            Technique::ConstraintRule => "Constraint rule",
+            Technique::LockedCandidates => "Locked candidates",
+            Technique::NakedPair => "Naked pair",
+            Technique::HiddenPair => "Hidden pair",
+            Technique::Guess => "Guess",
+        }
+    }
+}
+
+/// One deduction: a placement, or candidates ruled out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub technique: Technique,
+    pub placement: Option<((usize, usize), u8)>,
+    pub detail: String,
+}
+
+/// How the player came to fill a cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filled {
+    /// The techniques could have found it from the board at the time.
+    Logic,
+    Guess,
+    Hint,
+}
+
+/// A cell the player filled with its solution digit and kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Placement {
+    pub cell: (usize, usize),
+    pub digit: u8,
+    pub filled: Filled,
+}
+
+/// A logical solve path for a finished game, next to the order the player
+/// filled its cells in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Analysis {
+    pub steps: Vec<Step>,
+    /// In the order the player filled them.
+    pub placements: Vec<Placement>,
+    /// Entries that didn't match the solution.
+    pub wrong: usize,
+}
+
+impl Analysis {
+    pub fn of(state: &GameState) -> Self {
+        let rules = Rules::of(state);
+        let givens: Vec<Vec<Option<u8>>> = state
+            .grid
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|cell| cell.value.filter(|_| cell.given))
+                    .collect()
+            })
+            .collect();
+
+        let mut solver = Solver::new(&givens, &rules);
+        let steps = std::iter::from_fn(|| solver.step(true)).collect();
+
+        // Only a cell's last move counts: earlier ones were overwritten.
+        let last_moves: Vec<usize> = (0..state.moves.len())
+            .filter(|&i| {
+                let m = state.moves[i];
+                !state.moves[i + 1..]
+                    .iter()
+                    .any(|later| (later.row, later.col) == (m.row, m.col))
+            })
+            .collect();
+        let mut board = givens;
+        let mut placements = Vec::new();
+        let mut wrong = 0;
+        for (i, m) in state.moves.iter().enumerate() {
+            let cell = (m.row, m.col);
+            let correct = m.value == Some(state.solution[m.row][m.col]);
+            if m.value.is_some() && !correct {
+                wrong += 1;
+            }
+            if correct && last_moves.contains(&i) {
+                let filled = if m.hint {
+                    Filled::Hint
+                } else if Solver::new(&board, &rules).finds(cell) {
+                    Filled::Logic
+                } else {
+                    Filled::Guess
+                };
+                placements.push(Placement {
+                    cell,
+                    digit: state.solution[m.row][m.col],
+                    filled,
+                });
+            }
+            // Wrong digits would mislead the techniques, so the board only
+            // keeps correct ones.
+            board[m.row][m.col] = m.value.filter(|_| correct);
+        }
+
+        Self {
+            steps,
+            placements,
+            wrong,
+        }
+    }
+
+    /// How often each technique was used, leaving out the unused ones.
+    pub fn counts(&self) -> Vec<(Technique, usize)> {
+        Technique::ALL
+            .into_iter()
+            .map(|technique| {
+                let count = self
+                    .steps
+                    .iter()
+                    .filter(|step| step.technique == technique)
+                    .count();
+                (technique, count)
+            })
+            .filter(|&(_, count)| count > 0)
+            .collect()
+    }
+
+    /// When the player filled `cell`, counting from 1, and how.
+    pub fn filled_at(&self, cell: (usize, usize)) -> Option<(usize, Filled)> {
+        self.placements
+            .iter()
+            .position(|placement| placement.cell == cell)
+            .map(|i| (i + 1, self.placements[i].filled))
+    }
+
+    pub fn count_filled(&self, filled: Filled) -> usize {
+        self.placements
+            .iter()
+            .filter(|placement| placement.filled == filled)
+            .count()
+    }
+}
+
+/// Names for `crate::puzzle::regions`' rows, columns, boxes and extras.
+fn region_names(size: usize, variant: Variant) -> Vec<String> {
+    let mut names = Vec::new();
+    for i in 0..size {
+        names.push(format!("row {}", row_label(i)));
+        names.push(format!("column {}", i + 1));
+    }
+    names.extend((1..=size).map(|i| format!("box {i}")));
+    let extra = match variant {
+        Variant::SudokuX => "diagonal",
+        _ => "window",
+    };
+    names.extend((1..=4).map(|i| format!("{extra} {i}")));
+    names
+}
+
+fn bit(digit: u8) -> u32 {
+    1 << digit
+}
+
+fn digits(mask: u32) -> impl Iterator<Item = u8> {
+    (1..=16).filter(move |&digit| mask & bit(digit) != 0)
+}
+
+fn cell_list(cells: &[(usize, usize)]) -> String {
+    cells
+        .iter()
+        .map(|&cell| cell_name(cell))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn plural(count: usize, what: &str) -> String {
+    format!("{count} {what}{}", if count == 1 { "" } else { "s" })
+}
+
+fn constraint_name(constraint: &Constraint) -> &'static str {
+    match constraint {
+        Constraint::Thermo(_) => "thermometer",
+        Constraint::Arrow { .. } => "arrow",
+        Constraint::WhiteDot(_) => "white dot",
+        Constraint::BlackDot(_) => "black dot",
+        Constraint::V(_) => "V",
+        Constraint::X(_) => "X",
+    }
+}
+
+/// Most fillings a cage or constraint is searched for before the solver
+/// gives up on it for the step.
+const FILLING_BUDGET: usize = 20_000;
+
+/// What a game's digits must satisfy, and its solution for guesses.
+struct Rules<'a> {
+    regions: Vec<Region>,
+    names: Vec<String>,
+    cages: &'a [Cage],
+    constraints: &'a [Constraint],
+    solution: &'a Solution,
+}
+
+impl<'a> Rules<'a> {
+    fn of(state: &'a GameState) -> Self {
+        Self {
+            regions: crate::puzzle::regions(state.shape, state.variant, &state.boxes),
+            names: region_names(state.shape.size(), state.variant),
+            cages: &state.cages,
+            constraints: &state.constraints,
+            solution: &state.solution,
+        }
+    }
+}
+
+/// Pencil-and-paper solving with candidates kept for every empty cell.
+struct Solver<'a> {
+    rules: &'a Rules<'a>,
+    values: Vec<Vec<Option<u8>>>,
+    candidates: Vec<Vec<u32>>,
+    /// The regions each cell is in.
+    cell_regions: Vec<Vec<Vec<usize>>>,
+}
+
+impl<'a> Solver<'a> {
+    fn new(values: &[Vec<Option<u8>>], rules: &'a Rules<'a>) -> Self {
+        let size = values.len();
+        let mut cell_regions = vec![vec![Vec::new(); size]; size];
+        for (index, region) in rules.regions.iter().enumerate() {
+            for &(row, col) in region {
+                cell_regions[row][col].push(index);
+            }
+        }
+        let all = (1..=size as u8).fold(0, |mask, digit| mask | bit(digit));
+        let mut solver = Self {
+            rules,
+            values: vec![vec![None; size]; size],
+            candidates: vec![vec![all; size]; size],
+            cell_regions,
+        };
+        for (row, cells) in values.iter().enumerate() {
+            for (col, value) in cells.iter().enumerate() {
+                if let Some(digit) = value {
+                    solver.place((row, col), *digit);
+                }
+            }
+        }
+        solver
+    }
+
+    fn place(&mut self, (row, col): (usize, usize), digit: u8) {
+        self.values[row][col] = Some(digit);
+        self.candidates[row][col] = 0;
+        for &index in &self.cell_regions[row][col] {
+            for &(r, c) in &self.rules.regions[index] {
+                self.candidates[r][c] &= !bit(digit);
+            }
+        }
+    }
+
+    fn empty_cells(&self, region: usize) -> Vec<(usize, usize)> {
+        self.rules.regions[region]
+            .iter()
+            .copied()
+            .filter(|&(row, col)| self.values[row][col].is_none())
+            .collect()
+    }
+
+    /// Whether `cell` is a single on this board, once the techniques have
+    /// ruled out all they can without placing anything.
+    fn finds(mut self, cell: (usize, usize)) -> bool {
+        loop {
+            if self.is_single(cell) {
+                return true;
+            }
+            let eliminated = self
+                .cage_combination()
+                .or_else(|| self.constraint_rule())
+                .or_else(|| self.locked_candidates())
+                .or_else(|| self.naked_pair())
+                .or_else(|| self.hidden_pair());
+            if eliminated.is_none() {
+                return false;
+            }
+        }
+    }
+
+    fn is_single(&self, (row, col): (usize, usize)) -> bool {
+        let mask = self.candidates[row][col];
+        mask.count_ones() == 1
+            || digits(mask).any(|digit| {
+                self.cell_regions[row][col].iter().any(|&region| {
+                    self.empty_cells(region)
+                        .iter()
+                        .filter(|&&(r, c)| self.candidates[r][c] & bit(digit) != 0)
+                        .count()
+                        == 1
+                })
+            })
+    }
+
+    /// Makes the easiest deduction left, or a guess when `guess` allows it
+    /// and nothing else does. `None` once the grid is full or stuck.
+    fn step(&mut self, guess: bool) -> Option<Step> {
+        let placement = |technique, cell: (usize, usize), digit: u8, detail: String| Step {
+            technique,
+            placement: Some((cell, digit)),
+            detail: format!("{digit} in {}, {detail}", cell_name(cell)),
+        };
+        let step = if let Some((cell, digit, region)) = self.full_house() {
+            let detail = format!("the last cell of {}", self.rules.names[region]);
+            placement(Technique::FullHouse, cell, digit, detail)
+        } else if let Some((cell, digit)) = self.naked_single() {
+            let detail = "its only candidate".to_string();
+            placement(Technique::NakedSingle, cell, digit, detail)
+        } else if let Some((cell, digit, region)) = self.hidden_single() {
+            let detail = format!("its only place in {}", self.rules.names[region]);
+            placement(Technique::HiddenSingle, cell, digit, detail)
+        } else if let Some(step) = self
+            .cage_combination()
+            .or_else(|| self.constraint_rule())
+            .or_else(|| self.locked_candidates())
+            .or_else(|| self.naked_pair())
+            .or_else(|| self.hidden_pair())
+        {
+            return Some(step);
+        } else if guess {
+            let (cell, options) = self.guess()?;
+            let digit = self.rules.solution[cell.0][cell.1];
+            let detail = format!("one of {options} candidates");
+            placement(Technique::Guess, cell, digit, detail)
+        } else {
+            return None;
+        };
+        let (cell, digit) = step.placement.expect("placements are made above");
+        self.place(cell, digit);
+        Some(step)
+    }
+
+    fn full_house(&self) -> Option<((usize, usize), u8, usize)> {
+        (0..self.rules.regions.len()).find_map(|region| match self.empty_cells(region)[..] {
+            [(row, col)] => {
+                let digit = digits(self.candidates[row][col]).next()?;
+                Some(((row, col), digit, region))
+            }
+            _ => None,
+        })
+    }
+
+    fn naked_single(&self) -> Option<((usize, usize), u8)> {
+        let size = self.values.len();
+        (0..size * size)
+            .map(|i| (i / size, i % size))
+            .filter(|&(row, col)| self.values[row][col].is_none())
+            .find_map(|(row, col)| {
+                let mask = self.candidates[row][col];
+                (mask.count_ones() == 1).then(|| ((row, col), mask.trailing_zeros() as u8))
+            })
+    }
+
+    fn hidden_single(&self) -> Option<((usize, usize), u8, usize)> {
+        let size = self.values.len() as u8;
+        (0..self.rules.regions.len()).find_map(|region| {
+            let empty = self.empty_cells(region);
+            (1..=size).find_map(|digit| {
+                match empty
+                    .iter()
+                    .filter(|&&(row, col)| self.candidates[row][col] & bit(digit) != 0)
+                    .collect::<Vec<_>>()[..]
+                {
+                    [&cell] => Some((cell, digit, region)),
+                    _ => None,
+                }
+            })
+        })
+    }
+
+    /// A digit no filling of a cage uses is ruled out of its cells.
+    fn cage_combination(&mut self) -> Option<Step> {
+        let rules = self.rules;
+        rules.cages.iter().find_map(|cage| {
+            let removed = self.rule_out_unused(&cage.cells, |digits| {
+                let placed: Vec<u32> = digits.iter().flatten().map(|&d| d as u32).collect();
+                let sum: u32 = placed.iter().sum();
+                sum > cage.sum
+                    || (placed.len() == digits.len() && sum != cage.sum)
+                    || placed
+                        .iter()
+                        .enumerate()
+                        .any(|(i, d)| placed[..i].contains(d))
+            })?;
+            Some(Step {
+                technique: Technique::CageCombination,
+                placement: None,
+                detail: format!(
+                    "the {} cage on {} rules out {}",
+                    cage.sum,
+                    cell_list(&cage.cells),
+                    plural(removed, "candidate")
+                ),
+            })
+        })
+    }
+
+    /// A digit no filling of a constraint's cells allows is ruled out of them.
+    fn constraint_rule(&mut self) -> Option<Step> {
+        let rules = self.rules;
+        let size = self.values.len();
+        rules.constraints.iter().find_map(|constraint| {
+            let cells = constraint.cells();
+            let removed = self.rule_out_unused(&cells, |digits| {
+                constraint.is_broken(size, |cell| {
+                    cells
+                        .iter()
+                        .position(|&other| other == cell)
+                        .and_then(|i| digits[i])
+                })
+            })?;
+            Some(Step {
+                technique: Technique::ConstraintRule,
+                placement: None,
+                detail: format!(
+                    "the {} on {} rules out {}",
+                    constraint_name(constraint),
+                    cell_list(&cells),
+                    plural(removed, "candidate")
+                ),
+            })
+        })
+    }
+
+    /// Rules out of `cells` every candidate that no filling of them uses,
+    /// returning how many went. `broken` tells a partial filling that can't
+    /// be completed; cells sharing a region never repeat a digit. `None` when
+    /// nothing goes, or the fillings are too many to search.
+    fn rule_out_unused(
+        &mut self,
+        cells: &[(usize, usize)],
+        broken: impl Fn(&[Option<u8>]) -> bool,
+    ) -> Option<usize> {
+        if cells
+            .iter()
+            .all(|&(row, col)| self.values[row][col].is_some())
+        {
+            return None;
+        }
+        let shares = |(r1, c1): (usize, usize), (r2, c2): (usize, usize)| {
+            self.cell_regions[r1][c1]
+                .iter()
+                .any(|region| self.cell_regions[r2][c2].contains(region))
+        };
+        let mut fillings = Fillings {
+            options: cells
+                .iter()
+                .map(|&(row, col)| match self.values[row][col] {
+                    Some(digit) => bit(digit),
+                    None => self.candidates[row][col],
+                })
+                .collect(),
+            clash: cells
+                .iter()
+                .map(|&a| cells.iter().map(|&b| a != b && shares(a, b)).collect())
+                .collect(),
+            digits: vec![None; cells.len()],
+            used: vec![0; cells.len()],
+            budget: FILLING_BUDGET,
+        };
+        if !fillings.search(0, &broken) || fillings.used == fillings.options {
+            return None;
+        }
+        let mut removed = 0;
+        for (&(row, col), used) in cells.iter().zip(&fillings.used) {
+            if self.values[row][col].is_none() {
+                removed += (self.candidates[row][col] & !used).count_ones() as usize;
+                self.candidates[row][col] &= used;
+            }
+        }
+        (removed > 0).then_some(removed)
+    }
+
+    /// A digit confined to where one region meets another is ruled out of
+    /// the rest of the other.
+    fn locked_candidates(&mut self) -> Option<Step> {
+        let size = self.values.len() as u8;
+        for region in 0..self.rules.regions.len() {
+            let empty = self.empty_cells(region);
+            for digit in 1..=size {
+                let cells: Vec<_> = empty
+                    .iter()
+                    .copied()
+                    .filter(|&(row, col)| self.candidates[row][col] & bit(digit) != 0)
+                    .collect();
+                let Some(&(row, col)) = cells.first() else {
+                    continue;
+                };
+                for &other in &self.cell_regions[row][col] {
+                    let shared = cells
+                        .iter()
+                        .all(|&(r, c)| self.cell_regions[r][c].contains(&other));
+                    if other == region || !shared {
+                        continue;
+                    }
+                    let removed: Vec<_> = self
+                        .empty_cells(other)
+                        .into_iter()
+                        .filter(|cell| !cells.contains(cell))
+                        .filter(|&(r, c)| self.candidates[r][c] & bit(digit) != 0)
+                        .collect();
+                    if removed.is_empty() {
+                        continue;
+                    }
+                    for &(r, c) in &removed {
+                        self.candidates[r][c] &= !bit(digit);
+                    }
+                    return Some(Step {
+                        technique: Technique::LockedCandidates,
+                        placement: None,
+                        detail: format!(
+                            "{digit} in {} lies in {}, ruling it out of {}",
+                            self.rules.names[region],
+                            self.rules.names[other],
+                            cell_list(&removed)
+                        ),
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// Two cells of a region with the same two candidates hold those digits
+    /// between them.
+    fn naked_pair(&mut self) -> Option<Step> {
+        for region in 0..self.rules.regions.len() {
+            let empty = self.empty_cells(region);
+            for (i, &(r1, c1)) in empty.iter().enumerate() {
+                let mask = self.candidates[r1][c1];
+                if mask.count_ones() != 2 {
+                    continue;
+                }
+                for &(r2, c2) in &empty[i + 1..] {
+                    if self.candidates[r2][c2] != mask {
+                        continue;
+                    }
+                    let pair = [(r1, c1), (r2, c2)];
+                    let removed = self.remove_from(&empty, &pair, mask);
+                    if removed > 0 {
+                        let [a, b] = [mask.trailing_zeros(), 31 - mask.leading_zeros()];
+                        return Some(Step {
+                            technique: Technique::NakedPair,
+                            placement: None,
+                            detail: format!(
+                                "{a} and {b} fill {} of {}, ruling out {}",
+                                cell_list(&pair),
+                                self.rules.names[region],
+                                plural(removed, "candidate")
+                            ),
+                        });
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Two digits with the same two places in a region leave no room there
+    /// for any other candidate.
+    fn hidden_pair(&mut self) -> Option<Step> {
+        let size = self.values.len() as u8;
+        for region in 0..self.rules.regions.len() {
+            let empty = self.empty_cells(region);
+            let places = |digit: u8| -> Vec<(usize, usize)> {
+                empty
+                    .iter()
+                    .copied()
+                    .filter(|&(row, col)| self.candidates[row][col] & bit(digit) != 0)
+                    .collect()
+            };
+            for a in 1..=size {
+                let pair = places(a);
+                if pair.len() != 2 {
+                    continue;
+                }
+                for b in a + 1..=size {
+                    if places(b) != pair {
+                        continue;
+                    }
+                    let keep = bit(a) | bit(b);
+                    let removed: usize = pair
+                        .iter()
+                        .map(|&(row, col)| {
+                            (self.candidates[row][col] & !keep).count_ones() as usize
+                        })
+                        .sum();
+                    if removed == 0 {
+                        continue;
+                    }
+                    for &(row, col) in &pair {
+                        self.candidates[row][col] &= keep;
+                    }
+                    return Some(Step {
+                        technique: Technique::HiddenPair,
+                        placement: None,
+                        detail: format!(
+                            "{a} and {b} only fit {} in {}, ruling out {}",
+                            cell_list(&pair),
+                            self.rules.names[region],
+                            plural(removed, "candidate")
+                        ),
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// Rules the digits in `mask` out of `cells` other than `keep`, returning
+    /// how many candidates went.
+    fn remove_from(
+        &mut self,
+        cells: &[(usize, usize)],
+        keep: &[(usize, usize)],
+        mask: u32,
+    ) -> usize {
+        let mut removed = 0;
+        for &(row, col) in cells.iter().filter(|cell| !keep.contains(cell)) {
+            removed += (self.candidates[row][col] & mask).count_ones() as usize;
+            self.candidates[row][col] &= !mask;
+        }
+        removed
+    }
+
+    /// The first empty cell with the fewest candidates, and how many it has.
+    fn guess(&self) -> Option<((usize, usize), u32)> {
+        let size = self.values.len();
+        (0..size * size)
+            .map(|i| (i / size, i % size))
+            .filter(|&(row, col)| self.values[row][col].is_none())
+            .map(|(row, col)| ((row, col), self.candidates[row][col].count_ones()))
+            .min_by_key(|&(_, options)| options)
+    }
+}
+
+/// A search through the ways of filling a few cells from their options.
+struct Fillings {
+    options: Vec<u32>,
+    /// Which pairs of cells share a region.
+    clash: Vec<Vec<bool>>,
+    digits: Vec<Option<u8>>,
+    /// The digits each cell takes in some filling found so far.
+    used: Vec<u32>,
+    budget: usize,
+}
+
+impl Fillings {
+    /// Fills cell `i` onwards, marking what every complete filling `broken`
+    /// allows as used. False when it stopped early, out of budget or with
+    /// every option already used.
+    fn search(&mut self, i: usize, broken: &impl Fn(&[Option<u8>]) -> bool) -> bool {
+        if i == self.digits.len() {
+            for (used, digit) in self.used.iter_mut().zip(&self.digits) {
+                *used |= digit.map_or(0, bit);
+            }
+            return self.used != self.options;
+        }
+        for digit in digits(self.options[i]) {
+            if self.budget == 0 {
+                return false;
+            }
+            self.budget -= 1;
+            if (0..i).any(|j| self.clash[i][j] && self.digits[j] == Some(digit)) {
+                continue;
+            }
+            self.digits[i] = Some(digit);
+            if !broken(&self.digits) && !self.search(i + 1, broken) {
+                return false;
+            }
+        }
+        self.digits[i] = None;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{history::Move, settings::Settings, tutorial::lessons};
+
+    fn lesson_game(index: usize) -> GameState {
+        let lesson = &lessons()[index];
+        GameState::from_puzzle(
+            crate::state::Difficulty::Easy,
+            Settings::default(),
+            crate::puzzle::GridShape::CLASSIC,
+            Variant::Classic,
+            lesson.puzzle.clone(),
+        )
+    }
+
+    fn fill(state: &mut GameState, cell: (usize, usize), value: u8, hint: bool) {
+        state.moves.push(Move {
+            row: cell.0,
+            col: cell.1,
+            value: Some(value),
+            at_secs: 0,
+            hint,
+        });
+    }
+
+    #[test]
+    fn solve_path_fills_every_empty_cell_with_the_solution() {
+        for index in 0..lessons().len() {
+            let state = lesson_game(index);
+            let analysis = Analysis::of(&state);
+            let empty = state
+                .grid
+                .iter()
+                .flatten()
+                .filter(|cell| cell.value.is_none())
+                .count();
+            let placed: Vec<_> = analysis
+                .steps
+                .iter()
+                .filter_map(|step| step.placement)
+                .collect();
+            assert_eq!(placed.len(), empty);
+            for ((row, col), digit) in placed {
+                assert_eq!(state.solution[row][col], digit);
+            }
+            assert!(analysis.placements.is_empty());
+        }
+    }
+
+    #[test]
+    fn lessons_are_solved_with_the_technique_they_teach() {
+        let first = |index: usize| Analysis::of(&lesson_game(index)).steps[0].clone();
+        assert_eq!(
+            first(0),
+            Step {
+                technique: Technique::FullHouse,
+                placement: Some(((0, 4), 1)),
+                detail: "1 in A5, the last cell of row A".to_string(),
+            }
+        );
+        let counts = Analysis::of(&lesson_game(4)).counts();
+        assert!(counts.iter().all(|&(_, count)| count > 0));
+        assert!(!counts.iter().any(|&(t, _)| t == Technique::Guess));
+    }
+
+    #[test]
+    fn killer_cages_rule_out_candidates_on_the_solve_path() {
+        let settings = Settings {
+            variant: Variant::Killer,
+            ..Settings::default()
+        };
+        let mut state = GameState::with_settings(crate::state::Difficulty::Easy, settings);
+        // With no givens, only the cages can start the solve.
+        for cell in state.grid.iter_mut().flatten() {
+            cell.value = None;
+            cell.given = false;
+        }
+        let analysis = Analysis::of(&state);
+        assert_eq!(analysis.steps[0].technique, Technique::CageCombination);
+        let placed: Vec<_> = analysis
+            .steps
+            .iter()
+            .filter_map(|step| step.placement)
+            .collect();
+        assert_eq!(placed.len(), 81);
+        for ((row, col), digit) in placed {
+            assert_eq!(state.solution[row][col], digit);
+        }
+
+        let (cell, digit) = analysis
+            .steps
+            .iter()
+            .find_map(|step| step.placement)
+            .unwrap();
+        fill(&mut state, cell, digit, false);
+        let analysis = Analysis::of(&state);
+        assert_eq!(analysis.filled_at(cell), Some((1, Filled::Logic)));
+    }
+
+    #[test]
+    fn marks_guesses_hints_and_wrong_entries_in_the_players_order() {
+        let mut state = crate::packs::PackBrowser::campaign().packs[3]
+            .game(0, Settings::default())
+            .unwrap();
+        let givens = state.values();
+        let solution = state.solution.clone();
+        let rules = Rules::of(&state);
+        let digit = |(row, col): (usize, usize)| solution[row][col];
+        let (logical, _) = Analysis::of(&state).steps[0].placement.unwrap();
+        let guessed = (0..81)
+            .map(|i| (i / 9, i % 9))
+            .find(|&cell| {
+                givens[cell.0][cell.1].is_none() && !Solver::new(&givens, &rules).finds(cell)
+            })
+            .expect("an expert puzzle can't be filled in any order");
+        let hinted = (0..81)
+            .map(|i| (i / 9, i % 9))
+            .find(|&cell| givens[cell.0][cell.1].is_none() && ![logical, guessed].contains(&cell))
+            .unwrap();
+
+        fill(&mut state, logical, digit(logical) % 9 + 1, false);
+        fill(&mut state, guessed, digit(guessed), false);
+        fill(&mut state, logical, digit(logical), false);
+        fill(&mut state, hinted, digit(hinted), true);
+
+        let analysis = Analysis::of(&state);
+        assert_eq!(analysis.wrong, 1);
+        assert_eq!(analysis.filled_at(guessed), Some((1, Filled::Guess)));
+        assert_eq!(analysis.filled_at(logical), Some((2, Filled::Logic)));
+        assert_eq!(analysis.filled_at(hinted), Some((3, Filled::Hint)));
+        assert_eq!(analysis.count_filled(Filled::Logic), 1);
+    }
+}
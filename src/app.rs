@@ -1,20 +1,31 @@
-use std::{io, time::{Duration, Instant}};
+use std::{
+    io,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 
 use crossterm::{
-    event::{self, Event},
+    event::{self, DisableFocusChange, EnableFocusChange, Event},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{backend::CrosstermBackend, Terminal};
 
-use crate::{input, state::GameState, ui};
-
-const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(30);
+use crate::{
+    error::PersistError,
+    input,
+    keymap::Keymap,
+    packs::{PackBrowser, PackProgress},
+    settings::Settings,
+    state::GameState,
+    theme::ThemeCatalog,
+    ui,
+};
 
 pub fn run() -> io::Result<()> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
+    execute!(stdout, EnterAlternateScreen, EnableFocusChange)?;
 
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
@@ -22,40 +33,94 @@ pub fn run() -> io::Result<()> {
     let result = run_loop(&mut terminal);
 
     disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    execute!(
+        terminal.backend_mut(),
+        DisableFocusChange,
+        LeaveAlternateScreen
+    )?;
     terminal.show_cursor()?;
 
     result
 }
 
 fn run_loop(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> io::Result<()> {
-    let mut state = GameState::load_or_show_selector()?;
+    let keymap = Keymap::load();
+    let (settings, settings_error) = match Settings::load() {
+        Ok(settings) => (settings, None),
+        Err(e) => (Settings::default(), Some(e)),
+    };
+    let mut state = GameState::load_or_show_selector(settings)?;
+    if let Some(e) = settings_error {
+        state.report(e);
+    }
+    state.themes = ThemeCatalog::load();
+    state.packs = PackBrowser::load();
+    match PackProgress::load() {
+        Ok(progress) => state.packs.progress = progress,
+        Err(e) => state.report(e),
+    }
+    if let Some(path) = std::env::args_os().nth(1).map(PathBuf::from) {
+        match crate::import::load(&path, state.settings.clone()) {
+            Ok(imported) => state.start(imported),
+            Err(source) => state.report(PersistError::Import { path, source }),
+        }
+    }
+    // Surface key binding conflicts right away through the help overlay.
+    state.help_visible = !keymap.problems.is_empty();
     let mut last_autosave = Instant::now();
 
     loop {
         terminal.draw(|frame| match state.screen {
-            crate::state::Screen::LevelSelector => ui::render_selector(frame, &state),
-            crate::state::Screen::Playing => ui::render(frame, &state),
-            crate::state::Screen::Win => ui::render_win(frame, &state),
+            crate::state::Screen::LevelSelector => ui::render_selector(frame, &state, &keymap),
+            crate::state::Screen::Playing | crate::state::Screen::Tutorial => {
+                ui::render(frame, &state, &keymap)
+            }
+            crate::state::Screen::Win => ui::render_win(frame, &state, &keymap),
+            crate::state::Screen::TimeUp => ui::render_time_up(frame, &state, &keymap),
+            crate::state::Screen::Replay => ui::render_replay(frame, &state, &keymap),
+            crate::state::Screen::Analysis => ui::render_analysis(frame, &state, &keymap),
+            crate::state::Screen::Settings => ui::render_settings(frame, &state, &keymap),
+            crate::state::Screen::Slots => ui::render_slots(frame, &state, &keymap),
+            crate::state::Screen::Packs => ui::render_packs(frame, &state, &keymap),
         })?;
 
         if event::poll(Duration::from_millis(50))? {
-            if let Event::Key(key_event) = event::read()? {
-                if let Some(command) = input::command_from_key_event(key_event) {
-                    input::apply_command(&mut state, command);
+            match event::read()? {
+                Event::Key(key_event) if state.browser.name_input.is_some() => {
+                    input::apply_name_key(&mut state, key_event);
+                }
+                Event::Key(key_event) => {
+                    if let Some(command) =
+                        input::command_from_key_event(&keymap, key_event, state.shape)
+                    {
+                        input::apply_command(&mut state, command);
+                    }
                 }
+                Event::FocusLost if state.settings.pause_on_focus_loss => state.pause(),
+                _ => {}
             }
         }
+        state.pause_if_idle();
+        input::check_time_limit(&mut state);
 
-        if last_autosave.elapsed() >= AUTOSAVE_INTERVAL {
+        let autosave_interval = Duration::from_secs(state.settings.autosave_interval_secs);
+        if last_autosave.elapsed() >= autosave_interval {
             if matches!(state.screen, crate::state::Screen::Playing) && !state.game_completed {
-                let _ = state.save_default();
+                if let Err(e) = state.save_default() {
+                    state.report(e);
+                }
             }
             last_autosave = Instant::now();
         }
 
         if state.should_quit {
-            let _ = state.save_default();
+            // Quitting from the selector must not replace the session with its placeholder.
+            if state.has_game() {
+                if let Err(e) = state.save_default() {
+                    // Too late for a toast; the log keeps it.
+                    crate::error::log("ERROR", &e.to_string());
+                }
+            }
             return Ok(());
         }
     }
@@ -0,0 +1,64 @@
+use std::{env, path::PathBuf};
+
+pub fn config_dir() -> Option<PathBuf> {
+    if let Some(xdg_config_home) = env::var_os("XDG_CONFIG_HOME") {
+        return Some(PathBuf::from(xdg_config_home).join("sudokui"));
+    }
+
+    if let Some(home) = env::var_os("HOME") {
+        return Some(PathBuf::from(home).join(".config").join("sudokui"));
+    }
+
+    #[cfg(windows)]
+    if let Some(app_data) = env::var_os("APPDATA") {
+        return Some(PathBuf::from(app_data).join("sudokui"));
+    }
+
+    None
+}
+
+/// Where sessions, save slots and logs live.
+pub fn state_dir() -> Option<PathBuf> {
+    if let Some(xdg_state_home) = env::var_os("XDG_STATE_HOME") {
+        return Some(PathBuf::from(xdg_state_home).join("sudokui"));
+    }
+
+    if let Some(home) = env::var_os("HOME") {
+        return Some(
+            PathBuf::from(home)
+                .join(".local")
+                .join("state")
+                .join("sudokui"),
+        );
+    }
+
+    #[cfg(windows)]
+    if let Some(local_app_data) = env::var_os("LOCALAPPDATA") {
+        return Some(PathBuf::from(local_app_data).join("sudokui"));
+    }
+
+    None
+}
+
+/// Where the leaderboard and pack progress live.
+pub fn data_dir() -> Option<PathBuf> {
+    if let Some(xdg_data_home) = env::var_os("XDG_DATA_HOME") {
+        return Some(PathBuf::from(xdg_data_home).join("sudokui"));
+    }
+
+    if let Some(home) = env::var_os("HOME") {
+        return Some(
+            PathBuf::from(home)
+                .join(".local")
+                .join("share")
+                .join("sudokui"),
+        );
+    }
+
+    #[cfg(windows)]
+    if let Some(local_app_data) = env::var_os("LOCALAPPDATA") {
+        return Some(PathBuf::from(local_app_data).join("sudokui"));
+    }
+
+    None
+}
@@ -0,0 +1,132 @@
+use std::{
+    fmt,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+};
+
+pub const LOG_FILE: &str = "sudokui.log";
+
+/// A failed save, load, export, import, leaderboard or pack progress update,
+/// with the file involved.
+#[derive(Debug)]
+pub enum PersistError {
+    Save { path: PathBuf, source: io::Error },
+    Load { path: PathBuf, source: io::Error },
+    Delete { path: PathBuf, source: io::Error },
+    Leaderboard { path: PathBuf, source: io::Error },
+    Settings { path: PathBuf, source: io::Error },
+    Export { path: PathBuf, source: io::Error },
+    Import { path: PathBuf, source: io::Error },
+    Packs { path: PathBuf, source: io::Error },
+    NoStateDir,
+}
+
+impl PersistError {
+    /// A missing file is the normal first-run case rather than a failure.
+    pub fn is_not_found(&self) -> bool {
+        self.io_error()
+            .is_some_and(|e| e.kind() == io::ErrorKind::NotFound)
+    }
+
+    fn io_error(&self) -> Option<&io::Error> {
+        match self {
+            PersistError::Save { source, .. }
+            | PersistError::Load { source, .. }
+            | PersistError::Delete { source, .. }
+            | PersistError::Leaderboard { source, .. }
+            | PersistError::Settings { source, .. }
+            | PersistError::Export { source, .. }
+            | PersistError::Import { source, .. }
+            | PersistError::Packs { source, .. } => Some(source),
+            PersistError::NoStateDir => None,
+        }
+    }
+}
+
+impl fmt::Display for PersistError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PersistError::Save { path, source } => {
+                write!(f, "Save failed ({}): {source}", path.display())
+            }
+            PersistError::Load { path, source } => {
+                write!(f, "Load failed ({}): {source}", path.display())
+            }
+            PersistError::Delete { path, source } => {
+                write!(f, "Delete failed ({}): {source}", path.display())
+            }
+            PersistError::Leaderboard { path, source } => {
+                write!(f, "Leaderboard failed ({}): {source}", path.display())
+            }
+            PersistError::Settings { path, source } => {
+                write!(f, "Settings file error ({}): {source}", path.display())
+            }
+            PersistError::Export { path, source } => {
+                write!(f, "Export failed ({}): {source}", path.display())
+            }
+            PersistError::Import { path, source } => {
+                write!(f, "Import failed ({}): {source}", path.display())
+            }
+            PersistError::Packs { path, source } => {
+                write!(f, "Pack progress failed ({}): {source}", path.display())
+            }
+            PersistError::NoStateDir => {
+                write!(f, "No state directory; set HOME or XDG_STATE_HOME")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PersistError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.io_error()
+            .map(|e| e as &(dyn std::error::Error + 'static))
+    }
+}
+
+/// Appends a timestamped line to the log file in the state directory.
+/// Logging is best effort: there is nowhere left to report its own failure.
+pub fn log(level: &str, message: &str) {
+    let _ = append_log(level, message);
+}
+
+fn append_log(level: &str, message: &str) -> io::Result<()> {
+    let dir = crate::config::state_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no state directory"))?;
+    fs::create_dir_all(&dir)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(LOG_FILE))?;
+    writeln!(
+        file,
+        "{} {level} {message}",
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_name_the_operation_and_file() {
+        let err = PersistError::Save {
+            path: PathBuf::from("/tmp/session.json"),
+            source: io::Error::new(io::ErrorKind::StorageFull, "disk full"),
+        };
+        assert_eq!(
+            err.to_string(),
+            "Save failed (/tmp/session.json): disk full"
+        );
+        assert!(!err.is_not_found());
+
+        let missing = PersistError::Load {
+            path: PathBuf::from("session.json"),
+            source: io::Error::from(io::ErrorKind::NotFound),
+        };
+        assert!(missing.is_not_found());
+        assert!(std::error::Error::source(&missing).is_some());
+    }
+}
@@ -1,5 +1,20 @@
+use serde::{Deserialize, Serialize};
+
 const HISTORY_CAPACITY: usize = 50;
 
+/// One change to a cell's value, kept for the whole game so it can be replayed.
+/// Unlike `ActionHistory` this is never trimmed or rewound; an undo shows up
+/// as another move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Move {
+    pub row: usize,
+    pub col: usize,
+    pub value: Option<u8>,
+    /// Play time when the move was made.
+    pub at_secs: u64,
+    pub hint: bool,
+}
+
 #[derive(Debug, Clone)]
 pub enum GameAction {
     #[allow(dead_code)]
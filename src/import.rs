@@ -0,0 +1,317 @@
+use std::{fs, io, path::Path};
+
+use serde::Deserialize;
+
+use crate::{
+    puzzle::{Constraint, GridShape, Puzzle, Variant},
+    settings::Settings,
+    state::{Cell, Difficulty, GameMode, GameState},
+};
+
+/// A puzzle typed up by hand: its givens and any constraints, with cells
+/// named by row letter and column number, e.g. `C4`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PuzzleFile {
+    /// Shown in place of the difficulty; the file name when missing.
+    pub title: Option<String>,
+    #[serde(default)]
+    pub size: GridShape,
+    #[serde(default = "default_difficulty")]
+    pub difficulty: Difficulty,
+    /// See `parse_grid`.
+    pub grid: String,
+    /// Each thermometer's cells from the bulb up.
+    #[serde(default)]
+    pub thermo: Vec<Vec<String>>,
+    #[serde(default)]
+    pub arrow: Vec<ArrowSpec>,
+    #[serde(default)]
+    pub white: Vec<[String; 2]>,
+    #[serde(default)]
+    pub black: Vec<[String; 2]>,
+    #[serde(default)]
+    pub x: Vec<[String; 2]>,
+    #[serde(default)]
+    pub v: Vec<[String; 2]>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ArrowSpec {
+    pub circle: String,
+    /// From the circle outwards.
+    pub shaft: Vec<String>,
+}
+
+fn default_difficulty() -> Difficulty {
+    Difficulty::Medium
+}
+
+impl PuzzleFile {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        toml::from_str(source).map_err(|e| e.message().to_string())
+    }
+
+    /// The givens and constraints, with the one solution they allow.
+    pub fn to_puzzle(&self) -> Result<Puzzle, String> {
+        let shape = self.size;
+        let grid = parse_grid(&self.grid, shape)?;
+        let constraints = self.constraints()?;
+        let regions = crate::puzzle::regions(shape, Variant::Classic, &[]);
+        let solution = crate::puzzle::unique_solution(&grid, &regions, &constraints)
+            .map_err(str::to_string)?;
+        Ok(Puzzle {
+            grid,
+            solution,
+            cages: Vec::new(),
+            boxes: Vec::new(),
+            constraints,
+        })
+    }
+
+    /// A new game of this puzzle called `title`, played with `settings`.
+    pub fn to_game(&self, title: String, settings: Settings) -> Result<GameState, String> {
+        let puzzle = self.to_puzzle()?;
+        let mut state = GameState::from_puzzle(
+            self.difficulty,
+            settings,
+            self.size,
+            Variant::Classic,
+            puzzle,
+        );
+        state.title = Some(title);
+        // A time-attack run moves on to generated puzzles, so a single
+        // puzzle from a file is played against the open clock instead.
+        if state.mode == GameMode::TimeAttack {
+            state.mode = GameMode::Standard;
+            state.time_limit = None;
+        }
+        Ok(state)
+    }
+
+    fn constraints(&self) -> Result<Vec<Constraint>, String> {
+        let mut constraints = Vec::new();
+        for names in &self.thermo {
+            constraints.push(Constraint::Thermo(self.cells(names)?));
+        }
+        for arrow in &self.arrow {
+            constraints.push(Constraint::Arrow {
+                circle: parse_cell(&arrow.circle, self.size)?,
+                shaft: self.cells(&arrow.shaft)?,
+            });
+        }
+        let pairs = [
+            (&self.white, Constraint::WhiteDot as fn(_) -> _),
+            (&self.black, Constraint::BlackDot),
+            (&self.x, Constraint::X),
+            (&self.v, Constraint::V),
+        ];
+        for (names, constraint) in pairs {
+            for pair in names {
+                let cells = self.cells(pair)?;
+                constraints.push(constraint([cells[0], cells[1]]));
+            }
+        }
+        for constraint in &constraints {
+            constraint.validate(self.size.size())?;
+        }
+        Ok(constraints)
+    }
+
+    fn cells(&self, names: &[String]) -> Result<Vec<(usize, usize)>, String> {
+        names
+            .iter()
+            .map(|name| parse_cell(name, self.size))
+            .collect()
+    }
+}
+
+pub fn row_label(row: usize) -> char {
+    (b'A' + row as u8) as char
+}
+
+/// The name `parse_cell` reads back, e.g. `C4`.
+pub fn cell_name((row, col): (usize, usize)) -> String {
+    format!("{}{}", row_label(row), col + 1)
+}
+
+/// Givens written one line per row, with `.` for an empty cell. Spaces, `|`
+/// and lines of `-` and `+` are skipped, so an exported result reads back.
+pub fn parse_grid(grid: &str, shape: GridShape) -> Result<Vec<Vec<Cell>>, String> {
+    let size = shape.size();
+    let rows: Vec<Vec<char>> = grid
+        .lines()
+        .map(|line| line.chars().filter(|c| !c.is_whitespace() && *c != '|'))
+        .map(Iterator::collect::<Vec<char>>)
+        .filter(|row| row.iter().any(|c| !matches!(c, '-' | '+')))
+        .collect();
+    if rows.len() != size {
+        return Err(format!(
+            "the grid has {} rows; a {shape} grid needs {size}",
+            rows.len()
+        ));
+    }
+    rows.iter()
+        .enumerate()
+        .map(|(row, symbols)| {
+            if symbols.len() != size {
+                return Err(format!(
+                    "row {} has {} cells; a {shape} grid needs {size}",
+                    row_label(row),
+                    symbols.len()
+                ));
+            }
+            symbols
+                .iter()
+                .map(|&c| match c {
+                    '.' => Ok(Cell::empty()),
+                    _ => shape
+                        .parse_symbol(c)
+                        .map(Cell::given)
+                        .ok_or_else(|| format!("`{c}` is not a digit on a {shape} grid")),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// A cell name such as `C4`, in either case.
+pub fn parse_cell(name: &str, shape: GridShape) -> Result<(usize, usize), String> {
+    let invalid = || format!("`{name}` is not a cell on a {shape} grid");
+    let mut chars = name.trim().chars();
+    let letter = chars.next().ok_or_else(invalid)?.to_ascii_uppercase();
+    let row = (letter as usize).wrapping_sub('A' as usize);
+    let col = chars
+        .as_str()
+        .parse::<usize>()
+        .map_err(|_| invalid())?
+        .wrapping_sub(1);
+    if row < shape.size() && col < shape.size() {
+        Ok((row, col))
+    } else {
+        Err(invalid())
+    }
+}
+
+/// Opens the puzzle file at `path` as a new game played with `settings`.
+pub fn load(path: &Path, settings: Settings) -> io::Result<GameState> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+    let file = PuzzleFile::parse(&fs::read_to_string(path)?).map_err(invalid)?;
+    let title = file.title.clone().unwrap_or_else(|| {
+        path.file_stem()
+            .map_or_else(|| "Imported".to_string(), |s| s.to_string_lossy().into())
+    });
+    file.to_game(title, settings).map_err(invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const THERMO_AND_DOTS: &str = r#"
+title = "Warm-up"
+difficulty = "Hard"
+grid = """
+5 3 . | . 7 . | . . .
+6 . . | 1 9 5 | . . .
+. 9 8 | . . . | . 6 .
+------+-------+------
+8 . . | . 6 . | . . 3
+4 . . | 8 . 3 | . . 1
+7 . . | . 2 . | . . 6
+------+-------+------
+. 6 . | . . . | 2 8 .
+. . . | 4 1 9 | . . 5
+. . . | . 8 . | . 7 9
+"""
+thermo = [["A4", "A5", "A6"]]
+white = [["B1", "B2"]]
+black = [["c5", "c6"]]
+x = [["H1", "H2"]]
+
+[[arrow]]
+circle = "E4"
+shaft = ["E5", "E6"]
+"#;
+
+    #[test]
+    fn reads_givens_and_constraints() {
+        let file = PuzzleFile::parse(THERMO_AND_DOTS).unwrap();
+        assert_eq!(file.title.as_deref(), Some("Warm-up"));
+        assert_eq!(file.difficulty, Difficulty::Hard);
+        let puzzle = file.to_puzzle().unwrap();
+        assert_eq!(puzzle.grid[0][1], Cell::given(3));
+        assert_eq!(puzzle.grid[0][2], Cell::empty());
+        assert_eq!(puzzle.grid[8][8], Cell::given(9));
+        assert_eq!(
+            puzzle.constraints,
+            [
+                Constraint::Thermo(vec![(0, 3), (0, 4), (0, 5)]),
+                Constraint::Arrow {
+                    circle: (4, 3),
+                    shaft: vec![(4, 4), (4, 5)],
+                },
+                Constraint::WhiteDot([(1, 0), (1, 1)]),
+                Constraint::BlackDot([(2, 4), (2, 5)]),
+                Constraint::X([(7, 0), (7, 1)]),
+            ]
+        );
+        assert_eq!(puzzle.solution[0], [5, 3, 4, 6, 7, 8, 9, 1, 2]);
+        assert_eq!(puzzle.solution[8], [3, 4, 5, 2, 8, 6, 1, 7, 9]);
+    }
+
+    #[test]
+    fn explains_what_is_wrong_with_a_file() {
+        let error = |source: &str| {
+            PuzzleFile::parse(source)
+                .and_then(|file| file.to_puzzle())
+                .unwrap_err()
+        };
+        let with = |extra: &str| format!("{extra}\n{THERMO_AND_DOTS}");
+
+        assert_eq!(
+            error(&THERMO_AND_DOTS.replace(". 7 9", "7 9")),
+            "row I has 8 cells; a 9x9 grid needs 9"
+        );
+        assert_eq!(
+            error(&THERMO_AND_DOTS.replace("\"A6\"", "\"B6\"")),
+            "A5 and B6 in a thermometer are not side by side"
+        );
+        assert_eq!(
+            error(&THERMO_AND_DOTS.replace("\"H2\"", "\"J2\"")),
+            "`J2` is not a cell on a 9x9 grid"
+        );
+        assert_eq!(
+            error(&with("v = [[\"A3\", \"B3\"]]")),
+            "the puzzle has no solution"
+        );
+        assert_eq!(
+            error(&with("v = [[\"A1\", \"A2\"]]")),
+            "the givens break the rules"
+        );
+        assert_eq!(
+            error(&THERMO_AND_DOTS.replace("\"E6\"]", "\"E6\", \"E5\"]")),
+            "E5 is in an arrow twice"
+        );
+        assert_eq!(
+            error("grid = \"\"\"\n....\n....\n....\n....\n\"\"\"\nsize = \"4x4\""),
+            "the puzzle has more than one solution"
+        );
+        assert!(error(&with("colour = \"red\"")).contains("unknown field"));
+    }
+
+    #[test]
+    fn load_names_the_game_after_the_file() {
+        let dir = std::env::temp_dir().join(format!("sudokui-import-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("thermo-warm-up.toml");
+        fs::write(&path, THERMO_AND_DOTS.replace("title = \"Warm-up\"", "")).unwrap();
+
+        let state = load(&path, Settings::default()).unwrap();
+        assert_eq!(state.title.as_deref(), Some("thermo-warm-up"));
+        assert_eq!(state.constraints.len(), 5);
+        assert!(!state.is_ranked());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,6 +1,12 @@
-use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
 
-use crate::state::{GameState, InputMode, LastAction, MoveDir};
+use crate::{
+    error::PersistError,
+    keymap::Keymap,
+    puzzle::GridShape,
+    slots::{BrowserMode, MAX_NAME_LEN},
+    state::{DifficultyOption, GameMode, GameState, InputMode, LastAction, MoveDir, Screen},
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Command {
@@ -12,151 +18,373 @@ pub enum Command {
     NewGame,
     ToggleGivens,
     ToggleHelp,
+    TogglePeers,
+    ToggleConflicts,
     ZoomIn,
     ZoomOut,
     Validate,
+    Pause,
     Action(LastAction),
+    DeleteSlot,
+    DuplicateSlot,
     SelectDifficulty,
+    Back,
     Quit,
 }
 
-pub fn command_from_key_event(event: KeyEvent) -> Option<Command> {
+pub fn command_from_key_event(
+    keymap: &Keymap,
+    event: KeyEvent,
+    shape: GridShape,
+) -> Option<Command> {
     if matches!(event.kind, KeyEventKind::Release) {
         return None;
     }
 
+    keymap.command_for(event, shape)
+}
+
+pub fn apply_command(state: &mut GameState, command: Command) {
+    if state.paused && command != Command::Quit {
+        // Any key resumes; it is not applied to the board the player couldn't see.
+        state.paused = false;
+    } else {
+        run_command(state, command);
+    }
+
+    if state.screen == crate::state::Screen::Playing {
+        state.touch();
+    }
+    state.sync_clock();
+}
+
+/// Text entry for a new slot name, which bypasses the keymap.
+pub fn apply_name_key(state: &mut GameState, event: KeyEvent) {
+    if matches!(event.kind, KeyEventKind::Release) {
+        return;
+    }
+    let Some(name) = state.browser.name_input.as_mut() else {
+        return;
+    };
+
     match event.code {
-        KeyCode::Left => Some(Command::Move(MoveDir::Left)),
-        KeyCode::Right => Some(Command::Move(MoveDir::Right)),
-        KeyCode::Up => Some(Command::Move(MoveDir::Up)),
-        KeyCode::Down => Some(Command::Move(MoveDir::Down)),
-        // Match the ui-mock.md footer: "Arrows/HJKL Move".
-        KeyCode::Char('H') => Some(Command::Move(MoveDir::Left)),
-        KeyCode::Char('L') => Some(Command::Move(MoveDir::Right)),
-        KeyCode::Char('K') => Some(Command::Move(MoveDir::Up)),
-        KeyCode::Char('J') => Some(Command::Move(MoveDir::Down)),
-        // Selector navigation (can be used on level selector)
-        KeyCode::Char('P') => Some(Command::MoveSelectorUp),
-        KeyCode::Char('N') => Some(Command::MoveSelectorDown),
-        // Enter to select difficulty
-        KeyCode::Enter => Some(Command::SelectDifficulty),
-        KeyCode::Char('n') if event.modifiers.contains(KeyModifiers::CONTROL) => {
-            Some(Command::NewGame)
+        KeyCode::Char(c) if name.chars().count() < MAX_NAME_LEN => name.push(c),
+        KeyCode::Backspace => {
+            name.pop();
         }
-        KeyCode::Char('n') => Some(Command::ToggleNotes),
-        KeyCode::Char('g') => Some(Command::ToggleGivens),
-        KeyCode::Char('?') => Some(Command::ToggleHelp),
-        KeyCode::Char('+') => Some(Command::ZoomIn),
-        KeyCode::Char('-') => Some(Command::ZoomOut),
-        KeyCode::Char('v') => Some(Command::Validate),
-        KeyCode::Char('u') => Some(Command::Action(LastAction::Undo)),
-        KeyCode::Char('r') => Some(Command::Action(LastAction::Redo)),
-        KeyCode::Char('h') if event.modifiers.contains(KeyModifiers::CONTROL) => None,
-        KeyCode::Char('h') => Some(Command::Action(LastAction::Hint)),
-        KeyCode::Char('c') => Some(Command::Action(LastAction::Clear)),
-        KeyCode::Char('s') => Some(Command::Action(LastAction::Save)),
-        KeyCode::Char('o') => Some(Command::Action(LastAction::Load)),
-        KeyCode::Char('q') => Some(Command::Quit),
-        KeyCode::Char(d) if d.is_ascii_digit() => {
-            let digit = d.to_digit(10)? as u8;
-            if (1..=9).contains(&digit) {
-                Some(Command::Digit(digit))
-            } else {
-                None
+        KeyCode::Esc => state.browser.name_input = None,
+        KeyCode::Enter => {
+            if let Err(e) = state.confirm_slot_name() {
+                state.report(e);
             }
         }
-        _ => None,
+        _ => {}
     }
 }
 
-pub fn apply_command(state: &mut GameState, command: Command) {
+fn record_win(state: &GameState) -> Result<(), PersistError> {
+    let mut leaderboard = crate::leaderboard::Leaderboard::load()?;
+    leaderboard.add_entry(crate::leaderboard::LeaderboardEntry {
+        difficulty: state.difficulty,
+        shape: state.shape,
+        variant: state.variant,
+        time_seconds: state.clock.elapsed().as_secs(),
+        completed_at: chrono::Local::now().format("%Y-%m-%d").to_string(),
+        rules: Some(state.rules()),
+        mode: state.mode,
+        time_limit_secs: state.time_limit.map(|limit| limit.as_secs()),
+        solved: state.run_solved.max(1),
+    });
+    leaderboard.save()
+}
+
+/// Ends a countdown or time-attack game whose clock has run out, entering a
+/// time-attack run's score on the leaderboard.
+pub fn check_time_limit(state: &mut GameState) {
+    if !state.is_out_of_time() {
+        return;
+    }
+    state.game_completed = true;
+    if state.mode == GameMode::TimeAttack && state.run_solved > 0 && state.is_ranked() {
+        if let Err(e) = record_win(state) {
+            state.report(e);
+        }
+    }
+    state.open_time_up();
+}
+
+fn apply_slots_command(state: &mut GameState, command: Command) {
+    let result = match command {
+        Command::Move(MoveDir::Up) | Command::MoveSelectorUp => {
+            state.browser.move_cursor(false);
+            Ok(())
+        }
+        Command::Move(MoveDir::Down) | Command::MoveSelectorDown => {
+            state.browser.move_cursor(true);
+            Ok(())
+        }
+        Command::SelectDifficulty => state.confirm_slot(),
+        Command::DeleteSlot => state.delete_selected_slot(),
+        Command::DuplicateSlot => state.duplicate_selected_slot(),
+        Command::Back => {
+            state.close_slots();
+            Ok(())
+        }
+        Command::ToggleHelp => {
+            state.help_visible = !state.help_visible;
+            Ok(())
+        }
+        Command::Quit => {
+            state.should_quit = true;
+            Ok(())
+        }
+        _ => Ok(()),
+    };
+    if let Err(e) = result {
+        state.report(e);
+    }
+}
+
+fn apply_packs_command(state: &mut GameState, command: Command) {
+    let result = match command {
+        Command::Move(MoveDir::Up) | Command::MoveSelectorUp => {
+            state.packs.move_cursor(false);
+            Ok(())
+        }
+        Command::Move(MoveDir::Down) | Command::MoveSelectorDown => {
+            state.packs.move_cursor(true);
+            Ok(())
+        }
+        Command::SelectDifficulty => state.confirm_pack(),
+        Command::Back if state.help_visible => {
+            state.help_visible = false;
+            Ok(())
+        }
+        Command::Back => {
+            state.close_packs();
+            Ok(())
+        }
+        Command::ToggleHelp => {
+            state.help_visible = !state.help_visible;
+            Ok(())
+        }
+        Command::Quit => {
+            state.should_quit = true;
+            Ok(())
+        }
+        _ => Ok(()),
+    };
+    if let Err(e) = result {
+        state.report(e);
+    }
+}
+
+fn apply_win_command(state: &mut GameState, command: Command) {
+    match command {
+        Command::Move(MoveDir::Up) | Command::MoveSelectorUp => state.move_win_selection(false),
+        Command::Move(MoveDir::Down) | Command::MoveSelectorDown => {
+            state.move_win_selection(true);
+        }
+        Command::SelectDifficulty => {
+            if let Err(e) = state.confirm_win_action() {
+                state.report(e);
+            }
+        }
+        Command::NewGame => state.new_game(state.difficulty),
+        Command::Back => state.screen = Screen::LevelSelector,
+        Command::ToggleHelp => state.help_visible = !state.help_visible,
+        Command::Quit => state.should_quit = true,
+        _ => {}
+    }
+}
+
+fn apply_replay_command(state: &mut GameState, command: Command) {
+    match command {
+        Command::Move(MoveDir::Left | MoveDir::Up) | Command::MoveSelectorUp => {
+            state.step_replay(false);
+        }
+        Command::Move(MoveDir::Right | MoveDir::Down) | Command::MoveSelectorDown => {
+            state.step_replay(true);
+        }
+        Command::ZoomIn => state.ui_zoom = state.ui_zoom.zoom_in(),
+        Command::ZoomOut => state.ui_zoom = state.ui_zoom.zoom_out(),
+        Command::SelectDifficulty | Command::Back => state.screen = Screen::Win,
+        Command::Quit => state.should_quit = true,
+        _ => {}
+    }
+}
+
+fn apply_analysis_command(state: &mut GameState, command: Command) {
+    match command {
+        Command::Move(MoveDir::Up) | Command::MoveSelectorUp => state.step_analysis(false),
+        Command::Move(MoveDir::Down) | Command::MoveSelectorDown => state.step_analysis(true),
+        Command::SelectDifficulty | Command::Back => state.screen = Screen::Win,
+        Command::Quit => state.should_quit = true,
+        _ => {}
+    }
+}
+
+/// Handles the commands a lesson treats differently from a game, returning
+/// whether `command` was used up. Moving, notes, zoom, help and the
+/// highlighting toggles are left to `run_command`.
+fn apply_tutorial_command(state: &mut GameState, command: Command) -> bool {
+    let done = state.tutorial.is_some_and(|t| t.current().is_none());
+    match command {
+        Command::Digit(digit) => state.enter_lesson_digit(digit),
+        Command::SelectDifficulty if done => state.finish_lesson(),
+        Command::Back if !state.help_visible => state.screen = Screen::LevelSelector,
+        Command::Action(LastAction::Clear) if state.selected_cell().value.is_none() => {
+            state.clear_selected();
+        }
+        Command::Move(_)
+        | Command::ToggleNotes
+        | Command::ToggleGivens
+        | Command::ToggleHelp
+        | Command::TogglePeers
+        | Command::ToggleConflicts
+        | Command::ZoomIn
+        | Command::ZoomOut
+        | Command::Back
+        | Command::Quit => return false,
+        _ => {}
+    }
+    true
+}
+
+fn run_command(state: &mut GameState, command: Command) {
+    match state.screen {
+        Screen::Slots => return apply_slots_command(state, command),
+        Screen::Packs => return apply_packs_command(state, command),
+        Screen::Win | Screen::TimeUp => return apply_win_command(state, command),
+        Screen::Replay => return apply_replay_command(state, command),
+        Screen::Analysis => return apply_analysis_command(state, command),
+        _ => {}
+    }
+    if state.screen == Screen::Tutorial && apply_tutorial_command(state, command) {
+        return;
+    }
+
     match command {
         Command::Move(dir) => {
             use crate::state::Screen;
-            if state.screen == Screen::LevelSelector {
-                match dir {
-                    MoveDir::Up => {
-                        use crate::state::DifficultyOption;
-                        state.selector_selection = match state.selector_selection {
-                            DifficultyOption::Easy => DifficultyOption::Expert,
-                            DifficultyOption::Medium => DifficultyOption::Easy,
-                            DifficultyOption::Hard => DifficultyOption::Medium,
-                            DifficultyOption::Expert => DifficultyOption::Hard,
-                            DifficultyOption::Resume => DifficultyOption::Expert,
-                        };
+            match state.screen {
+                Screen::LevelSelector => match dir {
+                    MoveDir::Up => state.move_selector(false),
+                    MoveDir::Down => state.move_selector(true),
+                    MoveDir::Left | MoveDir::Right
+                        if state.selector_selection == DifficultyOption::GridSize =>
+                    {
+                        if let Err(e) = state.cycle_grid_shape(dir == MoveDir::Right) {
+                            state.report(e);
+                        }
+                    }
+                    MoveDir::Left | MoveDir::Right
+                        if state.selector_selection == DifficultyOption::Variant =>
+                    {
+                        if let Err(e) = state.cycle_variant(dir == MoveDir::Right) {
+                            state.report(e);
+                        }
+                    }
+                    MoveDir::Left | MoveDir::Right
+                        if state.selector_selection == DifficultyOption::Mode =>
+                    {
+                        if let Err(e) = state.cycle_mode(dir == MoveDir::Right) {
+                            state.report(e);
+                        }
                     }
-                    MoveDir::Down => {
-                        use crate::state::DifficultyOption;
-                        state.selector_selection = match state.selector_selection {
-                            DifficultyOption::Easy => DifficultyOption::Medium,
-                            DifficultyOption::Medium => DifficultyOption::Hard,
-                            DifficultyOption::Hard => DifficultyOption::Expert,
-                            DifficultyOption::Expert => DifficultyOption::Easy,
-                            DifficultyOption::Resume => DifficultyOption::Easy,
-                        };
+                    MoveDir::Left | MoveDir::Right
+                        if state.selector_selection == DifficultyOption::Tutorial =>
+                    {
+                        state.cycle_lesson(dir == MoveDir::Right);
                     }
                     _ => {}
-                }
-            } else {
-                state.move_selection(dir);
+                },
+                Screen::Settings => match dir {
+                    MoveDir::Up => state.settings_field = state.settings_field.prev(),
+                    MoveDir::Down => state.settings_field = state.settings_field.next(),
+                    MoveDir::Left => state.adjust_setting(false),
+                    MoveDir::Right => state.adjust_setting(true),
+                },
+                _ => state.move_selection(dir),
             }
         }
         Command::MoveSelectorUp => {
-            use crate::state::{DifficultyOption, Screen};
-            if state.screen == Screen::LevelSelector {
-                state.selector_selection = match state.selector_selection {
-                    DifficultyOption::Easy => DifficultyOption::Expert,
-                    DifficultyOption::Medium => DifficultyOption::Easy,
-                    DifficultyOption::Hard => DifficultyOption::Medium,
-                    DifficultyOption::Expert => DifficultyOption::Hard,
-                    DifficultyOption::Resume => DifficultyOption::Expert,
-                };
+            use crate::state::Screen;
+            match state.screen {
+                Screen::LevelSelector => state.move_selector(false),
+                Screen::Settings => state.settings_field = state.settings_field.prev(),
+                _ => {}
             }
         }
         Command::MoveSelectorDown => {
-            use crate::state::{DifficultyOption, Screen};
-            if state.screen == Screen::LevelSelector {
-                state.selector_selection = match state.selector_selection {
-                    DifficultyOption::Easy => DifficultyOption::Medium,
-                    DifficultyOption::Medium => DifficultyOption::Hard,
-                    DifficultyOption::Hard => DifficultyOption::Expert,
-                    DifficultyOption::Expert => DifficultyOption::Easy,
-                    DifficultyOption::Resume => DifficultyOption::Easy,
-                };
+            use crate::state::Screen;
+            match state.screen {
+                Screen::LevelSelector => state.move_selector(true),
+                Screen::Settings => state.settings_field = state.settings_field.next(),
+                _ => {}
             }
         }
         Command::SelectDifficulty => {
-            use crate::state::{Difficulty, DifficultyOption, Screen};
-            if state.screen == Screen::LevelSelector {
+            use crate::state::{Difficulty, Screen};
+            if state.screen == Screen::Settings {
+                if let Err(e) = state.close_settings(true) {
+                    state.report(e);
+                }
+            } else if state.screen == Screen::LevelSelector {
                 match state.selector_selection {
-                    DifficultyOption::Resume => {
-                        if let Ok(loaded) = GameState::load_default() {
-                            *state = loaded;
+                    DifficultyOption::Resume => match GameState::load_default() {
+                        Ok(loaded) => {
+                            state.resume_from(loaded);
                             state.screen = Screen::Playing;
                         }
-                    }
+                        Err(e) => state.report(e),
+                    },
                     DifficultyOption::Easy => state.new_game(Difficulty::Easy),
                     DifficultyOption::Medium => state.new_game(Difficulty::Medium),
                     DifficultyOption::Hard => state.new_game(Difficulty::Hard),
                     DifficultyOption::Expert => state.new_game(Difficulty::Expert),
+                    DifficultyOption::GridSize => {
+                        if let Err(e) = state.cycle_grid_shape(true) {
+                            state.report(e);
+                        }
+                    }
+                    DifficultyOption::Variant => {
+                        if let Err(e) = state.cycle_variant(true) {
+                            state.report(e);
+                        }
+                    }
+                    DifficultyOption::Mode => {
+                        if let Err(e) = state.cycle_mode(true) {
+                            state.report(e);
+                        }
+                    }
+                    DifficultyOption::ContinuePack => {
+                        if let Err(e) = state.continue_pack() {
+                            state.report(e);
+                        }
+                    }
+                    DifficultyOption::Packs => state.open_packs(),
+                    DifficultyOption::Tutorial => state.start_lesson(state.lesson_choice),
+                    DifficultyOption::Settings => state.open_settings(),
                 }
             }
         }
+        Command::Back => {
+            use crate::state::Screen;
+            if state.screen == Screen::Settings {
+                // Nothing is written when cancelling.
+                let _ = state.close_settings(false);
+            } else {
+                state.help_visible = false;
+            }
+        }
         Command::Digit(digit) => {
+            let before = state.values();
             state.enter_digit(digit);
             use crate::state::Screen;
-            if state.screen == Screen::Playing && state.check_win() {
-                state.game_completed = true;
-                if let Ok(mut leaderboard) = crate::leaderboard::Leaderboard::load() {
-                    let entry = crate::leaderboard::LeaderboardEntry {
-                        difficulty: state.difficulty,
-                        time_seconds: state.started_at.elapsed().as_secs(),
-                        completed_at: chrono::Local::now().format("%Y-%m-%d").to_string(),
-                    };
-                    leaderboard.add_entry(entry);
-                    let _ = leaderboard.save();
-                }
-                state.screen = Screen::Win;
+            if state.screen == Screen::Playing {
+                state.log_moves_since(&before, false);
+                finish_if_won(state);
             }
         }
         Command::ToggleNotes => {
@@ -168,10 +396,20 @@ pub fn apply_command(state: &mut GameState, command: Command) {
         Command::NewGame => state.new_game(state.difficulty),
         Command::ToggleGivens => state.show_givens = !state.show_givens,
         Command::ToggleHelp => state.help_visible = !state.help_visible,
+        // Session toggles; the defaults live on the settings screen.
+        Command::TogglePeers => {
+            state.settings.highlight_peers = !state.settings.highlight_peers;
+        }
+        Command::ToggleConflicts => {
+            state.settings.highlight_conflicts = !state.settings.highlight_conflicts;
+        }
         Command::ZoomIn => state.ui_zoom = state.ui_zoom.zoom_in(),
         Command::ZoomOut => state.ui_zoom = state.ui_zoom.zoom_out(),
         Command::Validate => state.validate_and_count_mistakes(),
+        Command::Pause => state.pause(),
+        Command::DeleteSlot | Command::DuplicateSlot => {}
         Command::Action(action) => {
+            let before = state.values();
             state.last_action = Some(action);
             if action == LastAction::Clear {
                 state.clear_selected();
@@ -183,24 +421,53 @@ pub fn apply_command(state: &mut GameState, command: Command) {
                 state.redo();
             }
             if action == LastAction::Hint
-                && state.hints_left > 0
+                && (state.hints_left > 0 || state.mode == GameMode::Zen)
                 && crate::puzzle::apply_hint(state)
+                && state.mode != GameMode::Zen
             {
                 state.hints_left -= 1;
             }
-            if action == LastAction::Save {
-                let _ = state.save_default();
-            }
-            if action == LastAction::Load {
-                if let Ok(loaded) = GameState::load_default() {
-                    *state = loaded;
+            if state.screen == Screen::Playing {
+                state.log_moves_since(&before, action == LastAction::Hint);
+                if action == LastAction::Hint {
+                    finish_if_won(state);
                 }
             }
+            if action == LastAction::Save && state.screen == Screen::Playing {
+                state.open_slots(BrowserMode::Save);
+            }
+            if action == LastAction::Load
+                && matches!(state.screen, Screen::Playing | Screen::LevelSelector)
+            {
+                state.open_slots(BrowserMode::Load);
+            }
         }
         Command::Quit => state.should_quit = true,
     }
 }
 
+/// Ends the game once the grid is solved, or deals the next puzzle of a
+/// time-attack run.
+fn finish_if_won(state: &mut GameState) {
+    if !state.check_win() {
+        return;
+    }
+    if state.mode == GameMode::TimeAttack {
+        state.next_in_run();
+        return;
+    }
+    state.game_completed = true;
+    if state.is_ranked() {
+        if let Err(e) = record_win(state) {
+            state.report(e);
+        }
+    }
+    if let Err(e) = state.record_pack_win() {
+        state.report(e);
+    }
+    state.open_win();
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -217,36 +484,52 @@ mod tests {
     #[test]
     fn maps_navigation_shortcuts() {
         assert_eq!(
-            command_from_key_event(key(KeyCode::Left)),
+            command_from_key_event(&Keymap::default(), key(KeyCode::Left), GridShape::CLASSIC),
             Some(Command::Move(MoveDir::Left))
         );
         assert_eq!(
-            command_from_key_event(key(KeyCode::Right)),
+            command_from_key_event(&Keymap::default(), key(KeyCode::Right), GridShape::CLASSIC),
             Some(Command::Move(MoveDir::Right))
         );
         assert_eq!(
-            command_from_key_event(key(KeyCode::Up)),
+            command_from_key_event(&Keymap::default(), key(KeyCode::Up), GridShape::CLASSIC),
             Some(Command::Move(MoveDir::Up))
         );
         assert_eq!(
-            command_from_key_event(key(KeyCode::Down)),
+            command_from_key_event(&Keymap::default(), key(KeyCode::Down), GridShape::CLASSIC),
             Some(Command::Move(MoveDir::Down))
         );
 
         assert_eq!(
-            command_from_key_event(key(KeyCode::Char('H'))),
+            command_from_key_event(
+                &Keymap::default(),
+                key(KeyCode::Char('H')),
+                GridShape::CLASSIC
+            ),
             Some(Command::Move(MoveDir::Left))
         );
         assert_eq!(
-            command_from_key_event(key(KeyCode::Char('J'))),
+            command_from_key_event(
+                &Keymap::default(),
+                key(KeyCode::Char('J')),
+                GridShape::CLASSIC
+            ),
             Some(Command::Move(MoveDir::Down))
         );
         assert_eq!(
-            command_from_key_event(key(KeyCode::Char('K'))),
+            command_from_key_event(
+                &Keymap::default(),
+                key(KeyCode::Char('K')),
+                GridShape::CLASSIC
+            ),
             Some(Command::Move(MoveDir::Up))
         );
         assert_eq!(
-            command_from_key_event(key(KeyCode::Char('L'))),
+            command_from_key_event(
+                &Keymap::default(),
+                key(KeyCode::Char('L')),
+                GridShape::CLASSIC
+            ),
             Some(Command::Move(MoveDir::Right))
         );
     }
@@ -254,31 +537,59 @@ mod tests {
     #[test]
     fn maps_action_shortcuts() {
         assert_eq!(
-            command_from_key_event(key(KeyCode::Char('u'))),
+            command_from_key_event(
+                &Keymap::default(),
+                key(KeyCode::Char('u')),
+                GridShape::CLASSIC
+            ),
             Some(Command::Action(LastAction::Undo))
         );
         assert_eq!(
-            command_from_key_event(key(KeyCode::Char('r'))),
+            command_from_key_event(
+                &Keymap::default(),
+                key(KeyCode::Char('r')),
+                GridShape::CLASSIC
+            ),
             Some(Command::Action(LastAction::Redo))
         );
         assert_eq!(
-            command_from_key_event(key(KeyCode::Char('h'))),
+            command_from_key_event(
+                &Keymap::default(),
+                key(KeyCode::Char('h')),
+                GridShape::CLASSIC
+            ),
             Some(Command::Action(LastAction::Hint))
         );
         assert_eq!(
-            command_from_key_event(key(KeyCode::Char('c'))),
+            command_from_key_event(
+                &Keymap::default(),
+                key(KeyCode::Char('c')),
+                GridShape::CLASSIC
+            ),
             Some(Command::Action(LastAction::Clear))
         );
         assert_eq!(
-            command_from_key_event(key(KeyCode::Char('s'))),
+            command_from_key_event(
+                &Keymap::default(),
+                key(KeyCode::Char('s')),
+                GridShape::CLASSIC
+            ),
             Some(Command::Action(LastAction::Save))
         );
         assert_eq!(
-            command_from_key_event(key(KeyCode::Char('o'))),
+            command_from_key_event(
+                &Keymap::default(),
+                key(KeyCode::Char('o')),
+                GridShape::CLASSIC
+            ),
             Some(Command::Action(LastAction::Load))
         );
         assert_eq!(
-            command_from_key_event(key(KeyCode::Char('q'))),
+            command_from_key_event(
+                &Keymap::default(),
+                key(KeyCode::Char('q')),
+                GridShape::CLASSIC
+            ),
             Some(Command::Quit)
         );
     }
@@ -286,41 +597,84 @@ mod tests {
     #[test]
     fn maps_digit_entry_and_toggles() {
         assert_eq!(
-            command_from_key_event(key(KeyCode::Char('1'))),
+            command_from_key_event(
+                &Keymap::default(),
+                key(KeyCode::Char('1')),
+                GridShape::CLASSIC
+            ),
             Some(Command::Digit(1))
         );
         assert_eq!(
-            command_from_key_event(key(KeyCode::Char('9'))),
+            command_from_key_event(
+                &Keymap::default(),
+                key(KeyCode::Char('9')),
+                GridShape::CLASSIC
+            ),
             Some(Command::Digit(9))
         );
-        assert_eq!(command_from_key_event(key(KeyCode::Char('0'))), None);
+        assert_eq!(
+            command_from_key_event(
+                &Keymap::default(),
+                key(KeyCode::Char('0')),
+                GridShape::CLASSIC
+            ),
+            None
+        );
 
         assert_eq!(
-            command_from_key_event(key(KeyCode::Char('n'))),
+            command_from_key_event(
+                &Keymap::default(),
+                key(KeyCode::Char('n')),
+                GridShape::CLASSIC
+            ),
             Some(Command::ToggleNotes)
         );
         assert_eq!(
-            command_from_key_event(key_mod(KeyCode::Char('n'), KeyModifiers::CONTROL)),
+            command_from_key_event(
+                &Keymap::default(),
+                key_mod(KeyCode::Char('n'), KeyModifiers::CONTROL),
+                GridShape::CLASSIC
+            ),
             Some(Command::NewGame)
         );
         assert_eq!(
-            command_from_key_event(key(KeyCode::Char('g'))),
+            command_from_key_event(
+                &Keymap::default(),
+                key(KeyCode::Char('g')),
+                GridShape::CLASSIC
+            ),
             Some(Command::ToggleGivens)
         );
         assert_eq!(
-            command_from_key_event(key(KeyCode::Char('?'))),
+            command_from_key_event(
+                &Keymap::default(),
+                key(KeyCode::Char('?')),
+                GridShape::CLASSIC
+            ),
             Some(Command::ToggleHelp)
         );
         assert_eq!(
-            command_from_key_event(key(KeyCode::Char('v'))),
+            command_from_key_event(
+                &Keymap::default(),
+                key(KeyCode::Char('v')),
+                GridShape::CLASSIC
+            ),
             Some(Command::Validate)
         );
         assert_eq!(
-            command_from_key_event(key(KeyCode::Char('+'))),
+            command_from_key_event(
+                &Keymap::default(),
+                key(KeyCode::Char('+')),
+                GridShape::CLASSIC
+            ),
             Some(Command::ZoomIn)
         );
         assert_eq!(
-            command_from_key_event(key(KeyCode::Char('-'))),
+            command_from_key_event(
+                &Keymap::default(),
+                key(KeyCode::Char('-')),
+                GridShape::CLASSIC
+            ),
             Some(Command::ZoomOut)
         );
     }
@@ -354,4 +708,210 @@ mod tests {
         assert_eq!(state.selection.row, 8);
         assert_eq!(state.selection.col, 8);
     }
+
+    #[test]
+    fn settings_screen_edits_a_draft_until_confirmed() {
+        use crate::state::{DifficultyOption, Screen};
+
+        let mut state = GameState::new(crate::state::Difficulty::Easy);
+        state.selector_selection = DifficultyOption::Easy;
+        apply_command(&mut state, Command::Move(MoveDir::Up));
+        assert_eq!(state.selector_selection, DifficultyOption::Settings);
+
+        apply_command(&mut state, Command::SelectDifficulty);
+        assert_eq!(state.screen, Screen::Settings);
+
+        apply_command(&mut state, Command::Move(MoveDir::Right));
+        assert_eq!(
+            state.settings_draft.mistakes_max,
+            state.settings.mistakes_max + 1
+        );
+
+        apply_command(&mut state, Command::Back);
+        assert_eq!(state.screen, Screen::LevelSelector);
+        assert_eq!(state.settings, crate::settings::Settings::default());
+    }
+
+    #[test]
+    fn pause_stops_the_clock_and_swallows_the_resuming_key() {
+        use crate::state::{Difficulty, Screen, Selection};
+        use std::time::{Duration, SystemTime};
+
+        let mut state = GameState::new(Difficulty::Easy);
+        state.new_game(Difficulty::Easy);
+        apply_command(&mut state, Command::Move(MoveDir::Left));
+        assert_eq!(state.screen, Screen::Playing);
+
+        apply_command(&mut state, Command::Pause);
+        assert!(state.paused);
+        let frozen = state.clock.elapsed();
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(state.clock.elapsed(), frozen);
+
+        let (row, col) = (0..81)
+            .map(|i| (i / 9, i % 9))
+            .find(|&(r, c)| !state.grid[r][c].given)
+            .unwrap();
+        state.selection = Selection { row, col };
+        apply_command(&mut state, Command::Digit(5));
+        assert!(!state.paused);
+        assert_eq!(state.grid[row][col].value, None);
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(state.clock.elapsed() > frozen);
+
+        state.last_played_at = SystemTime::now() - Duration::from_secs(600);
+        state.pause_if_idle();
+        assert!(state.paused);
+    }
+
+    #[test]
+    fn a_hint_that_completes_the_grid_wins_the_game() {
+        use crate::state::{Difficulty, Screen, Selection};
+
+        let settings = crate::settings::Settings {
+            mode: GameMode::Zen,
+            ..crate::settings::Settings::default()
+        };
+        let mut state = GameState::with_settings(Difficulty::Easy, settings);
+        state.new_game(Difficulty::Easy);
+        let mut empty = (0..81)
+            .map(|i| (i / 9, i % 9))
+            .filter(|&(r, c)| state.grid[r][c].value.is_none());
+        let (row, col) = empty.next().unwrap();
+        for (r, c) in empty.collect::<Vec<_>>() {
+            state.grid[r][c].value = Some(state.solution[r][c]);
+        }
+        state.selection = Selection { row, col };
+
+        apply_command(&mut state, Command::Action(LastAction::Hint));
+        assert_eq!(state.grid[row][col].value, Some(state.solution[row][col]));
+        assert!(state.game_completed);
+        assert_eq!(state.screen, Screen::Win);
+    }
+
+    #[test]
+    fn zen_games_neither_charge_mistakes_nor_run_out_of_hints() {
+        use crate::state::{Difficulty, Selection};
+
+        let settings = crate::settings::Settings {
+            mode: GameMode::Zen,
+            ..crate::settings::Settings::default()
+        };
+        let mut state = GameState::with_settings(Difficulty::Easy, settings);
+        state.new_game(Difficulty::Easy);
+        assert!(!state.is_ranked());
+        assert_eq!(state.time_limit, None);
+
+        let (row, col) = (0..81)
+            .map(|i| (i / 9, i % 9))
+            .find(|&(r, c)| !state.grid[r][c].given)
+            .unwrap();
+        state.selection = Selection { row, col };
+        let wrong = state.solution[row][col] % 9 + 1;
+        apply_command(&mut state, Command::Digit(wrong));
+        apply_command(&mut state, Command::Validate);
+        assert!(state.grid[row][col].wrong);
+        assert_eq!(state.mistakes, 0);
+        assert!(!state.max_mistakes_warning);
+
+        state.hints_left = 0;
+        for _ in 0..3 {
+            let (row, col) = (0..81)
+                .map(|i| (i / 9, i % 9))
+                .find(|&(r, c)| state.grid[r][c].value.is_none())
+                .unwrap();
+            state.selection = Selection { row, col };
+            apply_command(&mut state, Command::Action(LastAction::Hint));
+            assert_eq!(state.grid[row][col].value, Some(state.solution[row][col]));
+        }
+        assert_eq!(state.hints_left, 0);
+        assert_eq!(state.hints_used(), 3);
+    }
+
+    #[test]
+    fn lessons_only_take_the_placement_each_step_asks_for() {
+        use crate::state::{Difficulty, Screen, Selection};
+
+        let mut state = GameState::new(Difficulty::Easy);
+        state.selector_selection = DifficultyOption::Tutorial;
+        apply_command(&mut state, Command::Move(MoveDir::Right));
+        apply_command(&mut state, Command::SelectDifficulty);
+        assert_eq!(state.screen, Screen::Tutorial);
+        assert_eq!(state.title.as_deref(), Some("Naked single"));
+        assert!(!state.is_ranked());
+        // F8, the cell the first step is about, is selected.
+        assert_eq!(state.selection, Selection { row: 5, col: 7 });
+
+        apply_command(&mut state, Command::Digit(4));
+        assert_eq!(state.grid[5][7].value, None);
+        assert_eq!(
+            state.active_toast().map(|t| t.message.as_str()),
+            Some("4 doesn't belong in F8; read the lesson again")
+        );
+        assert_eq!(state.mistakes, 0);
+
+        apply_command(&mut state, Command::Move(MoveDir::Up));
+        apply_command(&mut state, Command::Digit(2));
+        assert_eq!(state.grid[4][7].value, None);
+        apply_command(&mut state, Command::ToggleNotes);
+        apply_command(&mut state, Command::Digit(2));
+        assert_eq!(state.grid[4][7].candidates(), [2]);
+        apply_command(&mut state, Command::ToggleNotes);
+        apply_command(&mut state, Command::Action(LastAction::Hint));
+        assert_eq!(state.grid[4][7].value, None);
+
+        apply_command(&mut state, Command::Move(MoveDir::Down));
+        apply_command(&mut state, Command::Digit(5));
+        assert_eq!(state.grid[5][7].value, Some(5));
+        assert_eq!(state.tutorial.map(|t| t.step), Some(1));
+        assert_eq!(state.selection, Selection { row: 4, col: 7 });
+
+        apply_command(&mut state, Command::SelectDifficulty);
+        assert_eq!(state.tutorial.map(|t| t.lesson), Some(1));
+        apply_command(&mut state, Command::Digit(2));
+        assert!(state.tutorial.is_some_and(|t| t.current().is_none()));
+        apply_command(&mut state, Command::SelectDifficulty);
+        assert_eq!(state.title.as_deref(), Some("Hidden single"));
+
+        apply_command(&mut state, Command::Back);
+        assert_eq!(state.screen, Screen::LevelSelector);
+        assert!(!state.has_game());
+        assert_eq!(state.lesson_choice, 2);
+    }
+
+    #[test]
+    fn win_screen_actions_start_a_puzzle_open_the_menu_or_replay() {
+        use crate::state::{Difficulty, DifficultyOption, Screen};
+
+        let mut state = GameState::new(Difficulty::Hard);
+        state.screen = Screen::Win;
+        apply_command(&mut state, Command::SelectDifficulty);
+        assert_eq!(state.screen, Screen::Playing);
+        assert_eq!(state.difficulty, Difficulty::Hard);
+
+        let (row, col) = (0..81)
+            .map(|i| (i / 9, i % 9))
+            .find(|&(r, c)| !state.grid[r][c].given)
+            .unwrap();
+        state.selection = crate::state::Selection { row, col };
+        apply_command(&mut state, Command::Digit(4));
+        assert_eq!(state.moves.len(), 1);
+
+        state.screen = Screen::Win;
+        apply_command(&mut state, Command::MoveSelectorDown);
+        apply_command(&mut state, Command::MoveSelectorDown);
+        apply_command(&mut state, Command::SelectDifficulty);
+        assert_eq!(state.screen, Screen::Replay);
+        assert_eq!(state.replay_step, 0);
+        apply_command(&mut state, Command::Move(MoveDir::Right));
+        apply_command(&mut state, Command::Move(MoveDir::Right));
+        assert_eq!(state.replay_step, 1);
+        apply_command(&mut state, Command::Back);
+        assert_eq!(state.screen, Screen::Win);
+
+        apply_command(&mut state, Command::MoveSelectorUp);
+        apply_command(&mut state, Command::SelectDifficulty);
+        assert_eq!(state.screen, Screen::LevelSelector);
+        assert_eq!(state.selector_selection, DifficultyOption::Hard);
+    }
 }
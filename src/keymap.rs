@@ -0,0 +1,700 @@
+use std::{collections::BTreeMap, fs};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+
+use crate::{
+    input::Command,
+    puzzle::GridShape,
+    state::{LastAction, MoveDir},
+};
+
+pub const KEYMAP_FILE: &str = "keys.toml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    pub fn from_event(event: KeyEvent) -> Self {
+        // Shift is already folded into the character for printable keys
+        // (`H`, `?`, `+`), so only keep it for named keys.
+        let mut modifiers = event.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT);
+        if !matches!(event.code, KeyCode::Char(_)) {
+            modifiers |= event.modifiers & KeyModifiers::SHIFT;
+        }
+        Self {
+            code: event.code,
+            modifiers,
+        }
+    }
+
+    /// Parses bindings such as `q`, `H`, `ctrl+n`, `ctrl++`, `left` or `f1`.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let (mods, key) = if text == "+" {
+            ("", "+")
+        } else if let Some(mods) = text.strip_suffix("++") {
+            (mods, "+")
+        } else {
+            match text.rsplit_once('+') {
+                Some((mods, key)) => (mods, key),
+                None => ("", text),
+            }
+        };
+
+        let mut modifiers = KeyModifiers::NONE;
+        for part in mods.split('+').filter(|part| !part.is_empty()) {
+            modifiers |= match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return None,
+            };
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_ascii_lowercase().as_str() {
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "space" => KeyCode::Char(' '),
+                name => {
+                    let n = name.strip_prefix('f')?.parse::<u8>().ok()?;
+                    if !(1..=12).contains(&n) {
+                        return None;
+                    }
+                    KeyCode::F(n)
+                }
+            },
+        };
+
+        // `shift+a` is the same key press as `A`.
+        let code = match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::Char(c.to_ascii_uppercase())
+            }
+            code => code,
+        };
+
+        Some(Self { code, modifiers })
+    }
+
+    pub fn label(&self) -> String {
+        let mut out = String::new();
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            out.push_str("Ctrl+");
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            out.push_str("Alt+");
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            out.push_str("Shift+");
+        }
+        match self.code {
+            KeyCode::Left => out.push('←'),
+            KeyCode::Right => out.push('→'),
+            KeyCode::Up => out.push('↑'),
+            KeyCode::Down => out.push('↓'),
+            KeyCode::Enter => out.push_str("Enter"),
+            KeyCode::Esc => out.push_str("Esc"),
+            KeyCode::Tab => out.push_str("Tab"),
+            KeyCode::Backspace => out.push_str("Bksp"),
+            KeyCode::Delete => out.push_str("Del"),
+            KeyCode::Insert => out.push_str("Ins"),
+            KeyCode::Home => out.push_str("Home"),
+            KeyCode::End => out.push_str("End"),
+            KeyCode::PageUp => out.push_str("PgUp"),
+            KeyCode::PageDown => out.push_str("PgDn"),
+            KeyCode::F(n) => out.push_str(&format!("F{n}")),
+            KeyCode::Char(' ') => out.push_str("Space"),
+            KeyCode::Char(c) => out.push(c),
+            _ => out.push('?'),
+        }
+        out
+    }
+}
+
+/// Every bindable command together with its name in `keys.toml`.
+pub const COMMAND_NAMES: &[(&str, Command)] = &[
+    ("move_left", Command::Move(MoveDir::Left)),
+    ("move_right", Command::Move(MoveDir::Right)),
+    ("move_up", Command::Move(MoveDir::Up)),
+    ("move_down", Command::Move(MoveDir::Down)),
+    ("selector_up", Command::MoveSelectorUp),
+    ("selector_down", Command::MoveSelectorDown),
+    ("select", Command::SelectDifficulty),
+    ("back", Command::Back),
+    ("notes", Command::ToggleNotes),
+    ("new_game", Command::NewGame),
+    ("givens", Command::ToggleGivens),
+    ("help", Command::ToggleHelp),
+    ("peers", Command::TogglePeers),
+    ("conflicts", Command::ToggleConflicts),
+    ("zoom_in", Command::ZoomIn),
+    ("zoom_out", Command::ZoomOut),
+    ("validate", Command::Validate),
+    ("pause", Command::Pause),
+    ("undo", Command::Action(LastAction::Undo)),
+    ("redo", Command::Action(LastAction::Redo)),
+    ("hint", Command::Action(LastAction::Hint)),
+    ("clear", Command::Action(LastAction::Clear)),
+    ("save", Command::Action(LastAction::Save)),
+    ("load", Command::Action(LastAction::Load)),
+    ("delete_slot", Command::DeleteSlot),
+    ("duplicate_slot", Command::DuplicateSlot),
+    ("quit", Command::Quit),
+];
+
+pub fn command_name(command: Command) -> Option<&'static str> {
+    COMMAND_NAMES
+        .iter()
+        .find(|(_, c)| *c == command)
+        .map(|(name, _)| *name)
+}
+
+fn command_by_name(name: &str) -> Option<Command> {
+    COMMAND_NAMES
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, command)| *command)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    Default,
+    Vim,
+    Numpad,
+}
+
+impl Preset {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "default" => Some(Self::Default),
+            "vim" => Some(Self::Vim),
+            "numpad" => Some(Self::Numpad),
+            _ => None,
+        }
+    }
+
+    fn bindings(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Self::Default => DEFAULT_PRESET,
+            Self::Vim => VIM_PRESET,
+            Self::Numpad => NUMPAD_PRESET,
+        }
+    }
+}
+
+impl std::fmt::Display for Preset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Preset::Default => write!(f, "default"),
+            Preset::Vim => write!(f, "vim"),
+            Preset::Numpad => write!(f, "numpad"),
+        }
+    }
+}
+
+const DEFAULT_PRESET: &[(&str, &str)] = &[
+    ("left", "move_left"),
+    ("H", "move_left"),
+    ("right", "move_right"),
+    ("L", "move_right"),
+    ("up", "move_up"),
+    ("K", "move_up"),
+    ("down", "move_down"),
+    ("J", "move_down"),
+    ("P", "selector_up"),
+    ("N", "selector_down"),
+    ("enter", "select"),
+    ("esc", "back"),
+    ("n", "notes"),
+    ("ctrl+n", "new_game"),
+    ("g", "givens"),
+    ("?", "help"),
+    ("p", "peers"),
+    ("x", "conflicts"),
+    ("+", "zoom_in"),
+    ("-", "zoom_out"),
+    ("v", "validate"),
+    ("space", "pause"),
+    ("u", "undo"),
+    ("r", "redo"),
+    ("h", "hint"),
+    ("c", "clear"),
+    ("s", "save"),
+    ("o", "load"),
+    ("d", "delete_slot"),
+    ("y", "duplicate_slot"),
+    ("q", "quit"),
+];
+
+// Lowercase hjkl move, so the hint moves to `t` and redo to Ctrl+r.
+const VIM_PRESET: &[(&str, &str)] = &[
+    ("left", "move_left"),
+    ("h", "move_left"),
+    ("right", "move_right"),
+    ("l", "move_right"),
+    ("up", "move_up"),
+    ("k", "move_up"),
+    ("down", "move_down"),
+    ("j", "move_down"),
+    ("K", "selector_up"),
+    ("J", "selector_down"),
+    ("enter", "select"),
+    ("esc", "back"),
+    ("n", "notes"),
+    ("ctrl+n", "new_game"),
+    ("g", "givens"),
+    ("?", "help"),
+    ("p", "peers"),
+    ("X", "conflicts"),
+    ("+", "zoom_in"),
+    ("-", "zoom_out"),
+    ("v", "validate"),
+    ("space", "pause"),
+    ("u", "undo"),
+    ("ctrl+r", "redo"),
+    ("t", "hint"),
+    ("x", "clear"),
+    ("delete", "clear"),
+    ("s", "save"),
+    ("o", "load"),
+    ("d", "delete_slot"),
+    ("y", "duplicate_slot"),
+    ("q", "quit"),
+];
+
+// Digits come from the keypad; the keypad operators drive the common actions.
+const NUMPAD_PRESET: &[(&str, &str)] = &[
+    ("left", "move_left"),
+    ("right", "move_right"),
+    ("up", "move_up"),
+    ("down", "move_down"),
+    ("pageup", "selector_up"),
+    ("pagedown", "selector_down"),
+    ("enter", "select"),
+    ("esc", "back"),
+    ("/", "notes"),
+    ("ctrl+n", "new_game"),
+    ("g", "givens"),
+    ("?", "help"),
+    ("p", "peers"),
+    ("x", "conflicts"),
+    ("+", "zoom_in"),
+    ("-", "zoom_out"),
+    ("v", "validate"),
+    ("space", "pause"),
+    ("backspace", "undo"),
+    ("u", "undo"),
+    ("r", "redo"),
+    ("*", "hint"),
+    ("0", "clear"),
+    (".", "clear"),
+    ("delete", "clear"),
+    ("s", "save"),
+    ("o", "load"),
+    ("d", "delete_slot"),
+    ("y", "duplicate_slot"),
+    ("q", "quit"),
+];
+
+#[derive(Debug, Deserialize)]
+struct KeymapFile {
+    preset: Option<String>,
+    #[serde(default)]
+    bindings: BTreeMap<String, KeyList>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum KeyList {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeyList {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            KeyList::One(key) => vec![key],
+            KeyList::Many(keys) => keys,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    pub preset: Preset,
+    bindings: Vec<(KeyBinding, Command)>,
+    /// Conflicts and unreadable entries found while loading, shown at startup.
+    pub problems: Vec<String>,
+}
+
+impl Keymap {
+    pub fn from_preset(preset: Preset) -> Self {
+        let bindings = preset
+            .bindings()
+            .iter()
+            .filter_map(|(key, name)| Some((KeyBinding::parse(key)?, command_by_name(name)?)))
+            .collect();
+        Self {
+            preset,
+            bindings,
+            problems: Vec::new(),
+        }
+    }
+
+    /// Loads `keys.toml` from the config dir, falling back to the default
+    /// preset when the file is missing or unreadable.
+    pub fn load() -> Self {
+        let Some(path) = crate::config::config_dir().map(|dir| dir.join(KEYMAP_FILE)) else {
+            return Self::default();
+        };
+        if !path.exists() {
+            return Self::default();
+        }
+
+        match fs::read_to_string(&path) {
+            Ok(source) => Self::from_toml(&source),
+            Err(e) => {
+                let mut keymap = Self::default();
+                keymap
+                    .problems
+                    .push(format!("cannot read {}: {e}", path.display()));
+                keymap
+            }
+        }
+    }
+
+    pub fn from_toml(source: &str) -> Self {
+        let file: KeymapFile = match toml::from_str(source) {
+            Ok(file) => file,
+            Err(e) => {
+                let mut keymap = Self::default();
+                let message = e.message().to_string();
+                keymap.problems.push(format!("{KEYMAP_FILE}: {message}"));
+                return keymap;
+            }
+        };
+
+        let mut problems = Vec::new();
+        let preset = match file.preset.as_deref() {
+            None => Preset::Default,
+            Some(name) => Preset::from_name(name).unwrap_or_else(|| {
+                problems.push(format!("unknown preset `{name}`, using default"));
+                Preset::Default
+            }),
+        };
+
+        let mut keymap = Self::from_preset(preset);
+        let mut overrides: Vec<(KeyBinding, Command)> = Vec::new();
+        for (name, keys) in file.bindings {
+            let Some(command) = command_by_name(&name) else {
+                problems.push(format!("unknown command `{name}`"));
+                continue;
+            };
+            keymap.bindings.retain(|(_, c)| *c != command);
+
+            for key in keys.into_vec() {
+                let Some(binding) = KeyBinding::parse(&key) else {
+                    problems.push(format!("cannot parse key `{key}` for {name}"));
+                    continue;
+                };
+                if let Some((_, other)) = overrides.iter().find(|(b, _)| *b == binding) {
+                    let other = command_name(*other).unwrap_or("?");
+                    problems.push(format!(
+                        "`{}` is bound to both {other} and {name}; using {other}",
+                        binding.label()
+                    ));
+                    continue;
+                }
+                overrides.push((binding, command));
+            }
+        }
+
+        for (binding, command) in overrides {
+            if let Some(pos) = keymap.bindings.iter().position(|(b, _)| *b == binding) {
+                let (_, replaced) = keymap.bindings.remove(pos);
+                problems.push(format!(
+                    "`{}` is bound to both {} and {}; using {}",
+                    binding.label(),
+                    command_name(command).unwrap_or("?"),
+                    command_name(replaced).unwrap_or("?"),
+                    command_name(command).unwrap_or("?"),
+                ));
+            }
+            keymap.bindings.push((binding, command));
+        }
+
+        for (name, command) in COMMAND_NAMES {
+            if keymap.keys_for(*command).is_empty() {
+                problems.push(format!("{name} has no key binding"));
+            }
+        }
+
+        keymap.problems = problems;
+        keymap
+    }
+
+    /// The command for a key press. A key that types one of `shape`'s digit
+    /// symbols enters it even when bound, so `0` is a digit on 16x16 and
+    /// capital letters are digits past 9; lowercase letters only enter a
+    /// digit when nothing is bound to them.
+    pub fn command_for(&self, event: KeyEvent, shape: GridShape) -> Option<Command> {
+        let binding = KeyBinding::from_event(event);
+        let symbol = match binding.code {
+            KeyCode::Char(c) if binding.modifiers.is_empty() => {
+                shape.parse_symbol(c).map(|digit| (c, digit))
+            }
+            _ => None,
+        };
+        if let Some((c, digit)) = symbol {
+            if !c.is_ascii_lowercase() {
+                return Some(Command::Digit(digit));
+            }
+        }
+
+        if let Some((_, command)) = self.bindings.iter().find(|(b, _)| *b == binding) {
+            return Some(*command);
+        }
+        symbol.map(|(_, digit)| Command::Digit(digit))
+    }
+
+    pub fn keys_for(&self, command: Command) -> Vec<KeyBinding> {
+        self.bindings
+            .iter()
+            .filter(|(_, c)| *c == command)
+            .map(|(binding, _)| *binding)
+            .collect()
+    }
+
+    /// Labels for every key bound to `command`, e.g. `u` or `Bksp,u`.
+    pub fn label_for(&self, command: Command) -> String {
+        let labels: Vec<String> = self
+            .keys_for(command)
+            .iter()
+            .map(KeyBinding::label)
+            .collect();
+        if labels.is_empty() {
+            "-".to_string()
+        } else {
+            labels.join(",")
+        }
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::from_preset(Preset::Default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn parses_key_strings() {
+        assert_eq!(
+            KeyBinding::parse("ctrl+n"),
+            Some(KeyBinding {
+                code: KeyCode::Char('n'),
+                modifiers: KeyModifiers::CONTROL,
+            })
+        );
+        assert_eq!(
+            KeyBinding::parse("+"),
+            Some(KeyBinding {
+                code: KeyCode::Char('+'),
+                modifiers: KeyModifiers::NONE,
+            })
+        );
+        assert_eq!(
+            KeyBinding::parse("ctrl++"),
+            Some(KeyBinding {
+                code: KeyCode::Char('+'),
+                modifiers: KeyModifiers::CONTROL,
+            })
+        );
+        assert_eq!(
+            KeyBinding::parse("shift+h"),
+            Some(KeyBinding {
+                code: KeyCode::Char('H'),
+                modifiers: KeyModifiers::NONE,
+            })
+        );
+        assert_eq!(
+            KeyBinding::parse("PageDown").map(|b| b.code),
+            Some(KeyCode::PageDown)
+        );
+        assert_eq!(KeyBinding::parse("f5").map(|b| b.code), Some(KeyCode::F(5)));
+        assert_eq!(KeyBinding::parse("hyper+x"), None);
+        assert_eq!(KeyBinding::parse("nonsense"), None);
+    }
+
+    #[test]
+    fn shifted_characters_match_unshifted_bindings() {
+        let keymap = Keymap::default();
+        let event = KeyEvent::new(KeyCode::Char('H'), KeyModifiers::SHIFT);
+        assert_eq!(
+            keymap.command_for(event, GridShape::CLASSIC),
+            Some(Command::Move(MoveDir::Left))
+        );
+        let event = KeyEvent::new(KeyCode::Char('?'), KeyModifiers::SHIFT);
+        assert_eq!(
+            keymap.command_for(event, GridShape::CLASSIC),
+            Some(Command::ToggleHelp)
+        );
+    }
+
+    #[test]
+    fn presets_bind_every_command_once() {
+        for preset in [Preset::Default, Preset::Vim, Preset::Numpad] {
+            let keymap = Keymap::from_preset(preset);
+            for (name, command) in COMMAND_NAMES {
+                assert!(
+                    !keymap.keys_for(*command).is_empty(),
+                    "{preset} preset leaves {name} unbound"
+                );
+            }
+            for (i, (binding, _)) in keymap.bindings.iter().enumerate() {
+                assert!(
+                    !keymap.bindings[i + 1..].iter().any(|(b, _)| b == binding),
+                    "{preset} preset binds `{}` twice",
+                    binding.label()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn vim_preset_uses_lowercase_movement() {
+        let keymap = Keymap::from_toml("preset = \"vim\"");
+        assert!(keymap.problems.is_empty());
+        assert_eq!(
+            keymap.command_for(key(KeyCode::Char('h')), GridShape::CLASSIC),
+            Some(Command::Move(MoveDir::Left))
+        );
+        assert_eq!(
+            keymap.command_for(key(KeyCode::Char('t')), GridShape::CLASSIC),
+            Some(Command::Action(LastAction::Hint))
+        );
+    }
+
+    #[test]
+    fn overrides_replace_preset_bindings() {
+        let keymap = Keymap::from_toml(
+            r#"
+            [bindings]
+            hint = ["i", "ctrl+h"]
+            "#,
+        );
+        assert!(keymap.problems.is_empty(), "{:?}", keymap.problems);
+        assert_eq!(
+            keymap.command_for(key(KeyCode::Char('h')), GridShape::CLASSIC),
+            None
+        );
+        assert_eq!(
+            keymap.command_for(key(KeyCode::Char('i')), GridShape::CLASSIC),
+            Some(Command::Action(LastAction::Hint))
+        );
+        assert_eq!(
+            keymap.command_for(
+                KeyEvent::new(KeyCode::Char('h'), KeyModifiers::CONTROL),
+                GridShape::CLASSIC
+            ),
+            Some(Command::Action(LastAction::Hint))
+        );
+    }
+
+    #[test]
+    fn reports_conflicts_and_bad_entries() {
+        let keymap = Keymap::from_toml(
+            r#"
+            preset = "emacs"
+
+            [bindings]
+            clear = "u"
+            redo = "u"
+            teleport = "t"
+            quit = "meta+q"
+            "#,
+        );
+        assert_eq!(keymap.preset, Preset::Default);
+        let problems = keymap.problems.join("\n");
+        assert!(problems.contains("unknown preset `emacs`"));
+        assert!(problems.contains("`u` is bound to both clear and redo"));
+        assert!(problems.contains("`u` is bound to both clear and undo"));
+        assert!(problems.contains("unknown command `teleport`"));
+        assert!(problems.contains("cannot parse key `meta+q` for quit"));
+        assert!(problems.contains("quit has no key binding"));
+        assert_eq!(
+            keymap.command_for(key(KeyCode::Char('u')), GridShape::CLASSIC),
+            Some(Command::Action(LastAction::Clear))
+        );
+    }
+
+    #[test]
+    fn malformed_file_falls_back_to_default() {
+        let keymap = Keymap::from_toml("preset = ");
+        assert_eq!(keymap.problems.len(), 1);
+        assert_eq!(
+            keymap.command_for(key(KeyCode::Char('q')), GridShape::CLASSIC),
+            Some(Command::Quit)
+        );
+    }
+
+    #[test]
+    fn digit_symbols_follow_the_grid_size() {
+        let keymap = Keymap::default();
+        let twelve = GridShape::ALL[3];
+        assert_eq!(
+            keymap.command_for(key(KeyCode::Char('C')), twelve),
+            Some(Command::Digit(12))
+        );
+        assert_eq!(
+            keymap.command_for(key(KeyCode::Char('c')), twelve),
+            Some(Command::Action(LastAction::Clear))
+        );
+        assert_eq!(
+            keymap.command_for(key(KeyCode::Char('a')), twelve),
+            Some(Command::Digit(10))
+        );
+        assert_eq!(
+            keymap.command_for(key(KeyCode::Char('a')), GridShape::CLASSIC),
+            None
+        );
+
+        let numpad = Keymap::from_preset(Preset::Numpad);
+        let hex = GridShape::ALL[4];
+        assert_eq!(
+            numpad.command_for(key(KeyCode::Char('0')), hex),
+            Some(Command::Digit(1))
+        );
+        assert_eq!(
+            numpad.command_for(key(KeyCode::Char('0')), GridShape::CLASSIC),
+            Some(Command::Action(LastAction::Clear))
+        );
+    }
+}
@@ -1,85 +1,192 @@
 use serde::{Deserialize, Serialize};
-use std::{env, fs, io, path::PathBuf};
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
 
-use crate::state::Difficulty;
+use crate::{
+    error::PersistError,
+    migrate::{self, Migration},
+    puzzle::{GridShape, Variant},
+    settings::Rules,
+    state::{Difficulty, GameMode},
+};
 
 #[allow(dead_code)]
 pub const LEADERBOARD_SIZE: usize = 20;
 #[allow(dead_code)]
 pub const TOP_DISPLAY_COUNT: usize = 5;
+pub const LEADERBOARD_VERSION: u32 = 5;
+
+/// `LEADERBOARD_MIGRATIONS[n]` upgrades a version `n + 1` file; files written
+/// before the leaderboard was versioned count as v1.
+const LEADERBOARD_MIGRATIONS: &[Migration] = &[
+    leaderboard_v1_to_v2,
+    leaderboard_v2_to_v3,
+    leaderboard_v3_to_v4,
+    leaderboard_v4_to_v5,
+];
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LeaderboardEntry {
     pub difficulty: Difficulty,
+    pub shape: GridShape,
+    pub variant: Variant,
     pub time_seconds: u64,
     pub completed_at: String,
+    /// `None` for entries recorded before rules were tracked.
+    pub rules: Option<Rules>,
+    pub mode: GameMode,
+    /// The countdown or time-attack limit the result was set against.
+    pub time_limit_secs: Option<u64>,
+    /// Puzzles finished: the score of a time-attack run, 1 otherwise.
+    pub solved: u32,
+}
+
+/// One leaderboard list. Results are only ranked against others in the same
+/// category.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Category {
+    pub difficulty: Difficulty,
+    pub shape: GridShape,
+    pub variant: Variant,
+    pub mode: GameMode,
+    pub time_limit_secs: Option<u64>,
+    /// Entries from before rules were tracked keep a list of their own.
+    pub rules: Option<Rules>,
+}
+
+impl LeaderboardEntry {
+    pub fn category(&self) -> Category {
+        Category {
+            difficulty: self.difficulty,
+            shape: self.shape,
+            variant: self.variant,
+            mode: self.mode,
+            time_limit_secs: self.time_limit_secs,
+            rules: self.rules,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Leaderboard {
+    pub version: u32,
     pub entries: Vec<LeaderboardEntry>,
 }
 
+/// v2 adds the version and writes `rules: null` on entries that predate rules.
+fn leaderboard_v1_to_v2(value: &mut serde_json::Value) -> io::Result<()> {
+    for entry in entries_mut(value)? {
+        migrate::insert_default(entry, "rules", serde_json::Value::Null);
+    }
+    Ok(())
+}
+
+/// v3 adds the grid size; earlier entries are all 9x9 games.
+fn leaderboard_v2_to_v3(value: &mut serde_json::Value) -> io::Result<()> {
+    let classic = serde_json::to_value(GridShape::CLASSIC)?;
+    for entry in entries_mut(value)? {
+        migrate::insert_default(entry, "shape", classic.clone());
+    }
+    Ok(())
+}
+
+/// v4 adds the variant; earlier entries are all classic games.
+fn leaderboard_v3_to_v4(value: &mut serde_json::Value) -> io::Result<()> {
+    let classic = serde_json::to_value(Variant::Classic)?;
+    for entry in entries_mut(value)? {
+        migrate::insert_default(entry, "variant", classic.clone());
+    }
+    Ok(())
+}
+
+/// v5 adds the countdown and time-attack categories; earlier entries are
+/// standard games that each solved one puzzle.
+fn leaderboard_v4_to_v5(value: &mut serde_json::Value) -> io::Result<()> {
+    let standard = serde_json::to_value(GameMode::Standard)?;
+    for entry in entries_mut(value)? {
+        migrate::insert_default(entry, "mode", standard.clone());
+        migrate::insert_default(entry, "time_limit_secs", serde_json::Value::Null);
+        migrate::insert_default(entry, "solved", 1.into());
+    }
+    Ok(())
+}
+
+fn entries_mut(value: &mut serde_json::Value) -> io::Result<&mut Vec<serde_json::Value>> {
+    value
+        .get_mut("entries")
+        .and_then(serde_json::Value::as_array_mut)
+        .ok_or_else(|| migrate::invalid("leaderboard has no entries list"))
+}
+
 impl Leaderboard {
     pub fn new() -> Self {
         Self {
+            version: LEADERBOARD_VERSION,
             entries: Vec::new(),
         }
     }
 
-    pub fn load() -> io::Result<Self> {
-        let path = leaderboard_path()?;
+    /// Parses a leaderboard of any supported version, upgrading it first.
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        let mut value: serde_json::Value = serde_json::from_str(json)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        migrate::upgrade(&mut value, LEADERBOARD_MIGRATIONS)?;
+        serde_json::from_value(value).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn load() -> Result<Self, PersistError> {
+        let path = leaderboard_path();
         if !path.exists() {
             return Ok(Self::new());
         }
 
-        let json = fs::read_to_string(&path)?;
-        serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        Self::read(&path).map_err(|source| PersistError::Leaderboard { path, source })
     }
 
-    #[allow(dead_code)]
-    pub fn save(&self) -> io::Result<()> {
-        let path = leaderboard_path()?;
+    fn read(path: &Path) -> io::Result<Self> {
+        let json = fs::read_to_string(path)?;
+        Self::from_json(&json)
+    }
+
+    pub fn save(&self) -> Result<(), PersistError> {
+        let path = leaderboard_path();
+        self.write(&path)
+            .map_err(|source| PersistError::Leaderboard { path, source })
+    }
+
+    fn write(&self, path: &Path) -> io::Result<()> {
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent)?;
         }
 
         let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
-        fs::write(&path, json)
+        crate::storage::write_atomic(path, json.as_bytes())
     }
 
     #[allow(dead_code)]
     pub fn add_entry(&mut self, entry: LeaderboardEntry) {
         self.entries.push(entry);
 
-        self.entries.sort_by(|a, b| {
-            if a.difficulty != b.difficulty {
-                return std::cmp::Ordering::Equal;
-            }
-            a.time_seconds.cmp(&b.time_seconds)
-        });
+        // More puzzles first, which only time attack varies, then faster.
+        self.entries
+            .sort_by_key(|e| (std::cmp::Reverse(e.solved), e.time_seconds));
 
-        let mut per_difficulty_count = [0usize; 4];
+        let mut counts: HashMap<Category, usize> = HashMap::new();
         self.entries.retain(|entry| {
-            let idx = match entry.difficulty {
-                Difficulty::Easy => 0,
-                Difficulty::Medium => 1,
-                Difficulty::Hard => 2,
-                Difficulty::Expert => 3,
-            };
-            per_difficulty_count[idx] += 1;
-            per_difficulty_count[idx] <= LEADERBOARD_SIZE
+            let count = counts.entry(entry.category()).or_default();
+            *count += 1;
+            *count <= LEADERBOARD_SIZE
         });
     }
 
-    pub fn get_top_for_difficulty(
-        &self,
-        difficulty: Difficulty,
-        n: usize,
-    ) -> Vec<&LeaderboardEntry> {
+    /// The best `n` results in `category`.
+    pub fn get_top(&self, category: Category, n: usize) -> Vec<&LeaderboardEntry> {
         self.entries
             .iter()
-            .filter(|e| e.difficulty == difficulty)
+            .filter(|e| e.category() == category)
             .take(n)
             .collect()
     }
@@ -91,54 +198,63 @@ impl Default for Leaderboard {
     }
 }
 
-fn leaderboard_path() -> io::Result<PathBuf> {
-    if let Some(xdg_data_home) = env::var_os("XDG_DATA_HOME") {
-        return Ok(PathBuf::from(xdg_data_home)
-            .join("sudokui")
-            .join("leaderboard.json"));
-    }
-
-    if let Some(home) = env::var_os("HOME") {
-        return Ok(PathBuf::from(home)
-            .join(".local")
-            .join("share")
-            .join("sudokui")
-            .join("leaderboard.json"));
-    }
-
-    #[cfg(windows)]
-    if let Some(local_app_data) = env::var_os("LOCALAPPDATA") {
-        return Ok(PathBuf::from(local_app_data)
-            .join("sudokui")
-            .join("leaderboard.json"));
-    }
-
-    Ok(PathBuf::from("sudokui-leaderboard.json"))
+fn leaderboard_path() -> PathBuf {
+    crate::config::data_dir().map_or_else(
+        || PathBuf::from("sudokui-leaderboard.json"),
+        |dir| dir.join("leaderboard.json"),
+    )
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// Standard games recorded without rules, like most entries below.
+    fn standard(difficulty: Difficulty, shape: GridShape, variant: Variant) -> Category {
+        Category {
+            difficulty,
+            shape,
+            variant,
+            mode: GameMode::Standard,
+            time_limit_secs: None,
+            rules: None,
+        }
+    }
+
     #[test]
     fn test_add_entry() {
         let mut leaderboard = Leaderboard::new();
 
         let entry1 = LeaderboardEntry {
             difficulty: Difficulty::Easy,
+            shape: GridShape::CLASSIC,
+            variant: Variant::Classic,
             time_seconds: 100,
             completed_at: "2026-01-26T00:00:00Z".to_string(),
+            rules: None,
+            mode: GameMode::Standard,
+            time_limit_secs: None,
+            solved: 1,
         };
         leaderboard.add_entry(entry1);
 
         let entry2 = LeaderboardEntry {
             difficulty: Difficulty::Easy,
+            shape: GridShape::CLASSIC,
+            variant: Variant::Classic,
             time_seconds: 50,
             completed_at: "2026-01-26T01:00:00Z".to_string(),
+            rules: None,
+            mode: GameMode::Standard,
+            time_limit_secs: None,
+            solved: 1,
         };
         leaderboard.add_entry(entry2);
 
-        let top = leaderboard.get_top_for_difficulty(Difficulty::Easy, 5);
+        let top = leaderboard.get_top(
+            standard(Difficulty::Easy, GridShape::CLASSIC, Variant::Classic),
+            5,
+        );
         assert_eq!(top.len(), 2);
         assert_eq!(top[0].time_seconds, 50);
         assert_eq!(top[1].time_seconds, 100);
@@ -151,35 +267,280 @@ mod tests {
         for i in 0..25 {
             leaderboard.add_entry(LeaderboardEntry {
                 difficulty: Difficulty::Easy,
+                shape: GridShape::CLASSIC,
+                variant: Variant::Classic,
                 time_seconds: 100 + i as u64,
                 completed_at: "2026-01-26T00:00:00Z".to_string(),
+                rules: None,
+                mode: GameMode::Standard,
+                time_limit_secs: None,
+                solved: 1,
             });
         }
 
-        let easy_entries = leaderboard.get_top_for_difficulty(Difficulty::Easy, 100);
+        let easy_entries = leaderboard.get_top(
+            standard(Difficulty::Easy, GridShape::CLASSIC, Variant::Classic),
+            100,
+        );
         assert_eq!(easy_entries.len(), LEADERBOARD_SIZE);
     }
 
+    #[test]
+    fn test_entries_without_rules_still_load() {
+        let json =
+            r#"{"entries":[{"difficulty":"Easy","time_seconds":90,"completed_at":"2026-01-26"}]}"#;
+        let leaderboard = Leaderboard::from_json(json).unwrap();
+        assert_eq!(leaderboard.entries[0].rules, None);
+
+        let mut entry = leaderboard.entries[0].clone();
+        entry.rules = Some(Rules {
+            mistakes_max: 5,
+            hints: 0,
+            check_mode: crate::settings::CheckMode::RulesOnly,
+        });
+        let json = serde_json::to_string(&entry).unwrap();
+        let parsed: LeaderboardEntry = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.rules, entry.rules);
+    }
+
+    #[test]
+    fn test_unversioned_fixtures_upgrade() {
+        let legacy = Leaderboard::from_json(include_str!(
+            "../tests/fixtures/migrations/leaderboard-unversioned.json"
+        ))
+        .unwrap();
+        assert_eq!(legacy.version, LEADERBOARD_VERSION);
+        assert_eq!(legacy.entries.len(), 2);
+        assert!(legacy.entries.iter().all(|e| e.rules.is_none()));
+        assert!(legacy.entries.iter().all(|e| e.shape == GridShape::CLASSIC));
+        assert!(legacy.entries.iter().all(|e| e.variant == Variant::Classic));
+        assert!(legacy
+            .entries
+            .iter()
+            .all(|e| e.mode == GameMode::Standard && e.solved == 1));
+
+        let mixed = Leaderboard::from_json(include_str!(
+            "../tests/fixtures/migrations/leaderboard-unversioned-rules.json"
+        ))
+        .unwrap();
+        assert_eq!(mixed.entries[0].rules.unwrap().hints, 2);
+        assert_eq!(mixed.entries[1].rules, None);
+        let expert = mixed.entries[2].rules.unwrap();
+        assert_eq!(expert.mistakes_max, 5);
+        assert_eq!(expert.check_mode, crate::settings::CheckMode::Solution);
+
+        let json = serde_json::to_string(&mixed).unwrap();
+        let reloaded = Leaderboard::from_json(&json).unwrap();
+        assert_eq!(reloaded.entries.len(), 3);
+        assert_eq!(reloaded.version, LEADERBOARD_VERSION);
+    }
+
+    #[test]
+    fn every_versioned_file_upgrades_with_defaults_for_later_fields() {
+        let fixtures = [
+            (
+                2,
+                include_str!("../tests/fixtures/migrations/leaderboard-v2.json"),
+            ),
+            (
+                3,
+                include_str!("../tests/fixtures/migrations/leaderboard-v3.json"),
+            ),
+            (
+                4,
+                include_str!("../tests/fixtures/migrations/leaderboard-v4.json"),
+            ),
+            (
+                5,
+                include_str!("../tests/fixtures/migrations/leaderboard-v5.json"),
+            ),
+        ];
+        // Each entry field and the version that started saving it.
+        let added = [
+            ("shape", 3),
+            ("variant", 4),
+            ("mode", 5),
+            ("time_limit_secs", 5),
+            ("solved", 5),
+        ];
+        for (version, json) in fixtures {
+            let raw: serde_json::Value = serde_json::from_str(json).unwrap();
+            assert_eq!(raw["version"], version);
+            for (field, since) in added {
+                let present = raw["entries"][0].get(field).is_some();
+                assert_eq!(present, version >= since, "{field} in v{version}");
+            }
+
+            let leaderboard = Leaderboard::from_json(json).unwrap();
+            assert_eq!(leaderboard.version, LEADERBOARD_VERSION);
+            assert_eq!(leaderboard.entries.len(), 3);
+            assert_eq!(leaderboard.entries[0].rules.unwrap().hints, 2);
+            assert_eq!(leaderboard.entries[1].rules, None);
+            assert!(leaderboard.entries.iter().all(|e| {
+                e.shape == GridShape::CLASSIC
+                    && e.variant == Variant::Classic
+                    && e.mode == GameMode::Standard
+                    && e.time_limit_secs.is_none()
+                    && e.solved == 1
+            }));
+        }
+    }
+
     #[test]
     fn test_multiple_difficulties() {
         let mut leaderboard = Leaderboard::new();
 
         leaderboard.add_entry(LeaderboardEntry {
             difficulty: Difficulty::Easy,
+            shape: GridShape::CLASSIC,
+            variant: Variant::Classic,
             time_seconds: 100,
             completed_at: "2026-01-26T00:00:00Z".to_string(),
+            rules: None,
+            mode: GameMode::Standard,
+            time_limit_secs: None,
+            solved: 1,
         });
 
         leaderboard.add_entry(LeaderboardEntry {
             difficulty: Difficulty::Hard,
+            shape: GridShape::CLASSIC,
+            variant: Variant::Classic,
             time_seconds: 200,
             completed_at: "2026-01-26T01:00:00Z".to_string(),
+            rules: None,
+            mode: GameMode::Standard,
+            time_limit_secs: None,
+            solved: 1,
         });
 
-        let easy_top = leaderboard.get_top_for_difficulty(Difficulty::Easy, 5);
-        let hard_top = leaderboard.get_top_for_difficulty(Difficulty::Hard, 5);
+        let easy_top = leaderboard.get_top(
+            standard(Difficulty::Easy, GridShape::CLASSIC, Variant::Classic),
+            5,
+        );
+        let hard_top = leaderboard.get_top(
+            standard(Difficulty::Hard, GridShape::CLASSIC, Variant::Classic),
+            5,
+        );
 
         assert_eq!(easy_top.len(), 1);
         assert_eq!(hard_top.len(), 1);
     }
+
+    #[test]
+    fn grid_sizes_and_variants_are_ranked_separately() {
+        let mut leaderboard = Leaderboard::new();
+        let small = GridShape::ALL[0];
+        for (shape, variant, time_seconds) in [
+            (GridShape::CLASSIC, Variant::Classic, 300),
+            (small, Variant::Classic, 40),
+            (GridShape::CLASSIC, Variant::Killer, 900),
+        ] {
+            leaderboard.add_entry(LeaderboardEntry {
+                difficulty: Difficulty::Easy,
+                shape,
+                variant,
+                time_seconds,
+                completed_at: "2026-01-26T00:00:00Z".to_string(),
+                rules: None,
+                mode: GameMode::Standard,
+                time_limit_secs: None,
+                solved: 1,
+            });
+        }
+
+        let classic = leaderboard.get_top(
+            standard(Difficulty::Easy, GridShape::CLASSIC, Variant::Classic),
+            5,
+        );
+        assert_eq!(classic.len(), 1);
+        assert_eq!(classic[0].time_seconds, 300);
+        let small_top = leaderboard.get_top(standard(Difficulty::Easy, small, Variant::Classic), 5);
+        assert_eq!(small_top[0].time_seconds, 40);
+        let killer = leaderboard.get_top(
+            standard(Difficulty::Easy, GridShape::CLASSIC, Variant::Killer),
+            5,
+        );
+        assert_eq!(killer.len(), 1);
+        assert_eq!(killer[0].time_seconds, 900);
+    }
+
+    #[test]
+    fn timed_modes_are_ranked_in_their_own_categories() {
+        let mut leaderboard = Leaderboard::new();
+        let entry = |mode, time_limit_secs, solved, time_seconds| LeaderboardEntry {
+            difficulty: Difficulty::Easy,
+            shape: GridShape::CLASSIC,
+            variant: Variant::Classic,
+            time_seconds,
+            completed_at: "2026-10-18".to_string(),
+            rules: None,
+            mode,
+            time_limit_secs,
+            solved,
+        };
+        for (solved, time_seconds) in [(3, 900), (7, 900), (5, 880)] {
+            leaderboard.add_entry(entry(GameMode::TimeAttack, Some(900), solved, time_seconds));
+        }
+        leaderboard.add_entry(entry(GameMode::Standard, None, 1, 100));
+        leaderboard.add_entry(entry(GameMode::Countdown, Some(300), 1, 250));
+        leaderboard.add_entry(entry(GameMode::Countdown, Some(600), 1, 200));
+
+        let attack = entry(GameMode::TimeAttack, Some(900), 0, 0).category();
+        let solved: Vec<u32> = leaderboard
+            .get_top(attack, 5)
+            .iter()
+            .map(|e| e.solved)
+            .collect();
+        assert_eq!(solved, [7, 5, 3]);
+
+        let standard = leaderboard.get_top(
+            standard(Difficulty::Easy, GridShape::CLASSIC, Variant::Classic),
+            5,
+        );
+        assert_eq!(standard.len(), 1);
+        assert_eq!(standard[0].time_seconds, 100);
+
+        let five_minutes = entry(GameMode::Countdown, Some(300), 1, 0).category();
+        let top = leaderboard.get_top(five_minutes, 5);
+        assert_eq!(top.len(), 1);
+        assert_eq!(top[0].time_seconds, 250);
+    }
+
+    #[test]
+    fn rules_are_ranked_separately() {
+        use crate::settings::CheckMode;
+
+        let rules = |mistakes_max, hints| {
+            Some(Rules {
+                mistakes_max,
+                hints,
+                check_mode: CheckMode::Solution,
+            })
+        };
+        let entry = |rules, time_seconds| LeaderboardEntry {
+            difficulty: Difficulty::Hard,
+            shape: GridShape::CLASSIC,
+            variant: Variant::Classic,
+            time_seconds,
+            completed_at: "2026-01-26T00:00:00Z".to_string(),
+            rules,
+            mode: GameMode::Standard,
+            time_limit_secs: None,
+            solved: 1,
+        };
+        let mut leaderboard = Leaderboard::new();
+        leaderboard.add_entry(entry(rules(3, 2), 600));
+        leaderboard.add_entry(entry(rules(10, 9), 300));
+        leaderboard.add_entry(entry(None, 200));
+        leaderboard.add_entry(entry(rules(3, 2), 500));
+
+        let strict = leaderboard.get_top(entry(rules(3, 2), 0).category(), 5);
+        let times: Vec<u64> = strict.iter().map(|e| e.time_seconds).collect();
+        assert_eq!(times, [500, 600]);
+        let lenient = leaderboard.get_top(entry(rules(10, 9), 0).category(), 5);
+        assert_eq!(lenient.len(), 1);
+        let legacy = leaderboard.get_top(entry(None, 0).category(), 5);
+        assert_eq!(legacy[0].time_seconds, 200);
+    }
 }
@@ -1,9 +1,22 @@
+mod analysis;
 mod app;
+mod config;
+mod error;
 mod history;
+mod import;
 mod input;
+mod keymap;
 mod leaderboard;
+mod migrate;
+mod packs;
 mod puzzle;
+mod settings;
+mod share;
+mod slots;
 mod state;
+mod storage;
+mod theme;
+mod tutorial;
 mod ui;
 
 fn main() -> std::io::Result<()> {
@@ -0,0 +1,82 @@
+use std::io;
+
+use serde_json::Value;
+
+/// Upgrades a document from version `n` to `n + 1` in place.
+pub type Migration = fn(&mut Value) -> io::Result<()>;
+
+/// Runs every migration from the document's `version` up to the latest one,
+/// where `migrations[0]` upgrades v1 to v2 and so on. A missing version is
+/// read as v1, the format written before files carried one.
+pub fn upgrade(value: &mut Value, migrations: &[Migration]) -> io::Result<()> {
+    let latest = migrations.len() as u64 + 1;
+    let Some(object) = value.as_object() else {
+        return Err(invalid("expected a JSON object"));
+    };
+    let version = match object.get("version") {
+        None => 1,
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| invalid("version is not a number"))?,
+    };
+    if version == 0 || version > latest {
+        return Err(invalid(&format!(
+            "unsupported version {version} (latest is {latest})"
+        )));
+    }
+
+    for migrate in &migrations[(version - 1) as usize..] {
+        migrate(value)?;
+    }
+    value["version"] = Value::from(latest);
+    Ok(())
+}
+
+/// Sets `key` to `default` unless the document already has it.
+pub fn insert_default(value: &mut Value, key: &str, default: Value) {
+    if let Some(object) = value.as_object_mut() {
+        object.entry(key).or_insert(default);
+    }
+}
+
+pub fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn add_a(value: &mut Value) -> io::Result<()> {
+        insert_default(value, "a", json!(1));
+        Ok(())
+    }
+
+    fn double_a(value: &mut Value) -> io::Result<()> {
+        let a = value["a"].as_u64().ok_or_else(|| invalid("missing a"))?;
+        value["a"] = json!(a * 2);
+        Ok(())
+    }
+
+    #[test]
+    fn runs_only_the_migrations_a_document_needs() {
+        let migrations: [Migration; 2] = [add_a, double_a];
+
+        let mut unversioned = json!({});
+        upgrade(&mut unversioned, &migrations).unwrap();
+        assert_eq!(unversioned, json!({"version": 3, "a": 2}));
+
+        let mut v2 = json!({"version": 2, "a": 5});
+        upgrade(&mut v2, &migrations).unwrap();
+        assert_eq!(v2, json!({"version": 3, "a": 10}));
+
+        let mut latest = json!({"version": 3, "a": 5});
+        upgrade(&mut latest, &migrations).unwrap();
+        assert_eq!(latest["a"], 5);
+
+        assert!(upgrade(&mut json!({"version": 4}), &migrations).is_err());
+        assert!(upgrade(&mut json!({"version": 0}), &migrations).is_err());
+        assert!(upgrade(&mut json!([]), &migrations).is_err());
+    }
+}
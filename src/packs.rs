@@ -0,0 +1,464 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::PersistError,
+    import::PuzzleFile,
+    migrate::{self, Migration},
+    settings::Settings,
+    state::GameState,
+};
+
+pub const PACKS_DIR: &str = "packs";
+pub const PROGRESS_VERSION: u32 = 1;
+/// Puzzles of a campaign pack to solve before the next one opens.
+pub const UNLOCK_SOLVED: usize = 4;
+
+/// The campaign, easiest pack first, as `(id, source)`.
+const CAMPAIGN: [(&str, &str); 4] = [
+    ("1-first-steps", include_str!("../packs/1-first-steps.toml")),
+    ("2-warming-up", include_str!("../packs/2-warming-up.toml")),
+    ("3-deep-water", include_str!("../packs/3-deep-water.toml")),
+    ("4-night-shift", include_str!("../packs/4-night-shift.toml")),
+];
+
+/// `PROGRESS_MIGRATIONS[n]` upgrades a version `n + 1` file.
+const PROGRESS_MIGRATIONS: &[Migration] = &[];
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PackFile {
+    title: String,
+    author: String,
+    /// 1 to 5 stars.
+    rating: u8,
+    puzzle: Vec<PuzzleFile>,
+}
+
+/// A collection of puzzles played in order, from the campaign or the config
+/// `packs` directory.
+#[derive(Debug, Clone)]
+pub struct Pack {
+    /// The file stem, which progress is recorded under.
+    pub id: String,
+    pub title: String,
+    pub author: String,
+    pub rating: u8,
+    pub puzzles: Vec<PuzzleFile>,
+    /// Campaign packs open one after another; other packs are always open.
+    pub campaign: bool,
+}
+
+/// A puzzle of a pack, saved with the game played from it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackRef {
+    pub pack: String,
+    pub index: usize,
+}
+
+impl Pack {
+    /// Reads a pack file, checking that every puzzle has one solution.
+    pub fn parse(id: &str, source: &str, campaign: bool) -> Result<Self, String> {
+        let file: PackFile = toml::from_str(source).map_err(|e| e.message().to_string())?;
+        if !(1..=5).contains(&file.rating) {
+            return Err("rating must be 1 to 5".to_string());
+        }
+        if file.puzzle.is_empty() {
+            return Err("a pack needs at least one puzzle".to_string());
+        }
+        for (i, puzzle) in file.puzzle.iter().enumerate() {
+            puzzle
+                .to_puzzle()
+                .map_err(|e| format!("puzzle {}: {e}", i + 1))?;
+        }
+        Ok(Self {
+            id: id.to_string(),
+            title: file.title,
+            author: file.author,
+            rating: file.rating,
+            puzzles: file.puzzle,
+            campaign,
+        })
+    }
+
+    /// The puzzle's own title, or its number in the pack.
+    pub fn puzzle_label(&self, index: usize) -> String {
+        self.puzzles[index]
+            .title
+            .clone()
+            .unwrap_or_else(|| format!("#{}", index + 1))
+    }
+
+    /// A new game of puzzle `index`, named after the pack.
+    pub fn game(&self, index: usize, settings: Settings) -> Result<GameState, String> {
+        let title = match &self.puzzles[index].title {
+            Some(title) => format!("{}: {title}", self.title),
+            None => format!("{} #{}", self.title, index + 1),
+        };
+        let mut state = self.puzzles[index].to_game(title, settings)?;
+        state.pack = Some(PackRef {
+            pack: self.id.clone(),
+            index,
+        });
+        Ok(state)
+    }
+}
+
+/// Which pack puzzles have been solved, and the one played last.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackProgress {
+    pub version: u32,
+    /// Solved puzzle indices by pack id.
+    pub solved: BTreeMap<String, BTreeSet<usize>>,
+    pub last: Option<PackRef>,
+}
+
+impl Default for PackProgress {
+    fn default() -> Self {
+        Self {
+            version: PROGRESS_VERSION,
+            solved: BTreeMap::new(),
+            last: None,
+        }
+    }
+}
+
+impl PackProgress {
+    /// Parses progress of any supported version, upgrading it first.
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        let mut value: serde_json::Value = serde_json::from_str(json)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        migrate::upgrade(&mut value, PROGRESS_MIGRATIONS)?;
+        serde_json::from_value(value).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn load() -> Result<Self, PersistError> {
+        let path = progress_path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        fs::read_to_string(&path)
+            .and_then(|json| Self::from_json(&json))
+            .map_err(|source| PersistError::Packs { path, source })
+    }
+
+    pub fn save(&self) -> Result<(), PersistError> {
+        let path = progress_path();
+        self.write(&path)
+            .map_err(|source| PersistError::Packs { path, source })
+    }
+
+    fn write(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        crate::storage::write_atomic(path, json.as_bytes())
+    }
+
+    pub fn solved_count(&self, pack: &Pack) -> usize {
+        self.solved
+            .get(&pack.id)
+            .map_or(0, |solved| solved.range(..pack.puzzles.len()).count())
+    }
+
+    pub fn is_solved(&self, pack: &Pack, index: usize) -> bool {
+        self.solved
+            .get(&pack.id)
+            .is_some_and(|solved| solved.contains(&index))
+    }
+
+    pub fn mark_solved(&mut self, puzzle: &PackRef) {
+        self.solved
+            .entry(puzzle.pack.clone())
+            .or_default()
+            .insert(puzzle.index);
+    }
+
+    /// The first unsolved puzzle from `start` on, wrapping round, if the pack
+    /// isn't finished.
+    pub fn next_puzzle(&self, pack: &Pack, start: usize) -> Option<usize> {
+        let len = pack.puzzles.len();
+        (0..len)
+            .map(|i| (start + i) % len)
+            .find(|&index| !self.is_solved(pack, index))
+    }
+}
+
+fn progress_path() -> PathBuf {
+    crate::config::data_dir().map_or_else(
+        || PathBuf::from("sudokui-packs.json"),
+        |dir| dir.join("packs.json"),
+    )
+}
+
+/// The level selector's pack list, and the pack opened from it.
+#[derive(Debug, Clone, Default)]
+pub struct PackBrowser {
+    pub packs: Vec<Pack>,
+    /// Pack files that couldn't be read, with the reason.
+    pub problems: Vec<String>,
+    pub progress: PackProgress,
+    /// The pack under the cursor, or the puzzle once a pack is open.
+    pub selected: usize,
+    pub open: Option<usize>,
+}
+
+impl PackBrowser {
+    /// The built-in packs alone.
+    pub fn campaign() -> Self {
+        Self {
+            packs: CAMPAIGN
+                .iter()
+                .map(|(id, source)| {
+                    Pack::parse(id, source, true).expect("built-in packs are checked by the tests")
+                })
+                .collect(),
+            ..Self::default()
+        }
+    }
+
+    /// The campaign followed by any packs in the config `packs` directory.
+    pub fn load() -> Self {
+        let mut browser = Self::campaign();
+        if let Some(dir) = crate::config::config_dir() {
+            browser.load_dir(&dir.join(PACKS_DIR));
+        }
+        browser
+    }
+
+    fn load_dir(&mut self, dir: &Path) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let id = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let parsed = if self.packs.iter().any(|pack| pack.id == id) {
+                Err("a campaign pack already has this name".to_string())
+            } else {
+                fs::read_to_string(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|source| Pack::parse(&id, &source, false))
+            };
+            match parsed {
+                Ok(pack) => self.packs.push(pack),
+                Err(e) => self.problems.push(format!("{}: {e}", path.display())),
+            }
+        }
+    }
+
+    pub fn find(&self, id: &str) -> Option<&Pack> {
+        self.packs.iter().find(|pack| pack.id == id)
+    }
+
+    /// Whether pack `index` can be played: campaign packs need enough of the
+    /// one before solved.
+    pub fn is_unlocked(&self, index: usize) -> bool {
+        if !self.packs[index].campaign || index == 0 {
+            return true;
+        }
+        let previous = &self.packs[index - 1];
+        previous.campaign
+            && self.is_unlocked(index - 1)
+            && self.progress.solved_count(previous) >= unlock_target(previous)
+    }
+
+    /// The puzzle "Continue pack" starts: the next unsolved one after the
+    /// last played, or the start of the next open pack once that one is done.
+    pub fn continue_target(&self) -> Option<PackRef> {
+        let last = self.progress.last.as_ref()?;
+        let start = self.packs.iter().position(|pack| pack.id == last.pack)?;
+        let pack = &self.packs[start];
+        if let Some(index) = self.progress.next_puzzle(pack, last.index) {
+            return Some(PackRef {
+                pack: pack.id.clone(),
+                index,
+            });
+        }
+        (start + 1..self.packs.len())
+            .filter(|&i| self.is_unlocked(i))
+            .find_map(|i| {
+                let pack = &self.packs[i];
+                self.progress.next_puzzle(pack, 0).map(|index| PackRef {
+                    pack: pack.id.clone(),
+                    index,
+                })
+            })
+    }
+
+    pub fn row_count(&self) -> usize {
+        match self.open {
+            Some(pack) => self.packs[pack].puzzles.len(),
+            None => self.packs.len(),
+        }
+    }
+
+    pub fn move_cursor(&mut self, forward: bool) {
+        let len = self.row_count();
+        if len > 0 {
+            self.selected = if forward {
+                self.selected + 1
+            } else {
+                self.selected + len - 1
+            } % len;
+        }
+    }
+
+    /// Opens the selected pack at its first unsolved puzzle, unless it is
+    /// still locked.
+    pub fn open_selected(&mut self) -> bool {
+        if self.open.is_some() || !self.is_unlocked(self.selected) {
+            return false;
+        }
+        let pack = &self.packs[self.selected];
+        let puzzle = self.progress.next_puzzle(pack, 0).unwrap_or(0);
+        self.open = Some(self.selected);
+        self.selected = puzzle;
+        true
+    }
+
+    /// Goes back from a pack's puzzles to the pack list.
+    pub fn close_pack(&mut self) -> bool {
+        let Some(pack) = self.open.take() else {
+            return false;
+        };
+        self.selected = pack;
+        true
+    }
+}
+
+/// Puzzles of `pack` to solve to open the campaign pack after it.
+pub fn unlock_target(pack: &Pack) -> usize {
+    UNLOCK_SOLVED.min(pack.puzzles.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solve(browser: &mut PackBrowser, pack: usize, indices: &[usize]) {
+        for &index in indices {
+            let id = browser.packs[pack].id.clone();
+            browser.progress.mark_solved(&PackRef { pack: id, index });
+        }
+    }
+
+    #[test]
+    fn campaign_packs_parse_and_get_harder() {
+        let browser = PackBrowser::campaign();
+        let titles: Vec<&str> = browser.packs.iter().map(|p| p.title.as_str()).collect();
+        assert_eq!(
+            titles,
+            ["First Steps", "Warming Up", "Deep Water", "Night Shift"]
+        );
+        let ratings: Vec<u8> = browser.packs.iter().map(|p| p.rating).collect();
+        assert_eq!(ratings, [1, 2, 3, 4]);
+        let pack = &browser.packs[1];
+        let game = pack.game(2, Settings::default()).unwrap();
+        assert_eq!(game.title.as_deref(), Some("Warming Up #3"));
+        assert_eq!(
+            game.pack,
+            Some(PackRef {
+                pack: "2-warming-up".to_string(),
+                index: 2
+            })
+        );
+        assert!(!game.is_ranked());
+    }
+
+    #[test]
+    fn explains_what_is_wrong_with_a_pack() {
+        let source = CAMPAIGN[0].1;
+        let error = |source: &str| Pack::parse("mine", source, false).unwrap_err();
+        assert_eq!(
+            error(&source.replace("rating = 1", "rating = 6")),
+            "rating must be 1 to 5"
+        );
+        assert_eq!(
+            error("title = \"Empty\"\nauthor = \"me\"\nrating = 2\npuzzle = []"),
+            "a pack needs at least one puzzle"
+        );
+        assert_eq!(
+            error(&source.replacen("2...59634", "2...5963", 1)),
+            "puzzle 1: row A has 8 cells; a 9x9 grid needs 9"
+        );
+        assert!(
+            error(&source.replace("rating = 1", "rating = 1\nstars = 1")).contains("unknown field")
+        );
+    }
+
+    #[test]
+    fn campaign_packs_unlock_in_order() {
+        let mut browser = PackBrowser::campaign();
+        assert!(browser.is_unlocked(0));
+        assert!(!browser.is_unlocked(1));
+        solve(&mut browser, 0, &[0, 1, 2]);
+        assert!(!browser.is_unlocked(1));
+        solve(&mut browser, 0, &[5]);
+        assert!(browser.is_unlocked(1));
+        assert!(!browser.is_unlocked(2));
+        // Progress in a later pack doesn't count while its own is locked.
+        solve(&mut browser, 2, &[0, 1, 2, 3]);
+        assert!(!browser.is_unlocked(3));
+
+        browser.selected = 3;
+        assert!(!browser.open_selected());
+        browser.selected = 0;
+        assert!(browser.open_selected());
+        assert_eq!((browser.open, browser.selected), (Some(0), 3));
+        assert!(browser.close_pack());
+        assert_eq!((browser.open, browser.selected), (None, 0));
+    }
+
+    #[test]
+    fn continue_picks_up_after_the_last_puzzle() {
+        let mut browser = PackBrowser::campaign();
+        assert_eq!(browser.continue_target(), None);
+        browser.progress.last = Some(PackRef {
+            pack: "1-first-steps".to_string(),
+            index: 4,
+        });
+        solve(&mut browser, 0, &[0, 1, 4]);
+        let target = |browser: &PackBrowser| browser.continue_target().map(|r| r.index);
+        assert_eq!(target(&browser), Some(5));
+        solve(&mut browser, 0, &[5]);
+        assert_eq!(target(&browser), Some(2));
+        solve(&mut browser, 0, &[2, 3]);
+        assert_eq!(
+            browser.continue_target(),
+            Some(PackRef {
+                pack: "2-warming-up".to_string(),
+                index: 0
+            })
+        );
+    }
+
+    #[test]
+    fn progress_round_trips() {
+        let mut progress = PackProgress::default();
+        progress.mark_solved(&PackRef {
+            pack: "1-first-steps".to_string(),
+            index: 3,
+        });
+        progress.last = Some(PackRef {
+            pack: "1-first-steps".to_string(),
+            index: 3,
+        });
+        let json = serde_json::to_string(&progress).unwrap();
+        assert_eq!(PackProgress::from_json(&json).unwrap(), progress);
+        assert!(PackProgress::from_json("{\"version\": 2}").is_err());
+    }
+}
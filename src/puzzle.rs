@@ -1,75 +1,574 @@
-use crate::state::{Cell, Difficulty};
-
-pub fn generate_puzzle(difficulty: Difficulty) -> [[Cell; 9]; 9] {
-    let mut board = generate_complete_board();
-    let cells_to_remove = match difficulty {
-        Difficulty::Easy => 45,
-        Difficulty::Medium => 51,
-        Difficulty::Hard => 55,
-        Difficulty::Expert => 60,
-    };
-    remove_cells(&mut board, cells_to_remove);
-    board
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    import::cell_name,
+    state::{Cell, Difficulty},
+};
+
+pub type Grid = Vec<Vec<Cell>>;
+
+/// The completed grid a puzzle was generated from.
+pub type Solution = Vec<Vec<u8>>;
+
+/// How a grid is cut into boxes: `box_rows` x `box_cols` boxes tile a
+/// square grid whose side is their product, so 2x3 boxes make a 6x6 grid.
+/// Stored as its label, e.g. `"6x6"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct GridShape {
+    pub box_rows: usize,
+    pub box_cols: usize,
 }
 
-fn generate_complete_board() -> [[Cell; 9]; 9] {
-    let mut board = [[Cell::empty(); 9]; 9];
-    fill_cell(&mut board, 0, 0);
-    board
+impl GridShape {
+    pub const CLASSIC: Self = Self::new(3, 3);
+
+    pub const ALL: [Self; 5] = [
+        Self::new(2, 2),
+        Self::new(2, 3),
+        Self::CLASSIC,
+        Self::new(3, 4),
+        Self::new(4, 4),
+    ];
+
+    const fn new(box_rows: usize, box_cols: usize) -> Self {
+        Self { box_rows, box_cols }
+    }
+
+    /// Rows, columns and digits in the grid.
+    pub fn size(self) -> usize {
+        self.box_rows * self.box_cols
+    }
+
+    pub fn cells(self) -> usize {
+        self.size() * self.size()
+    }
+
+    pub fn box_index(self, row: usize, col: usize) -> usize {
+        (row / self.box_rows) * (self.size() / self.box_cols) + col / self.box_cols
+    }
+
+    pub fn empty_grid(self) -> Grid {
+        vec![vec![Cell::empty(); self.size()]; self.size()]
+    }
+
+    /// The character shown for `digit`: `1`-`9` then `A`, `B`, ... on
+    /// 12x12, and hexadecimal `0`-`F` on 16x16.
+    pub fn symbol(self, digit: u8) -> char {
+        let index = if self.size() == 16 { digit - 1 } else { digit };
+        char::from_digit(index as u32, 36).map_or('?', |c| c.to_ascii_uppercase())
+    }
+
+    /// The digit typed as `c`, in either case, if this grid uses it.
+    pub fn parse_symbol(self, c: char) -> Option<u8> {
+        let index = c.to_digit(36)? as u8;
+        let digit = if self.size() == 16 { index + 1 } else { index };
+        (1..=self.size() as u8).contains(&digit).then_some(digit)
+    }
+
+    /// `1-9`, `1-C` or `0-F`, for help and footer text.
+    pub fn symbol_range(self) -> String {
+        format!("{}-{}", self.symbol(1), self.symbol(self.size() as u8))
+    }
+
+    pub fn cycle(self, forward: bool) -> Self {
+        let len = Self::ALL.len();
+        let idx = Self::ALL.iter().position(|s| *s == self).unwrap_or(0);
+        let next = if forward { idx + 1 } else { idx + len - 1 };
+        Self::ALL[next % len]
+    }
 }
 
-fn fill_cell(board: &mut [[Cell; 9]; 9], row: usize, col: usize) -> bool {
-    if row == 9 {
-        return true;
+impl Default for GridShape {
+    fn default() -> Self {
+        Self::CLASSIC
     }
+}
 
-    let next_row = if col == 8 { row + 1 } else { row };
-    let next_col = if col == 8 { 0 } else { col + 1 };
+impl std::fmt::Display for GridShape {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{0}x{0}", self.size())
+    }
+}
 
-    let mut numbers: Vec<u8> = (1..=9).collect();
-    shuffle(&mut numbers);
+impl TryFrom<String> for GridShape {
+    type Error = String;
 
-    for num in numbers {
-        if is_safe(board, row, col, num) {
-            board[row][col].value = Some(num);
-            board[row][col].given = true;
-            if fill_cell(board, next_row, next_col) {
-                return true;
+    fn try_from(label: String) -> Result<Self, Self::Error> {
+        Self::ALL
+            .into_iter()
+            .find(|shape| shape.to_string() == label)
+            .ok_or_else(|| format!("unsupported grid size `{label}`"))
+    }
+}
+
+impl From<GridShape> for String {
+    fn from(shape: GridShape) -> Self {
+        shape.to_string()
+    }
+}
+
+/// The rules played on top of rows, columns and boxes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+pub enum Variant {
+    #[default]
+    Classic,
+    /// Cages of cells whose digits don't repeat and add up to a given sum.
+    Killer,
+    /// Both main diagonals hold every digit once.
+    SudokuX,
+    /// Four extra box-sized windows hold every digit once.
+    Windoku,
+    /// Irregular connected regions take the place of the boxes.
+    Jigsaw,
+}
+
+impl Variant {
+    pub const ALL: [Self; 5] = [
+        Self::Classic,
+        Self::Killer,
+        Self::SudokuX,
+        Self::Windoku,
+        Self::Jigsaw,
+    ];
+
+    /// Windoku's windows only fit grids with room for two boxes and three
+    /// separating lines across and down.
+    pub fn supports(self, shape: GridShape) -> bool {
+        match self {
+            Variant::Windoku => {
+                shape.box_rows * 2 + 2 <= shape.size() && shape.box_cols * 2 + 2 <= shape.size()
             }
-            board[row][col].value = None;
-            board[row][col].given = false;
+            _ => true,
         }
     }
 
-    false
+    /// The next variant `shape` supports.
+    pub fn cycle(self, forward: bool, shape: GridShape) -> Self {
+        let len = Self::ALL.len();
+        let mut idx = Self::ALL.iter().position(|v| *v == self).unwrap_or(0);
+        // Classic fits every grid, so this always stops.
+        loop {
+            idx = if forward { idx + 1 } else { idx + len - 1 };
+            let next = Self::ALL[idx % len];
+            if next.supports(shape) {
+                return next;
+            }
+        }
+    }
 }
 
-fn is_safe(board: &[[Cell; 9]; 9], row: usize, col: usize, num: u8) -> bool {
-    #[allow(clippy::needless_range_loop)]
-    for i in 0..9 {
-        if board[row][i].value == Some(num) {
-            return false;
+impl std::fmt::Display for Variant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Variant::Classic => write!(f, "Classic"),
+            Variant::Killer => write!(f, "Killer"),
+            Variant::SudokuX => write!(f, "Sudoku-X"),
+            Variant::Windoku => write!(f, "Windoku"),
+            Variant::Jigsaw => write!(f, "Jigsaw"),
         }
     }
+}
 
-    #[allow(clippy::needless_range_loop)]
-    for i in 0..9 {
-        if board[i][col].value == Some(num) {
-            return false;
+/// Cells that must hold every digit once, as `(row, col)` positions.
+pub type Region = Vec<(usize, usize)>;
+
+/// The rows, columns and boxes, then the variant's extra regions. Jigsaw
+/// games pass their own `boxes`; empty means the shape's rectangular ones.
+pub fn regions(shape: GridShape, variant: Variant, boxes: &[Region]) -> Vec<Region> {
+    let size = shape.size();
+    let mut regions = Vec::with_capacity(size * 3);
+    for i in 0..size {
+        regions.push((0..size).map(|j| (i, j)).collect());
+        regions.push((0..size).map(|j| (j, i)).collect());
+    }
+    if boxes.is_empty() {
+        regions.extend(standard_boxes(shape));
+    } else {
+        regions.extend(boxes.iter().cloned());
+    }
+    regions.extend(extra_regions(shape, variant));
+    regions
+}
+
+/// The shape's `box_rows` x `box_cols` boxes in reading order.
+pub fn standard_boxes(shape: GridShape) -> Vec<Region> {
+    let size = shape.size();
+    let boxes_across = size / shape.box_cols;
+    (0..size)
+        .map(|i| {
+            let (box_row, box_col) = (
+                (i / boxes_across) * shape.box_rows,
+                (i % boxes_across) * shape.box_cols,
+            );
+            (0..size)
+                .map(|j| (box_row + j / shape.box_cols, box_col + j % shape.box_cols))
+                .collect()
+        })
+        .collect()
+}
+
+/// Sudoku-X's two diagonals, or Windoku's four windows: box-sized blocks
+/// one cell in from each corner. Empty for the other variants.
+pub fn extra_regions(shape: GridShape, variant: Variant) -> Vec<Region> {
+    let size = shape.size();
+    match variant {
+        Variant::SudokuX => vec![
+            (0..size).map(|i| (i, i)).collect(),
+            (0..size).map(|i| (i, size - 1 - i)).collect(),
+        ],
+        Variant::Windoku if variant.supports(shape) => {
+            let (rows, cols) = (shape.box_rows, shape.box_cols);
+            let mut windows = Vec::new();
+            for top in [1, size - 1 - rows] {
+                for left in [1, size - 1 - cols] {
+                    windows.push(
+                        (0..size)
+                            .map(|j| (top + j / cols, left + j % cols))
+                            .collect(),
+                    );
+                }
+            }
+            windows
         }
+        _ => Vec::new(),
     }
+}
+
+/// A Killer cage: `(row, col)` cells in reading order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cage {
+    pub sum: u32,
+    pub cells: Vec<(usize, usize)>,
+}
 
-    let box_row = (row / 3) * 3;
-    let box_col = (col / 3) * 3;
-    for i in 0..3 {
-        for j in 0..3 {
-            if board[box_row + i][box_col + j].value == Some(num) {
-                return false;
+/// An extra rule on a few cells, as found in imported puzzles.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Constraint {
+    /// Digits rise along the cells from the bulb, the first one.
+    Thermo(Vec<(usize, usize)>),
+    /// The digits along the shaft add up to the one in the circle; they may
+    /// repeat unless a region rules it out.
+    Arrow {
+        circle: (usize, usize),
+        shaft: Vec<(usize, usize)>,
+    },
+    /// The two digits are consecutive.
+    WhiteDot([(usize, usize); 2]),
+    /// One digit is double the other.
+    BlackDot([(usize, usize); 2]),
+    /// The two digits add up to 5.
+    V([(usize, usize); 2]),
+    /// The two digits add up to 10.
+    X([(usize, usize); 2]),
+}
+
+impl Constraint {
+    pub fn cells(&self) -> Vec<(usize, usize)> {
+        match self {
+            Constraint::Thermo(cells) => cells.clone(),
+            Constraint::Arrow { circle, shaft } => {
+                [*circle].into_iter().chain(shaft.clone()).collect()
             }
+            Constraint::WhiteDot(pair)
+            | Constraint::BlackDot(pair)
+            | Constraint::V(pair)
+            | Constraint::X(pair) => pair.to_vec(),
         }
     }
 
-    true
+    /// Checks the constraint's shape on a `size`x`size` grid: its cells are
+    /// on the grid, each next to the one before and none of them twice, a
+    /// thermometer has 2 to `size` cells and an arrow has a shaft.
+    pub fn validate(&self, size: usize) -> Result<(), String> {
+        let what = match self {
+            Constraint::Thermo(_) => "a thermometer",
+            Constraint::Arrow { .. } => "an arrow",
+            _ => "a pair",
+        };
+        let cells = self.cells();
+        if cells.iter().any(|&(row, col)| row >= size || col >= size) {
+            return Err(format!("{what} leaves the {size}x{size} grid"));
+        }
+        for (i, pair) in cells.windows(2).enumerate() {
+            let (a, b) = (pair[0], pair[1]);
+            if a.0.abs_diff(b.0) + a.1.abs_diff(b.1) != 1 {
+                return Err(format!(
+                    "{} and {} in {what} are not side by side",
+                    cell_name(a),
+                    cell_name(b)
+                ));
+            }
+            if cells[..i].contains(&b) {
+                return Err(format!("{} is in {what} twice", cell_name(b)));
+            }
+        }
+        match self {
+            Constraint::Thermo(cells) if cells.len() < 2 || cells.len() > size => {
+                Err(format!("a thermometer needs 2 to {size} cells"))
+            }
+            Constraint::Arrow { shaft, .. } if shaft.is_empty() => {
+                Err("an arrow needs a shaft of at least one cell".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether the digits placed so far already break the constraint, given
+    /// each cell's digit, if it has one.
+    pub fn is_broken(&self, size: usize, digit: impl Fn((usize, usize)) -> Option<u8>) -> bool {
+        match self {
+            Constraint::Thermo(cells) => {
+                let last = cells.len().saturating_sub(1);
+                let placed: Vec<(usize, usize)> = cells
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, &cell)| digit(cell).map(|d| (i, d as usize)))
+                    .collect();
+                // Each digit leaves room to rise by one a cell on either
+                // side, and later digits are at least that much higher.
+                placed.iter().any(|&(i, d)| d <= i || d + (last - i) > size)
+                    || placed
+                        .windows(2)
+                        .any(|w| w[1].1 < w[0].1 + (w[1].0 - w[0].0))
+            }
+            Constraint::Arrow { circle, shaft } => {
+                let (mut total, mut empty) = (0, 0);
+                for &cell in shaft {
+                    match digit(cell) {
+                        Some(d) => total += d as usize,
+                        None => empty += 1,
+                    }
+                }
+                match digit(*circle) {
+                    Some(target) => {
+                        let target = target as usize;
+                        total + empty > target || total + empty * size < target
+                    }
+                    None => total + empty > size,
+                }
+            }
+            Constraint::WhiteDot(pair)
+            | Constraint::BlackDot(pair)
+            | Constraint::V(pair)
+            | Constraint::X(pair) => match (digit(pair[0]), digit(pair[1])) {
+                (Some(a), Some(b)) => !self.pair_holds(a, b),
+                (Some(d), None) | (None, Some(d)) => {
+                    !(1..=size as u8).any(|other| self.pair_holds(d, other))
+                }
+                (None, None) => false,
+            },
+        }
+    }
+
+    fn pair_holds(&self, a: u8, b: u8) -> bool {
+        match self {
+            Constraint::WhiteDot(_) => a.abs_diff(b) == 1,
+            Constraint::BlackDot(_) => a == b * 2 || b == a * 2,
+            Constraint::V(_) => a + b == 5,
+            Constraint::X(_) => a + b == 10,
+            Constraint::Thermo(_) | Constraint::Arrow { .. } => true,
+        }
+    }
+}
+
+/// Cells removed from a full grid, scaled from the 9x9 counts by grid area.
+/// Killer sums carry most of what givens would, so far fewer are kept.
+fn cells_to_remove(difficulty: Difficulty, shape: GridShape, variant: Variant) -> usize {
+    let per_81 = match (variant, difficulty) {
+        (Variant::Killer, Difficulty::Easy) => 63,
+        (Variant::Killer, Difficulty::Medium) => 69,
+        (Variant::Killer, Difficulty::Hard) => 75,
+        (Variant::Killer, Difficulty::Expert) => 81,
+        (_, Difficulty::Easy) => 45,
+        (_, Difficulty::Medium) => 51,
+        (_, Difficulty::Hard) => 55,
+        (_, Difficulty::Expert) => 60,
+    };
+    shape.cells() * per_81 / 81
+}
+
+/// The number of givens a puzzle aims for; uniqueness can keep a few more.
+pub fn target_givens(difficulty: Difficulty, shape: GridShape, variant: Variant) -> usize {
+    shape.cells() - cells_to_remove(difficulty, shape, variant)
+}
+
+/// A generated puzzle and the layout it is played on.
+#[derive(Debug, Clone)]
+pub struct Puzzle {
+    pub grid: Grid,
+    pub solution: Solution,
+    /// Killer cages; empty for other variants.
+    pub cages: Vec<Cage>,
+    /// Jigsaw boxes; empty for the shape's rectangular ones.
+    pub boxes: Vec<Region>,
+    /// Thermometers, arrows and the like, which only imported puzzles have.
+    pub constraints: Vec<Constraint>,
+}
+
+/// Generates a puzzle with its solution. Killer cages are cut from the
+/// solved grid before any givens are removed, so they help keep the
+/// solution unique; the other variants have none.
+pub fn generate_puzzle(difficulty: Difficulty, shape: GridShape, variant: Variant) -> Puzzle {
+    let (boxes, mut board) = match variant {
+        Variant::Jigsaw => generate_jigsaw(shape),
+        _ => (
+            Vec::new(),
+            generate_complete_board(shape, &regions(shape, variant, &[])),
+        ),
+    };
+    let regions = regions(shape, variant, &boxes);
+    let solution = solution_of(&board);
+    let cages = match variant {
+        Variant::Killer => generate_cages(&solution, shape),
+        _ => Vec::new(),
+    };
+    let count = cells_to_remove(difficulty, shape, variant);
+    remove_cells(&mut board, &regions, &cages, count);
+    Puzzle {
+        grid: board,
+        solution,
+        cages,
+        boxes,
+        constraints: Vec::new(),
+    }
+}
+
+fn solution_of(board: &Grid) -> Solution {
+    board
+        .iter()
+        .map(|row| row.iter().map(|cell| cell.value.unwrap_or(0)).collect())
+        .collect()
+}
+
+/// Search steps one attempt at filling a grid may take. A random fill
+/// either finishes quickly or wanders a dead end for a very long time, so a
+/// stuck attempt is dropped and a fresh one started.
+const FILL_BUDGET: u64 = 20_000;
+
+fn generate_complete_board(shape: GridShape, regions: &[Region]) -> Grid {
+    loop {
+        if let Some(board) = fill_board(shape, regions) {
+            return board;
+        }
+    }
+}
+
+/// One attempt at filling an empty grid at random, `None` if it got stuck.
+fn fill_board(shape: GridShape, regions: &[Region]) -> Option<Grid> {
+    let mut search =
+        Search::new(regions, &[], &[], &shape.empty_grid(), false).expect("empty grid");
+    search.budget = FILL_BUDGET;
+    search.run(1, true);
+    let mut board = shape.empty_grid();
+    for (cell, digit) in board.iter_mut().flatten().zip(search.first?) {
+        *cell = Cell::given(digit);
+    }
+    Some(board)
+}
+
+/// Rounds of reshaping the boxes and refilling the grid to suit them.
+const LAYOUT_ROUNDS: usize = 6;
+
+/// A Jigsaw layout and a full grid that fits it. A random irregular layout
+/// often can't be filled at all, so the boxes are instead reshaped around a
+/// solved grid, which is then refilled to give the next round new digits to
+/// work with. A refill that gets stuck keeps the grid it already has.
+fn generate_jigsaw(shape: GridShape) -> (Vec<Region>, Grid) {
+    let mut boxes = standard_boxes(shape);
+    let mut board = generate_complete_board(shape, &regions(shape, Variant::Jigsaw, &boxes));
+    for round in 0..LAYOUT_ROUNDS {
+        if round > 0 {
+            match fill_board(shape, &regions(shape, Variant::Jigsaw, &boxes)) {
+                Some(refilled) => board = refilled,
+                None => break,
+            }
+        }
+        boxes = reshape_boxes(&boxes, &solution_of(&board));
+    }
+    (boxes, board)
+}
+
+/// Swaps tried per cell when reshaping the boxes into a Jigsaw layout.
+const LAYOUT_SWAPS_PER_CELL: usize = 20;
+
+/// Swaps cells holding the same digit across the edges of the boxes, so
+/// every box keeps each digit of `solution` once and stays connected.
+fn reshape_boxes(boxes: &[Region], solution: &Solution) -> Vec<Region> {
+    use rand::seq::SliceRandom;
+
+    let mut rng = rand::thread_rng();
+    let size = solution.len();
+    let mut owner = vec![vec![0; size]; size];
+    for (b, cells) in boxes.iter().enumerate() {
+        for &(row, col) in cells {
+            owner[row][col] = b;
+        }
+    }
+    let cells: Vec<(usize, usize)> = (0..size * size).map(|i| (i / size, i % size)).collect();
+
+    for _ in 0..cells.len() * LAYOUT_SWAPS_PER_CELL {
+        // A cell on the edge of its box moves into the box next door...
+        let &(row, col) = cells.choose(&mut rng).expect("cells");
+        let from = owner[row][col];
+        let across: Vec<usize> = neighbours(row, col, size)
+            .map(|(r, c)| owner[r][c])
+            .filter(|&b| b != from)
+            .collect();
+        let Some(&to) = across.choose(&mut rng) else {
+            continue;
+        };
+        // ...in exchange for that box's cell with the same digit, if it
+        // touches the first box.
+        let Some(&(back_row, back_col)) = cells
+            .iter()
+            .find(|&&(r, c)| owner[r][c] == to && solution[r][c] == solution[row][col])
+        else {
+            continue;
+        };
+        let touches = neighbours(back_row, back_col, size)
+            .any(|(r, c)| owner[r][c] == from && (r, c) != (row, col));
+        if !touches {
+            continue;
+        }
+        owner[row][col] = to;
+        owner[back_row][back_col] = from;
+        if !is_connected(&owner, from) || !is_connected(&owner, to) {
+            owner[row][col] = from;
+            owner[back_row][back_col] = to;
+        }
+    }
+
+    let mut boxes = vec![Vec::new(); size];
+    for &(row, col) in &cells {
+        boxes[owner[row][col]].push((row, col));
+    }
+    boxes.sort();
+    boxes
+}
+
+/// Whether the cells `owner` gives to `region` touch one another.
+fn is_connected(owner: &[Vec<usize>], region: usize) -> bool {
+    let size = owner.len();
+    let cells = (0..size * size).map(|i| (i / size, i % size));
+    let mut members = cells.filter(|&(r, c)| owner[r][c] == region);
+    let Some(start) = members.next() else {
+        return true;
+    };
+    let total = members.count() + 1;
+    let mut seen = vec![vec![false; size]; size];
+    seen[start.0][start.1] = true;
+    let mut stack = vec![start];
+    let mut reached = 0;
+    while let Some((row, col)) = stack.pop() {
+        reached += 1;
+        for (r, c) in neighbours(row, col, size) {
+            if owner[r][c] == region && !std::mem::replace(&mut seen[r][c], true) {
+                stack.push((r, c));
+            }
+        }
+    }
+    reached == total
 }
 
 #[allow(clippy::ptr_arg)]
@@ -78,18 +577,78 @@ fn shuffle<T>(vec: &mut Vec<T>) {
     vec.shuffle(&mut rand::thread_rng());
 }
 
-fn remove_cells(board: &mut [[Cell; 9]; 9], cells_to_remove: usize) {
+/// Cells a Killer cage may grow to.
+const CAGE_CELLS: std::ops::RangeInclusive<usize> = 2..=4;
+
+/// Cuts the solved grid into connected cages without repeated digits. Each
+/// cage grows from a random cell towards a random size; one that runs out of
+/// room stays smaller, down to a single cell.
+fn generate_cages(solution: &Solution, shape: GridShape) -> Vec<Cage> {
+    use rand::{seq::SliceRandom, Rng};
+
+    let mut rng = rand::thread_rng();
+    let size = shape.size();
+    let mut taken = vec![vec![false; size]; size];
+    let mut starts: Vec<(usize, usize)> =
+        (0..shape.cells()).map(|i| (i / size, i % size)).collect();
+    starts.shuffle(&mut rng);
+
+    let mut cages = Vec::new();
+    for (row, col) in starts {
+        if taken[row][col] {
+            continue;
+        }
+        taken[row][col] = true;
+        let target = rng.gen_range(CAGE_CELLS);
+        let mut cells = vec![(row, col)];
+        let mut digits = bit(solution[row][col]);
+        while cells.len() < target {
+            let options: Vec<(usize, usize)> = cells
+                .iter()
+                .flat_map(|&(r, c)| neighbours(r, c, size))
+                .filter(|&(r, c)| !taken[r][c] && digits & bit(solution[r][c]) == 0)
+                .collect();
+            let Some(&(r, c)) = options.choose(&mut rng) else {
+                break;
+            };
+            taken[r][c] = true;
+            digits |= bit(solution[r][c]);
+            cells.push((r, c));
+        }
+        cells.sort();
+        let sum = cells.iter().map(|&(r, c)| solution[r][c] as u32).sum();
+        cages.push(Cage { sum, cells });
+    }
+    cages.sort_by_key(|cage| cage.cells[0]);
+    cages
+}
+
+/// The cells above, below, left and right of `(row, col)`.
+fn neighbours(row: usize, col: usize, size: usize) -> impl Iterator<Item = (usize, usize)> {
+    [
+        row.checked_sub(1).map(|r| (r, col)),
+        (row + 1 < size).then_some((row + 1, col)),
+        col.checked_sub(1).map(|c| (row, c)),
+        (col + 1 < size).then_some((row, col + 1)),
+    ]
+    .into_iter()
+    .flatten()
+}
+
+fn remove_cells(board: &mut Grid, regions: &[Region], cages: &[Cage], cells_to_remove: usize) {
+    let size = board.len();
     let mut indices: Vec<(usize, usize)> = Vec::new();
-    for i in 0..9 {
-        for j in 0..9 {
+    for i in 0..size {
+        for j in 0..size {
             indices.push((i, j));
         }
     }
     shuffle(&mut indices);
 
     let mut removed = 0;
+    let mut steps_left = REMOVAL_TOTAL_BUDGET;
     for (row, col) in indices {
-        if removed >= cells_to_remove {
+        if removed >= cells_to_remove || steps_left == 0 {
             break;
         }
 
@@ -97,7 +656,10 @@ fn remove_cells(board: &mut [[Cell; 9]; 9], cells_to_remove: usize) {
         board[row][col].value = None;
         board[row][col].given = false;
 
-        if count_solutions(board) == 1 {
+        let mut budget = REMOVAL_BUDGET.min(steps_left);
+        let unique = count_solutions(board, regions, cages, &mut budget) == 1;
+        steps_left -= REMOVAL_BUDGET.min(steps_left) - budget;
+        if unique {
             removed += 1;
         } else {
             board[row][col].value = old_value;
@@ -106,107 +668,398 @@ fn remove_cells(board: &mut [[Cell; 9]; 9], cells_to_remove: usize) {
     }
 }
 
-fn count_solutions(board: &[[Cell; 9]; 9]) -> u32 {
-    let mut board_copy = *board;
-    let mut count = 0;
-    let mut row = 0;
-    let mut col = 0;
-    count_solutions_helper(&mut board_copy, &mut row, &mut col, &mut count, 2);
-    count
-}
+/// Search steps a uniqueness check may take while removing cells. A cell
+/// whose removal can't be shown to keep the solution unique within it stays
+/// a given; only the largest grids come near it.
+const REMOVAL_BUDGET: u64 = 5_000;
+
+/// Search steps all of a puzzle's uniqueness checks may take together, which
+/// keeps the largest Killer grids from taking seconds to generate.
+const REMOVAL_TOTAL_BUDGET: u64 = 50_000;
 
-fn count_solutions_helper(
-    board: &mut [[Cell; 9]; 9],
-    row: &mut usize,
-    col: &mut usize,
-    count: &mut u32,
-    limit: u32,
-) {
-    if *count >= limit {
-        return;
+/// Counts the board's solutions, stopping at two, or when the search has
+/// used up `budget`, which is left holding the steps not taken.
+fn count_solutions(board: &Grid, regions: &[Region], cages: &[Cage], budget: &mut u64) -> u32 {
+    match Search::new(regions, cages, &[], board, false) {
+        Some(mut search) => {
+            search.budget = *budget;
+            let count = search.run(2, false);
+            *budget = search.budget;
+            count
+        }
+        None => 0,
     }
+}
+
+/// Solves the puzzle defined by the givens alone, ignoring entered digits.
+/// Used when a puzzle arrives without its solution; play checks against the
+/// solution cached in `GameState` instead.
+pub fn get_solution(board: &Grid, regions: &[Region], cages: &[Cage]) -> Option<Solution> {
+    let mut search = Search::new(regions, cages, &[], board, true)?;
+    search.run(1, false);
+    let digits = search.first?;
+    Some(digits.chunks(board.len()).map(<[u8]>::to_vec).collect())
+}
 
-    if *row == 9 {
-        *count += 1;
-        return;
+/// Search steps an imported puzzle's uniqueness check may take.
+const IMPORT_BUDGET: u64 = 2_000_000;
+
+/// The one solution of a puzzle typed up by hand, or why it can't be played.
+pub fn unique_solution(
+    board: &Grid,
+    regions: &[Region],
+    constraints: &[Constraint],
+) -> Result<Solution, &'static str> {
+    let mut search =
+        Search::new(regions, &[], constraints, board, true).ok_or("the givens break the rules")?;
+    search.budget = IMPORT_BUDGET;
+    let count = search.run(2, false);
+    if search.budget == 0 {
+        return Err("the puzzle is too hard to check");
+    }
+    match (count, search.first) {
+        (1, Some(digits)) => Ok(digits.chunks(board.len()).map(<[u8]>::to_vec).collect()),
+        (0, _) => Err("the puzzle has no solution"),
+        _ => Err("the puzzle has more than one solution"),
     }
+}
 
-    let next_row = if *col == 8 { *row + 1 } else { *row };
-    let next_col = if *col == 8 { 0 } else { *col + 1 };
+fn bit(digit: u8) -> u32 {
+    1 << (digit - 1)
+}
 
-    if board[*row][*col].value.is_some() {
-        *row = next_row;
-        *col = next_col;
-        count_solutions_helper(board, &mut *row, &mut *col, count, limit);
-        *row = if *col == 0 { *row - 1 } else { *row };
-        *col = if *col == 0 { 8 } else { *col - 1 };
-    } else {
-        for num in 1..=10u8 {
-            let num = num % 10;
-            if num == 0 {
+/// Backtracking over the grid's units that always branches on the fewest
+/// options: the cell with the fewest candidates, or the digit with the
+/// fewest places left in some unit.
+struct Search {
+    size: usize,
+    /// Row-major digits, 0 for empty.
+    values: Vec<u8>,
+    units: Vec<Vec<usize>>,
+    cell_units: Vec<Vec<usize>>,
+    used: Vec<u32>,
+    cages: Vec<CageProgress>,
+    cell_cage: Vec<Option<usize>>,
+    constraints: Vec<Constraint>,
+    cell_constraints: Vec<Vec<usize>>,
+    first: Option<Vec<u8>>,
+    /// Steps left before `run` gives up and reports `limit` solutions.
+    budget: u64,
+}
+
+/// A cage's digits so far, for ruling out the ones its sum can't take.
+struct CageProgress {
+    used: u32,
+    /// The sum still to be made up by the empty cells.
+    remaining: u32,
+    empty: u32,
+}
+
+impl Search {
+    /// `None` when the board's digits already break a rule.
+    fn new(
+        regions: &[Region],
+        cages: &[Cage],
+        constraints: &[Constraint],
+        board: &Grid,
+        givens_only: bool,
+    ) -> Option<Self> {
+        let size = board.len();
+        let units: Vec<Vec<usize>> = regions
+            .iter()
+            .map(|region| region.iter().map(|&(row, col)| row * size + col).collect())
+            .collect();
+        let mut cell_units = vec![Vec::new(); size * size];
+        for (u, unit) in units.iter().enumerate() {
+            for &i in unit {
+                cell_units[i].push(u);
+            }
+        }
+        let mut cell_cage = vec![None; size * size];
+        for (c, cage) in cages.iter().enumerate() {
+            for &(row, col) in &cage.cells {
+                cell_cage[row * size + col] = Some(c);
+            }
+        }
+        let mut cell_constraints = vec![Vec::new(); size * size];
+        for (k, constraint) in constraints.iter().enumerate() {
+            for (row, col) in constraint.cells() {
+                cell_constraints[row * size + col].push(k);
+            }
+        }
+        let mut search = Self {
+            size,
+            values: vec![0; size * size],
+            used: vec![0; units.len()],
+            units,
+            cell_units,
+            cages: cages
+                .iter()
+                .map(|cage| CageProgress {
+                    used: 0,
+                    remaining: cage.sum,
+                    empty: cage.cells.len() as u32,
+                })
+                .collect(),
+            cell_cage,
+            constraints: constraints.to_vec(),
+            cell_constraints,
+            first: None,
+            budget: u64::MAX,
+        };
+
+        for (i, cell) in board.iter().flatten().enumerate() {
+            if givens_only && !cell.given {
                 continue;
             }
-            if is_safe(board, *row, *col, num) {
-                board[*row][*col].value = Some(num);
-                *row = next_row;
-                *col = next_col;
-                count_solutions_helper(board, &mut *row, &mut *col, count, limit);
-                *row = if *col == 0 { *row - 1 } else { *row };
-                *col = if *col == 0 { 8 } else { *col - 1 };
-                board[*row][*col].value = None;
+            if let Some(digit) = cell.value {
+                if search.candidates(i) & bit(digit) == 0 {
+                    return None;
+                }
+                search.place(i, digit);
             }
         }
+        Some(search)
     }
-}
 
-pub fn get_solution(board: &[[Cell; 9]; 9]) -> Option<[[u8; 9]; 9]> {
-    let mut solution = [[0u8; 9]; 9];
-    let mut board_copy = *board;
+    fn all_digits(&self) -> u32 {
+        (1u32 << self.size) - 1
+    }
 
-    if solve_board(&mut board_copy) {
-        for row in 0..9 {
-            for col in 0..9 {
-                solution[row][col] = board_copy[row][col].value?;
+    fn candidates(&self, i: usize) -> u32 {
+        let mask = self.unit_candidates(i);
+        let mask = match self.cell_cage[i] {
+            Some(c) => mask & self.cage_options(c),
+            None => mask,
+        };
+        self.constraint_options(i, mask)
+    }
+
+    fn unit_candidates(&self, i: usize) -> u32 {
+        self.cell_units[i]
+            .iter()
+            .fold(self.all_digits(), |mask, &u| mask & !self.used[u])
+    }
+
+    /// The digits an empty cell of cage `c` can take and still leave a sum
+    /// its other empty cells can make with digits the cage hasn't used.
+    fn cage_options(&self, c: usize) -> u32 {
+        let cage = &self.cages[c];
+        let free = self.all_digits() & !cage.used;
+        let mut options = 0;
+        for digit in (1..=self.size as u8).filter(|&d| free & bit(d) != 0) {
+            let Some(rest) = cage.remaining.checked_sub(digit as u32) else {
+                break;
+            };
+            let others = free & !bit(digit);
+            let count = cage.empty - 1;
+            if extreme_sum(others, count, false).is_some_and(|least| least <= rest)
+                && extreme_sum(others, count, true).is_some_and(|most| rest <= most)
+            {
+                options |= bit(digit);
             }
         }
-        Some(solution)
-    } else {
-        None
+        options
     }
-}
 
-pub fn get_correct_value_for_cell(board: &[[Cell; 9]; 9], row: usize, col: usize) -> Option<u8> {
-    let solution = get_solution(board)?;
-    Some(solution[row][col])
-}
+    /// The digits of `mask` that cell `i` can take without breaking one of
+    /// its constraints, given the digits placed so far.
+    fn constraint_options(&self, i: usize, mask: u32) -> u32 {
+        let mut options = mask;
+        for &k in &self.cell_constraints[i] {
+            for digit in 1..=self.size as u8 {
+                if options & bit(digit) == 0 {
+                    continue;
+                }
+                let broken = self.constraints[k].is_broken(self.size, |(row, col)| {
+                    let j = row * self.size + col;
+                    if j == i {
+                        Some(digit)
+                    } else {
+                        Some(self.values[j]).filter(|&d| d != 0)
+                    }
+                });
+                if broken {
+                    options &= !bit(digit);
+                }
+            }
+        }
+        options
+    }
 
-fn solve_board(board: &mut [[Cell; 9]; 9]) -> bool {
-    solve_helper(board, 0, 0)
-}
+    fn place(&mut self, i: usize, digit: u8) {
+        self.values[i] = digit;
+        for &u in &self.cell_units[i] {
+            self.used[u] |= bit(digit);
+        }
+        if let Some(c) = self.cell_cage[i] {
+            let cage = &mut self.cages[c];
+            cage.used |= bit(digit);
+            cage.remaining -= digit as u32;
+            cage.empty -= 1;
+        }
+    }
 
-fn solve_helper(board: &mut [[Cell; 9]; 9], row: usize, col: usize) -> bool {
-    if row == 9 {
-        return true;
+    fn unplace(&mut self, i: usize, digit: u8) {
+        self.values[i] = 0;
+        for &u in &self.cell_units[i] {
+            self.used[u] &= !bit(digit);
+        }
+        if let Some(c) = self.cell_cage[i] {
+            let cage = &mut self.cages[c];
+            cage.used &= !bit(digit);
+            cage.remaining += digit as u32;
+            cage.empty += 1;
+        }
     }
 
-    let next_row = if col == 8 { row + 1 } else { row };
-    let next_col = if col == 8 { 0 } else { col + 1 };
+    /// Counts solutions up to `limit`, keeping the first one found. Running
+    /// out of budget counts as reaching the limit.
+    /// `shuffle` tries digits in random order, for generating grids.
+    fn run(&mut self, limit: u32, shuffle: bool) -> u32 {
+        if self.budget == 0 {
+            return limit;
+        }
+        self.budget -= 1;
+        let Some(mut options) = self.fewest_options() else {
+            if self.first.is_none() {
+                self.first = Some(self.values.clone());
+            }
+            return 1;
+        };
+        if shuffle {
+            self::shuffle(&mut options);
+        }
 
-    if board[row][col].value.is_some() {
-        return solve_helper(board, next_row, next_col);
+        let mut count = 0;
+        for (i, digit) in options {
+            self.place(i, digit);
+            count += self.run(limit - count, shuffle);
+            self.unplace(i, digit);
+            if count >= limit {
+                break;
+            }
+        }
+        count
     }
 
-    for num in 1..=9 {
-        if is_safe(board, row, col, num) {
-            board[row][col].value = Some(num);
-            if solve_helper(board, next_row, next_col) {
-                return true;
+    /// The `(cell, digit)` placements to branch on, empty at a dead end, or
+    /// `None` once the grid is full.
+    fn fewest_options(&self) -> Option<Vec<(usize, u8)>> {
+        // Every cell of a cage shares its options, so they're worked out once.
+        let cage_options: Vec<u32> = (0..self.cages.len())
+            .map(|c| self.cage_options(c))
+            .collect();
+        let mut candidates = vec![0u32; self.values.len()];
+        let mut best: Option<(usize, u32)> = None;
+        for (i, mask) in candidates.iter_mut().enumerate() {
+            if self.values[i] != 0 {
+                continue;
+            }
+            *mask = self.unit_candidates(i);
+            if let Some(c) = self.cell_cage[i] {
+                *mask &= cage_options[c];
+            }
+            *mask = self.constraint_options(i, *mask);
+            if best.is_none_or(|(_, b)| mask.count_ones() < b.count_ones()) {
+                best = Some((i, *mask));
+            }
+        }
+        let (cell, mask) = best?;
+        if mask.count_ones() > 1 {
+            // A digit with a single place left in a unit is forced, and one
+            // with no place at all is a dead end.
+            for (u, unit) in self.units.iter().enumerate() {
+                let (mut once, mut twice) = (0u32, 0u32);
+                for &i in unit {
+                    twice |= once & candidates[i];
+                    once |= candidates[i];
+                }
+                let missing = self.all_digits() & !self.used[u];
+                if missing & !once != 0 {
+                    return Some(Vec::new());
+                }
+                let single = missing & once & !twice;
+                if single != 0 {
+                    let digit = single.trailing_zeros() as u8 + 1;
+                    let i = unit.iter().find(|&&i| candidates[i] & bit(digit) != 0)?;
+                    return Some(vec![(*i, digit)]);
+                }
             }
-            board[row][col].value = None;
         }
+        let digits = (1..=self.size as u8).filter(|&d| mask & bit(d) != 0);
+        Some(digits.map(|d| (cell, d)).collect())
     }
+}
 
-    false
+/// The sum of the `count` smallest (or largest) digits in `mask`, if it has
+/// that many.
+fn extreme_sum(mask: u32, count: u32, largest: bool) -> Option<u32> {
+    if mask.count_ones() < count {
+        return None;
+    }
+    let digits = (1..=32).filter(|&d| mask & (1 << (d - 1)) != 0);
+    Some(if largest {
+        digits.rev().take(count as usize).sum()
+    } else {
+        digits.take(count as usize).sum()
+    })
+}
+
+/// Marks every filled cell whose digit repeats in one of its regions or its
+/// cage, and the filled cells of a cage that can no longer make its sum or
+/// of a constraint they already break.
+/// This only checks the rules; it never consults the solution.
+pub fn find_conflicts(
+    board: &Grid,
+    regions: &[Region],
+    cages: &[Cage],
+    constraints: &[Constraint],
+) -> Vec<Vec<bool>> {
+    let size = board.len();
+    let mut conflicts = vec![vec![false; size]; size];
+    let cage_units = cages.iter().map(|cage| &cage.cells);
+    for unit in regions.iter().chain(cage_units) {
+        for (i, &(row, col)) in unit.iter().enumerate() {
+            let Some(value) = board[row][col].value else {
+                continue;
+            };
+            for &(other_row, other_col) in &unit[i + 1..] {
+                if board[other_row][other_col].value == Some(value) {
+                    conflicts[row][col] = true;
+                    conflicts[other_row][other_col] = true;
+                }
+            }
+        }
+    }
+    for cage in cages {
+        let filled: Vec<(usize, usize)> = cage
+            .cells
+            .iter()
+            .copied()
+            .filter(|&(row, col)| board[row][col].value.is_some())
+            .collect();
+        let total: u32 = filled
+            .iter()
+            .filter_map(|&(row, col)| board[row][col].value)
+            .map(u32::from)
+            .sum();
+        // Every empty cell still needs at least a 1.
+        let empty = (cage.cells.len() - filled.len()) as u32;
+        if total + empty > cage.sum || (empty == 0 && total != cage.sum) {
+            for (row, col) in filled {
+                conflicts[row][col] = true;
+            }
+        }
+    }
+    for constraint in constraints {
+        if constraint.is_broken(size, |(row, col)| board[row][col].value) {
+            for (row, col) in constraint.cells() {
+                conflicts[row][col] |= board[row][col].value.is_some();
+            }
+        }
+    }
+    conflicts
 }
 
 pub fn apply_hint(state: &mut crate::state::GameState) -> bool {
@@ -215,83 +1068,61 @@ pub fn apply_hint(state: &mut crate::state::GameState) -> bool {
         return false;
     }
 
-    if let Some(correct_value) =
-        get_correct_value_for_cell(&state.grid, state.selection.row, state.selection.col)
-    {
-        state.selected_cell_mut().value = Some(correct_value);
-        true
-    } else {
-        false
-    }
+    let correct_value = state.solution[state.selection.row][state.selection.col];
+    state.selected_cell_mut().value = Some(correct_value);
+    true
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    fn count_givens(grid: &[[crate::state::Cell; 9]; 9]) -> usize {
+    fn count_givens(grid: &Grid) -> usize {
         grid.iter()
             .flat_map(|row| row.iter())
             .filter(|cell| cell.given)
             .count()
     }
 
-    fn is_valid_sudoku(grid: &[[crate::state::Cell; 9]; 9]) -> bool {
-        // Check rows
-        for row in 0..9 {
-            let mut seen = [false; 9];
-            for col in 0..9 {
-                if let Some(val) = grid[row][col].value {
-                    let idx = (val - 1) as usize;
-                    if seen[idx] {
-                        return false;
-                    }
-                    seen[idx] = true;
-                }
-            }
-        }
+    fn classic() -> Vec<Region> {
+        regions(GridShape::CLASSIC, Variant::Classic, &[])
+    }
 
-        // Check columns
-        for col in 0..9 {
-            let mut seen = [false; 9];
-            for row in 0..9 {
-                if let Some(val) = grid[row][col].value {
-                    let idx = (val - 1) as usize;
-                    if seen[idx] {
-                        return false;
-                    }
-                    seen[idx] = true;
-                }
-            }
-        }
+    fn unlimited_solutions(grid: &Grid, regions: &[Region], cages: &[Cage]) -> u32 {
+        let mut budget = u64::MAX;
+        count_solutions(grid, regions, cages, &mut budget)
+    }
 
-        // Check 3x3 boxes
-        for box_row in 0..3 {
-            for box_col in 0..3 {
-                let mut seen = [false; 9];
-                for row in (box_row * 3)..(box_row * 3 + 3) {
-                    for col in (box_col * 3)..(box_col * 3 + 3) {
-                        if let Some(val) = grid[row][col].value {
-                            let idx = (val - 1) as usize;
-                            if seen[idx] {
-                                return false;
-                            }
-                            seen[idx] = true;
-                        }
-                    }
+    fn is_valid_sudoku(grid: &Grid, shape: GridShape) -> bool {
+        let size = shape.size();
+        let mut seen = std::collections::HashSet::new();
+        for (row, cells) in grid.iter().enumerate() {
+            for (col, cell) in cells.iter().enumerate() {
+                let Some(val) = cell.value else {
+                    continue;
+                };
+                if !(1..=size as u8).contains(&val)
+                    || !seen.insert(("row", row, val))
+                    || !seen.insert(("col", col, val))
+                    || !seen.insert(("box", shape.box_index(row, col), val))
+                {
+                    return false;
                 }
             }
         }
-
         true
     }
 
     #[test]
     fn test_easy_difficulty_givens() {
-        let grid = generate_puzzle(crate::state::Difficulty::Easy);
+        let Puzzle { grid, .. } = generate_puzzle(
+            crate::state::Difficulty::Easy,
+            GridShape::CLASSIC,
+            Variant::Classic,
+        );
         let givens = count_givens(&grid);
         assert!(
-            givens >= 36 && givens <= 38,
+            (36..=38).contains(&givens),
             "Easy: expected 36-38 givens, got {}",
             givens
         );
@@ -299,10 +1130,14 @@ mod tests {
 
     #[test]
     fn test_medium_difficulty_givens() {
-        let grid = generate_puzzle(crate::state::Difficulty::Medium);
+        let Puzzle { grid, .. } = generate_puzzle(
+            crate::state::Difficulty::Medium,
+            GridShape::CLASSIC,
+            Variant::Classic,
+        );
         let givens = count_givens(&grid);
         assert!(
-            givens >= 30 && givens <= 32,
+            (30..=32).contains(&givens),
             "Medium: expected 30-32 givens, got {}",
             givens
         );
@@ -310,10 +1145,14 @@ mod tests {
 
     #[test]
     fn test_hard_difficulty_givens() {
-        let grid = generate_puzzle(crate::state::Difficulty::Hard);
+        let Puzzle { grid, .. } = generate_puzzle(
+            crate::state::Difficulty::Hard,
+            GridShape::CLASSIC,
+            Variant::Classic,
+        );
         let givens = count_givens(&grid);
         assert!(
-            givens >= 24 && givens <= 27,
+            (24..=27).contains(&givens),
             "Hard: expected 24-27 givens, got {}",
             givens
         );
@@ -323,10 +1162,14 @@ mod tests {
     fn test_expert_difficulty_givens() {
         // Expert aims for 17-22 givens, but may have more due to uniqueness constraint
         // The algorithm removes up to 60 cells, but only if solution remains unique
-        let grid = generate_puzzle(crate::state::Difficulty::Expert);
+        let Puzzle { grid, .. } = generate_puzzle(
+            crate::state::Difficulty::Expert,
+            GridShape::CLASSIC,
+            Variant::Classic,
+        );
         let givens = count_givens(&grid);
         assert!(
-            givens >= 17 && givens <= 27,
+            (17..=27).contains(&givens),
             "Expert: expected 17-27 givens (uniqueness constraint), got {}",
             givens
         );
@@ -340,9 +1183,10 @@ mod tests {
             crate::state::Difficulty::Hard,
             crate::state::Difficulty::Expert,
         ] {
-            let grid = generate_puzzle(*difficulty);
+            let Puzzle { grid, .. } =
+                generate_puzzle(*difficulty, GridShape::CLASSIC, Variant::Classic);
             assert!(
-                is_valid_sudoku(&grid),
+                is_valid_sudoku(&grid, GridShape::CLASSIC),
                 "Generated puzzle for {:?} is invalid",
                 difficulty
             );
@@ -350,9 +1194,19 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::needless_range_loop)]
     fn test_solution_is_valid() {
-        let grid = generate_puzzle(crate::state::Difficulty::Easy);
-        let solution = get_solution(&grid);
+        let Puzzle {
+            grid,
+            solution: cached,
+            ..
+        } = generate_puzzle(
+            crate::state::Difficulty::Easy,
+            GridShape::CLASSIC,
+            Variant::Classic,
+        );
+        let solution = get_solution(&grid, &classic(), &[]);
+        assert_eq!(solution, Some(cached));
         assert!(solution.is_some(), "Easy puzzle should have a solution");
         if let Some(sol) = solution {
             // Solution should be complete (all cells filled with 1-9)
@@ -360,7 +1214,7 @@ mod tests {
                 for col in 0..9 {
                     let val = sol[row][col];
                     assert!(
-                        val >= 1 && val <= 9,
+                        (1..=9).contains(&val),
                         "Solution should have valid values, got {} at [{},{}]",
                         val,
                         row,
@@ -374,8 +1228,12 @@ mod tests {
     #[test]
     fn test_unique_solution() {
         // Test that puzzles have unique solutions (or very close)
-        let grid = generate_puzzle(crate::state::Difficulty::Easy);
-        let solution_count = count_solutions(&grid);
+        let Puzzle { grid, .. } = generate_puzzle(
+            crate::state::Difficulty::Easy,
+            GridShape::CLASSIC,
+            Variant::Classic,
+        );
+        let solution_count = unlimited_solutions(&grid, &classic(), &[]);
         assert_eq!(
             solution_count, 1,
             "Easy puzzle should have exactly 1 solution"
@@ -384,8 +1242,16 @@ mod tests {
 
     #[test]
     fn test_multiple_puzzles_are_different() {
-        let grid1 = generate_puzzle(crate::state::Difficulty::Easy);
-        let grid2 = generate_puzzle(crate::state::Difficulty::Easy);
+        let Puzzle { grid: grid1, .. } = generate_puzzle(
+            crate::state::Difficulty::Easy,
+            GridShape::CLASSIC,
+            Variant::Classic,
+        );
+        let Puzzle { grid: grid2, .. } = generate_puzzle(
+            crate::state::Difficulty::Easy,
+            GridShape::CLASSIC,
+            Variant::Classic,
+        );
 
         let mut different = false;
         for row in 0..9 {
@@ -401,4 +1267,321 @@ mod tests {
         }
         assert!(different, "Multiple Easy puzzles should be different");
     }
+
+    #[test]
+    fn find_conflicts_marks_duplicates_in_each_unit() {
+        let mut grid = GridShape::CLASSIC.empty_grid();
+        grid[0][0] = crate::state::Cell::given(5);
+        grid[0][7].value = Some(5); // same row
+        grid[4][4] = crate::state::Cell::given(2);
+        grid[8][4].value = Some(2); // same column
+        grid[6][6].value = Some(9);
+        grid[8][8].value = Some(9); // same box
+        grid[3][0].value = Some(1); // no conflict
+
+        let conflicts = find_conflicts(&grid, &classic(), &[], &[]);
+        for (row, col) in [(0, 0), (0, 7), (4, 4), (8, 4), (6, 6), (8, 8)] {
+            assert!(conflicts[row][col], "expected conflict at [{row},{col}]");
+        }
+        assert!(!conflicts[3][0]);
+        let count = conflicts.iter().flatten().filter(|c| **c).count();
+        assert_eq!(count, 6);
+    }
+
+    #[test]
+    fn generated_puzzle_has_no_conflicts() {
+        let Puzzle { grid, .. } = generate_puzzle(
+            crate::state::Difficulty::Easy,
+            GridShape::CLASSIC,
+            Variant::Classic,
+        );
+        let conflicts = find_conflicts(&grid, &classic(), &[], &[]);
+        assert!(conflicts.iter().flatten().all(|c| !c));
+    }
+
+    #[test]
+    fn get_solution_ignores_entered_digits() {
+        let Puzzle {
+            mut grid, solution, ..
+        } = generate_puzzle(
+            crate::state::Difficulty::Easy,
+            GridShape::CLASSIC,
+            Variant::Classic,
+        );
+        let (row, col) = (0..81)
+            .map(|i| (i / 9, i % 9))
+            .find(|&(r, c)| !grid[r][c].given)
+            .unwrap();
+        grid[row][col].value = Some(solution[row][col] % 9 + 1);
+        assert_eq!(get_solution(&grid, &classic(), &[]), Some(solution));
+    }
+
+    #[test]
+    fn every_grid_size_generates_a_unique_valid_puzzle() {
+        for shape in GridShape::ALL {
+            let Puzzle { grid, solution, .. } =
+                generate_puzzle(crate::state::Difficulty::Medium, shape, Variant::Classic);
+            assert_eq!(grid.len(), shape.size());
+            assert!(is_valid_sudoku(&grid, shape), "{shape} puzzle is invalid");
+            assert_eq!(
+                unlimited_solutions(&grid, &regions(shape, Variant::Classic, &[]), &[]),
+                1,
+                "{shape} is not unique"
+            );
+            assert_eq!(
+                get_solution(&grid, &regions(shape, Variant::Classic, &[]), &[]),
+                Some(solution)
+            );
+            assert!(
+                count_givens(&grid)
+                    >= target_givens(crate::state::Difficulty::Medium, shape, Variant::Classic)
+            );
+        }
+    }
+
+    #[test]
+    fn killer_cages_cover_the_grid_and_keep_the_solution_unique() {
+        for shape in [GridShape::CLASSIC, GridShape::ALL[1]] {
+            let Puzzle {
+                grid,
+                solution,
+                cages,
+                ..
+            } = generate_puzzle(crate::state::Difficulty::Expert, shape, Variant::Killer);
+            let mut covered = vec![vec![0; shape.size()]; shape.size()];
+            for cage in &cages {
+                let digits: std::collections::HashSet<u8> =
+                    cage.cells.iter().map(|&(r, c)| solution[r][c]).collect();
+                assert_eq!(digits.len(), cage.cells.len(), "repeated digit in {cage:?}");
+                let sum: u32 = digits.iter().map(|&d| d as u32).sum();
+                assert_eq!(sum, cage.sum);
+                for &(r, c) in &cage.cells {
+                    covered[r][c] += 1;
+                    assert!(
+                        cage.cells.len() == 1
+                            || neighbours(r, c, shape.size()).any(|n| cage.cells.contains(&n)),
+                        "{cage:?} is not connected"
+                    );
+                }
+            }
+            assert!(covered.iter().flatten().all(|&n| n == 1));
+            assert_eq!(
+                unlimited_solutions(&grid, &regions(shape, Variant::Killer, &[]), &cages),
+                1
+            );
+            assert_eq!(
+                get_solution(&grid, &regions(shape, Variant::Killer, &[]), &cages),
+                Some(solution)
+            );
+            assert!(
+                count_givens(&grid)
+                    < target_givens(crate::state::Difficulty::Easy, shape, Variant::Classic)
+            );
+        }
+    }
+
+    #[test]
+    fn extra_region_variants_generate_unique_puzzles() {
+        for (variant, shape) in [
+            (Variant::SudokuX, GridShape::ALL[1]),
+            (Variant::SudokuX, GridShape::CLASSIC),
+            (Variant::Windoku, GridShape::CLASSIC),
+        ] {
+            let Puzzle {
+                grid,
+                solution,
+                cages,
+                ..
+            } = generate_puzzle(crate::state::Difficulty::Hard, shape, variant);
+            assert!(cages.is_empty());
+            let regions = regions(shape, variant, &[]);
+            assert_eq!(
+                regions.len(),
+                shape.size() * 3 + extra_regions(shape, variant).len()
+            );
+            for region in &regions {
+                let digits: std::collections::HashSet<u8> =
+                    region.iter().map(|&(r, c)| solution[r][c]).collect();
+                assert_eq!(digits.len(), shape.size(), "{variant} {shape} {region:?}");
+            }
+            assert_eq!(unlimited_solutions(&grid, &regions, &[]), 1);
+            assert_eq!(get_solution(&grid, &regions, &[]), Some(solution));
+        }
+    }
+
+    #[test]
+    fn jigsaw_boxes_are_irregular_connected_and_keep_the_solution_unique() {
+        for shape in [GridShape::ALL[1], GridShape::CLASSIC] {
+            let Puzzle {
+                grid,
+                solution,
+                boxes,
+                ..
+            } = generate_puzzle(crate::state::Difficulty::Hard, shape, Variant::Jigsaw);
+            let size = shape.size();
+            assert_eq!(boxes.len(), size);
+            assert_ne!(
+                boxes,
+                standard_boxes(shape),
+                "{shape} boxes were not reshaped"
+            );
+
+            let mut owner = vec![vec![usize::MAX; size]; size];
+            for (b, cells) in boxes.iter().enumerate() {
+                assert_eq!(cells.len(), size);
+                for &(r, c) in cells {
+                    assert_eq!(owner[r][c], usize::MAX, "({r}, {c}) is in two boxes");
+                    owner[r][c] = b;
+                }
+                let digits: std::collections::HashSet<u8> =
+                    cells.iter().map(|&(r, c)| solution[r][c]).collect();
+                assert_eq!(digits.len(), size, "repeated digit in {cells:?}");
+            }
+            assert!((0..size).all(|b| is_connected(&owner, b)));
+
+            let regions = regions(shape, Variant::Jigsaw, &boxes);
+            assert_eq!(unlimited_solutions(&grid, &regions, &[]), 1);
+            assert_eq!(get_solution(&grid, &regions, &[]), Some(solution));
+        }
+    }
+
+    #[test]
+    fn windoku_windows_sit_one_cell_in_from_each_corner() {
+        let windows = extra_regions(GridShape::CLASSIC, Variant::Windoku);
+        let corners: Vec<_> = windows.iter().map(|w| (w[0], w[8])).collect();
+        assert_eq!(
+            corners,
+            [
+                ((1, 1), (3, 3)),
+                ((1, 5), (3, 7)),
+                ((5, 1), (7, 3)),
+                ((5, 5), (7, 7))
+            ]
+        );
+
+        let small = GridShape::ALL[1];
+        assert!(!Variant::Windoku.supports(small));
+        assert!(extra_regions(small, Variant::Windoku).is_empty());
+        assert_eq!(Variant::SudokuX.cycle(true, small), Variant::Jigsaw);
+        assert_eq!(Variant::Jigsaw.cycle(false, small), Variant::SudokuX);
+        assert_eq!(
+            Variant::SudokuX.cycle(true, GridShape::CLASSIC),
+            Variant::Windoku
+        );
+    }
+
+    #[test]
+    fn cage_conflicts_mark_repeats_and_impossible_sums() {
+        let mut grid = GridShape::CLASSIC.empty_grid();
+        let cages = [
+            Cage {
+                sum: 10,
+                cells: vec![(0, 0), (0, 1), (0, 2)],
+            },
+            Cage {
+                sum: 3,
+                cells: vec![(1, 0), (2, 0)],
+            },
+            Cage {
+                sum: 5,
+                cells: vec![(4, 4), (4, 5)],
+            },
+        ];
+        grid[0][0].value = Some(4);
+        grid[0][1].value = Some(6); // leaves nothing for the third cell
+        grid[1][0].value = Some(1);
+        grid[2][0].value = Some(1); // repeats within the cage and the column
+        grid[4][4].value = Some(2); // still room for a 3
+
+        let conflicts = find_conflicts(&grid, &classic(), &cages, &[]);
+        for (row, col) in [(0, 0), (0, 1), (1, 0), (2, 0)] {
+            assert!(conflicts[row][col], "expected conflict at [{row},{col}]");
+        }
+        assert!(!conflicts[4][4]);
+
+        grid[4][5].value = Some(4);
+        assert!(find_conflicts(&grid, &classic(), &cages, &[])[4][5]);
+    }
+
+    #[test]
+    fn constraints_narrow_the_solutions_and_mark_their_conflicts() {
+        let shape = GridShape::new(2, 2);
+        let regions = regions(shape, Variant::Classic, &[]);
+        let mut grid = shape.empty_grid();
+        assert_eq!(
+            unique_solution(&grid, &regions, &[]),
+            Err("the puzzle has more than one solution")
+        );
+
+        // A full-length thermometer fixes row A, and the dots and V then
+        // pin down the rest.
+        let constraints = [
+            Constraint::Thermo(vec![(0, 0), (0, 1), (0, 2), (0, 3)]),
+            Constraint::BlackDot([(1, 0), (2, 0)]),
+            Constraint::V([(2, 1), (3, 1)]),
+            Constraint::WhiteDot([(1, 1), (2, 1)]),
+            Constraint::Arrow {
+                circle: (1, 3),
+                shaft: vec![(2, 3)],
+            },
+        ];
+        assert_eq!(
+            unique_solution(&grid, &regions, &constraints[..3]),
+            Err("the puzzle has more than one solution")
+        );
+        assert_eq!(
+            unique_solution(&grid, &regions, &constraints[..4]),
+            Ok(vec![
+                vec![1, 2, 3, 4],
+                vec![4, 3, 2, 1],
+                vec![2, 4, 1, 3],
+                vec![3, 1, 4, 2],
+            ])
+        );
+        // A one-cell shaft asks for a repeat in the column.
+        assert_eq!(
+            unique_solution(&grid, &regions, &constraints),
+            Err("the puzzle has no solution")
+        );
+
+        grid[0][1] = Cell::given(3);
+        grid[0][2] = Cell::given(2);
+        assert_eq!(
+            unique_solution(&grid, &regions, &constraints[..4]),
+            Err("the givens break the rules")
+        );
+        let conflicts = find_conflicts(&grid, &regions, &[], &constraints[..4]);
+        assert!(conflicts[0][1] && conflicts[0][2]);
+        assert!(!conflicts[0][0] && !conflicts[0][3]);
+
+        let mut grid = shape.empty_grid();
+        grid[1][1].value = Some(3);
+        grid[2][1].value = Some(1);
+        let conflicts = find_conflicts(&grid, &regions, &[], &constraints[..4]);
+        assert!(conflicts[1][1] && conflicts[2][1]);
+        grid[2][1].value = Some(4);
+        assert!(!find_conflicts(&grid, &regions, &[], &constraints[..4])[2][1]);
+    }
+
+    #[test]
+    fn symbols_use_letters_past_nine_and_hex_on_16x16() {
+        let twelve = GridShape::new(3, 4);
+        assert_eq!(twelve.symbol(10), 'A');
+        assert_eq!(twelve.parse_symbol('c'), Some(12));
+        assert_eq!(twelve.parse_symbol('0'), None);
+        assert_eq!(twelve.symbol_range(), "1-C");
+
+        let hex = GridShape::new(4, 4);
+        assert_eq!((hex.symbol(1), hex.symbol(16)), ('0', 'F'));
+        assert_eq!(hex.parse_symbol('0'), Some(1));
+        assert_eq!(hex.parse_symbol('F'), Some(16));
+        assert_eq!(hex.parse_symbol('G'), None);
+
+        assert_eq!(GridShape::CLASSIC.parse_symbol('a'), None);
+        assert_eq!(
+            GridShape::try_from("6x6".to_string()),
+            Ok(GridShape::new(2, 3))
+        );
+        assert!(GridShape::try_from("7x7".to_string()).is_err());
+    }
 }
@@ -0,0 +1,373 @@
+use std::{fs, io, path::Path, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::PersistError,
+    puzzle::{GridShape, Variant},
+    state::{GameMode, UiZoom},
+};
+
+pub const SETTINGS_FILE: &str = "settings.toml";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub mistakes_max: u8,
+    pub hints: u8,
+    pub autosave_interval_secs: u64,
+    pub resume_window_hours: u64,
+    pub default_zoom: UiZoom,
+    /// The size of new games.
+    pub grid_shape: GridShape,
+    /// The rules of new games.
+    pub variant: Variant,
+    /// The clock of new games.
+    pub mode: GameMode,
+    pub countdown_minutes: u8,
+    pub theme: String,
+    pub highlight_peers: bool,
+    pub highlight_conflicts: bool,
+    pub check_mode: CheckMode,
+    pub pause_on_focus_loss: bool,
+    pub auto_pause_secs: u64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            mistakes_max: 3,
+            hints: 2,
+            autosave_interval_secs: 30,
+            resume_window_hours: 8,
+            default_zoom: UiZoom::XLarge,
+            grid_shape: GridShape::CLASSIC,
+            variant: Variant::Classic,
+            mode: GameMode::Standard,
+            countdown_minutes: 10,
+            theme: crate::theme::DEFAULT_THEME.to_string(),
+            highlight_peers: false,
+            highlight_conflicts: false,
+            check_mode: CheckMode::default(),
+            pause_on_focus_loss: true,
+            auto_pause_secs: 120,
+        }
+    }
+}
+
+/// How entries are judged while playing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+pub enum CheckMode {
+    /// `v` compares entries with the solution and charges mistakes.
+    #[default]
+    Solution,
+    /// Only rule conflicts are shown; wrong entries are charged once the grid is full.
+    RulesCountAtEnd,
+    /// Only rule conflicts are shown and mistakes are never counted.
+    RulesOnly,
+}
+
+impl CheckMode {
+    pub fn is_rules_based(self) -> bool {
+        !matches!(self, CheckMode::Solution)
+    }
+
+    pub fn counts_mistakes(self) -> bool {
+        !matches!(self, CheckMode::RulesOnly)
+    }
+
+    fn cycle(self, forward: bool) -> Self {
+        match (self, forward) {
+            (CheckMode::Solution, true) | (CheckMode::RulesOnly, false) => {
+                CheckMode::RulesCountAtEnd
+            }
+            (CheckMode::RulesCountAtEnd, true) | (CheckMode::Solution, false) => {
+                CheckMode::RulesOnly
+            }
+            (CheckMode::RulesOnly, true) | (CheckMode::RulesCountAtEnd, false) => {
+                CheckMode::Solution
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for CheckMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CheckMode::Solution => write!(f, "Solution"),
+            CheckMode::RulesCountAtEnd => write!(f, "Rules, count at end"),
+            CheckMode::RulesOnly => write!(f, "Rules only"),
+        }
+    }
+}
+
+/// The settings that change how hard a game is, recorded with each
+/// leaderboard entry so times are only compared like for like.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Rules {
+    pub mistakes_max: u8,
+    pub hints: u8,
+    #[serde(default)]
+    pub check_mode: CheckMode,
+}
+
+impl std::fmt::Display for Rules {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.check_mode {
+            CheckMode::Solution => write!(f, "M{} H{}", self.mistakes_max, self.hints),
+            CheckMode::RulesCountAtEnd => write!(f, "M{} H{} end", self.mistakes_max, self.hints),
+            CheckMode::RulesOnly => write!(f, "H{} rules", self.hints),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsField {
+    MistakesMax,
+    Hints,
+    AutosaveInterval,
+    ResumeWindow,
+    DefaultZoom,
+    GridShape,
+    Variant,
+    Mode,
+    CountdownMinutes,
+    Theme,
+    HighlightPeers,
+    HighlightConflicts,
+    CheckMode,
+    PauseOnFocusLoss,
+    AutoPause,
+}
+
+impl SettingsField {
+    pub const ALL: [SettingsField; 15] = [
+        SettingsField::MistakesMax,
+        SettingsField::Hints,
+        SettingsField::AutosaveInterval,
+        SettingsField::ResumeWindow,
+        SettingsField::DefaultZoom,
+        SettingsField::GridShape,
+        SettingsField::Variant,
+        SettingsField::Mode,
+        SettingsField::CountdownMinutes,
+        SettingsField::Theme,
+        SettingsField::HighlightPeers,
+        SettingsField::HighlightConflicts,
+        SettingsField::CheckMode,
+        SettingsField::PauseOnFocusLoss,
+        SettingsField::AutoPause,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SettingsField::MistakesMax => "Mistakes allowed",
+            SettingsField::Hints => "Hints per game",
+            SettingsField::AutosaveInterval => "Autosave every",
+            SettingsField::ResumeWindow => "Resume window",
+            SettingsField::DefaultZoom => "Default zoom",
+            SettingsField::GridShape => "Grid size",
+            SettingsField::Variant => "Variant",
+            SettingsField::Mode => "Mode",
+            SettingsField::CountdownMinutes => "Countdown length",
+            SettingsField::Theme => "Theme",
+            SettingsField::HighlightPeers => "Highlight peers",
+            SettingsField::HighlightConflicts => "Show conflicts",
+            SettingsField::CheckMode => "Checking",
+            SettingsField::PauseOnFocusLoss => "Pause on focus loss",
+            SettingsField::AutoPause => "Auto-pause after",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let idx = Self::ALL.iter().position(|f| *f == self).unwrap_or(0);
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+
+    pub fn prev(self) -> Self {
+        let idx = Self::ALL.iter().position(|f| *f == self).unwrap_or(0);
+        Self::ALL[(idx + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+}
+
+impl Settings {
+    pub fn load() -> Result<Self, PersistError> {
+        let Some(path) = settings_path() else {
+            return Ok(Self::default());
+        };
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        Self::read(&path).map_err(|source| PersistError::Settings { path, source })
+    }
+
+    fn read(path: &Path) -> io::Result<Self> {
+        let source = fs::read_to_string(path)?;
+        toml::from_str(&source).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn save(&self) -> Result<(), PersistError> {
+        let path = settings_path().ok_or(PersistError::NoStateDir)?;
+        self.write(&path)
+            .map_err(|source| PersistError::Settings { path, source })
+    }
+
+    fn write(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let source = toml::to_string_pretty(self).map_err(io::Error::other)?;
+        crate::storage::write_atomic(path, source.as_bytes())
+    }
+
+    pub fn adjust(&mut self, field: SettingsField, increase: bool) {
+        match field {
+            SettingsField::MistakesMax => {
+                self.mistakes_max = step(self.mistakes_max as u64, 1, 1, 9, increase) as u8;
+            }
+            SettingsField::Hints => {
+                self.hints = step(self.hints as u64, 1, 0, 9, increase) as u8;
+            }
+            SettingsField::AutosaveInterval => {
+                self.autosave_interval_secs =
+                    step(self.autosave_interval_secs, 10, 10, 300, increase);
+            }
+            SettingsField::ResumeWindow => {
+                self.resume_window_hours = step(self.resume_window_hours, 1, 1, 72, increase);
+            }
+            SettingsField::DefaultZoom => {
+                self.default_zoom = if increase {
+                    self.default_zoom.zoom_in()
+                } else {
+                    self.default_zoom.zoom_out()
+                };
+            }
+            SettingsField::GridShape => {
+                self.grid_shape = self.grid_shape.cycle(increase);
+                if !self.variant.supports(self.grid_shape) {
+                    self.variant = Variant::Classic;
+                }
+            }
+            SettingsField::Variant => {
+                self.variant = self.variant.cycle(increase, self.grid_shape);
+            }
+            SettingsField::Mode => self.mode = self.mode.cycle(increase),
+            SettingsField::CountdownMinutes => {
+                self.countdown_minutes =
+                    step(self.countdown_minutes as u64, 1, 1, 60, increase) as u8;
+            }
+            // Themes are cycled against the loaded catalog, see `GameState::adjust_setting`.
+            SettingsField::Theme => {}
+            SettingsField::HighlightPeers => self.highlight_peers = !self.highlight_peers,
+            SettingsField::HighlightConflicts => {
+                self.highlight_conflicts = !self.highlight_conflicts;
+            }
+            SettingsField::CheckMode => self.check_mode = self.check_mode.cycle(increase),
+            SettingsField::PauseOnFocusLoss => {
+                self.pause_on_focus_loss = !self.pause_on_focus_loss;
+            }
+            SettingsField::AutoPause => {
+                self.auto_pause_secs = step(self.auto_pause_secs, 30, 0, 600, increase);
+            }
+        }
+    }
+
+    pub fn value_label(&self, field: SettingsField) -> String {
+        match field {
+            SettingsField::MistakesMax => self.mistakes_max.to_string(),
+            SettingsField::Hints => self.hints.to_string(),
+            SettingsField::AutosaveInterval => format!("{}s", self.autosave_interval_secs),
+            SettingsField::ResumeWindow => format!("{}h", self.resume_window_hours),
+            SettingsField::DefaultZoom => format!("{:?}", self.default_zoom),
+            SettingsField::GridShape => self.grid_shape.to_string(),
+            SettingsField::Variant => self.variant.to_string(),
+            SettingsField::Mode => self.mode.to_string(),
+            SettingsField::CountdownMinutes => format!("{} min", self.countdown_minutes),
+            SettingsField::Theme => self.theme.clone(),
+            SettingsField::HighlightPeers => on_off(self.highlight_peers),
+            SettingsField::HighlightConflicts => on_off(self.highlight_conflicts),
+            SettingsField::CheckMode => self.check_mode.to_string(),
+            SettingsField::PauseOnFocusLoss => on_off(self.pause_on_focus_loss),
+            SettingsField::AutoPause => match self.auto_pause_secs {
+                0 => "Off".to_string(),
+                secs => format!("{secs}s"),
+            },
+        }
+    }
+}
+
+fn on_off(value: bool) -> String {
+    if value { "On" } else { "Off" }.to_string()
+}
+
+fn step(value: u64, by: u64, min: u64, max: u64, increase: bool) -> u64 {
+    if increase {
+        value.saturating_add(by).min(max)
+    } else {
+        value.saturating_sub(by).max(min)
+    }
+}
+
+fn settings_path() -> Option<PathBuf> {
+    crate::config::config_dir().map(|dir| dir.join(SETTINGS_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adjust_stays_within_bounds() {
+        let mut settings = Settings::default();
+        for _ in 0..40 {
+            settings.adjust(SettingsField::MistakesMax, false);
+            settings.adjust(SettingsField::AutosaveInterval, true);
+        }
+        assert_eq!(settings.mistakes_max, 1);
+        assert_eq!(settings.autosave_interval_secs, 300);
+
+        settings.adjust(SettingsField::Hints, false);
+        settings.adjust(SettingsField::Hints, false);
+        settings.adjust(SettingsField::Hints, false);
+        assert_eq!(settings.hints, 0);
+    }
+
+    #[test]
+    fn shrinking_the_grid_drops_a_variant_it_cannot_hold() {
+        let mut settings = Settings {
+            variant: Variant::Windoku,
+            ..Settings::default()
+        };
+        settings.adjust(SettingsField::GridShape, false);
+        assert_eq!(settings.variant, Variant::Classic);
+
+        settings.variant = Variant::SudokuX;
+        settings.adjust(SettingsField::GridShape, false);
+        assert_eq!(settings.variant, Variant::SudokuX);
+    }
+
+    #[test]
+    fn partial_file_uses_defaults_for_missing_keys() {
+        let settings: Settings = toml::from_str("hints = 5\ndefault_zoom = \"Small\"").unwrap();
+        assert_eq!(settings.hints, 5);
+        assert_eq!(settings.default_zoom, UiZoom::Small);
+        assert_eq!(settings.mistakes_max, Settings::default().mistakes_max);
+        assert_eq!(settings.resume_window_hours, 8);
+    }
+
+    #[test]
+    fn round_trips_through_toml() {
+        let mut settings = Settings::default();
+        settings.adjust(SettingsField::ResumeWindow, true);
+        settings.adjust(SettingsField::DefaultZoom, false);
+        settings.adjust(SettingsField::GridShape, false);
+        settings.adjust(SettingsField::Variant, true);
+        let source = toml::to_string_pretty(&settings).unwrap();
+        let parsed: Settings = toml::from_str(&source).unwrap();
+        assert_eq!(parsed, settings);
+        assert!(source.contains("grid_shape = \"6x6\""));
+        assert!(source.contains("variant = \"Killer\""));
+    }
+}
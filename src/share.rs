@@ -0,0 +1,111 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::PersistError,
+    state::{GameMode, GameState},
+};
+
+pub const EXPORTS_DIR: &str = "exports";
+
+/// A one-line summary to paste elsewhere, followed by the solved grid.
+pub fn result_text(state: &GameState) -> String {
+    let secs = state.clock.elapsed().as_secs();
+    let mistakes = if state.counts_mistakes() {
+        format!("mistakes {}/{}", state.mistakes, state.mistakes_max)
+    } else {
+        "mistakes off".to_string()
+    };
+    let hints_used = state.hints_used();
+    let hints = if state.mode == GameMode::Zen {
+        format!("hints {hints_used}")
+    } else {
+        format!("hints {hints_used}/{}", state.hints_max)
+    };
+
+    let shape = state.shape;
+    let mut text = format!(
+        "SUDOKUI {} solved in {:02}:{:02}:{:02} ({mistakes}, {hints})\n\n",
+        state.label(),
+        secs / 3600,
+        (secs % 3600) / 60,
+        secs % 60,
+    );
+    let rule =
+        vec!["-".repeat(shape.box_cols * 2 - 1); shape.size() / shape.box_cols].join("-+-") + "\n";
+    for (row, cells) in state.grid.iter().enumerate() {
+        if row > 0 && row.is_multiple_of(shape.box_rows) {
+            text.push_str(&rule);
+        }
+        let boxes: Vec<String> = cells
+            .chunks(shape.box_cols)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .map(|cell| cell.value.map_or('.', |v| shape.symbol(v)).to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect();
+        text.push_str(&boxes.join(" | "));
+        text.push('\n');
+    }
+    text
+}
+
+/// Writes the result to a new file under the state directory's `exports/`.
+pub fn export(state: &GameState) -> Result<PathBuf, PersistError> {
+    let dir = crate::config::state_dir()
+        .ok_or(PersistError::NoStateDir)?
+        .join(EXPORTS_DIR);
+    export_in(&dir, state).map_err(|source| PersistError::Export { path: dir, source })
+}
+
+pub fn export_in(dir: &Path, state: &GameState) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!(
+        "sudokui-{}-{}.txt",
+        format!("{:?}", state.difficulty).to_lowercase(),
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    crate::storage::write_atomic(&path, result_text(state).as_bytes())?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{Difficulty, PlayClock};
+    use std::time::Duration;
+
+    #[test]
+    fn result_lists_the_time_and_the_solved_grid() {
+        let mut state = GameState::new(Difficulty::Hard);
+        for (row, cells) in state.grid.iter_mut().enumerate() {
+            for (col, cell) in cells.iter_mut().enumerate() {
+                cell.value = Some(state.solution[row][col]);
+            }
+        }
+        state.clock = PlayClock::stopped_at(Duration::from_secs(754));
+        state.mistakes = 1;
+        state.hints_left = 1;
+
+        let text = result_text(&state);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            lines[0],
+            "SUDOKUI Hard solved in 00:12:34 (mistakes 1/3, hints 1/2)"
+        );
+        assert_eq!(lines.len(), 2 + 9 + 2);
+        assert_eq!(lines[5], "------+-------+------");
+        let first: String = state.solution[0].iter().map(|v| v.to_string()).collect();
+        assert_eq!(lines[2].replace([' ', '|'], ""), first);
+
+        let dir = std::env::temp_dir().join(format!("sudokui-export-{}", std::process::id()));
+        let path = export_in(&dir, &state).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), text);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,274 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    puzzle::{GridShape, Variant},
+    state::{Difficulty, GameState, Screen},
+};
+
+pub const SLOTS_DIR: &str = "slots";
+pub const AUTOSAVE_SLOT: &str = "Autosave";
+pub const MAX_NAME_LEN: usize = 32;
+
+/// A save file as listed in the slot browser.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotInfo {
+    pub name: String,
+    pub path: PathBuf,
+    /// `GameState::label` of the saved game.
+    pub label: String,
+    pub difficulty: Difficulty,
+    pub shape: GridShape,
+    pub variant: Variant,
+    pub progress_percent: u8,
+    pub elapsed: Duration,
+    pub last_played_at: SystemTime,
+}
+
+impl SlotInfo {
+    fn read(name: String, path: PathBuf) -> io::Result<Self> {
+        let state = GameState::load_from_path(&path)?;
+        Ok(Self {
+            name,
+            path,
+            label: state.label(),
+            difficulty: state.difficulty,
+            shape: state.shape,
+            variant: state.variant,
+            progress_percent: state.progress_percent(),
+            elapsed: state.clock.elapsed(),
+            last_played_at: state.last_played_at,
+        })
+    }
+
+    pub fn is_autosave(&self) -> bool {
+        self.name == AUTOSAVE_SLOT
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrowserMode {
+    Save,
+    Load,
+}
+
+#[derive(Debug, Clone)]
+pub struct SlotBrowser {
+    pub mode: BrowserMode,
+    pub slots: Vec<SlotInfo>,
+    /// In save mode, row 0 is "New slot" and slots start at row 1.
+    pub selected: usize,
+    pub name_input: Option<String>,
+    pub return_to: Screen,
+}
+
+impl Default for SlotBrowser {
+    fn default() -> Self {
+        Self {
+            mode: BrowserMode::Load,
+            slots: Vec::new(),
+            selected: 0,
+            name_input: None,
+            return_to: Screen::LevelSelector,
+        }
+    }
+}
+
+impl SlotBrowser {
+    pub fn row_count(&self) -> usize {
+        self.slots.len() + self.first_slot_row()
+    }
+
+    fn first_slot_row(&self) -> usize {
+        match self.mode {
+            BrowserMode::Save => 1,
+            BrowserMode::Load => 0,
+        }
+    }
+
+    /// The slot under the cursor; `None` on the "New slot" row.
+    pub fn selected_slot(&self) -> Option<&SlotInfo> {
+        self.selected
+            .checked_sub(self.first_slot_row())
+            .and_then(|i| self.slots.get(i))
+    }
+
+    pub fn select_named(&mut self, name: &str) {
+        if let Some(i) = self.slots.iter().position(|slot| slot.name == name) {
+            self.selected = i + self.first_slot_row();
+        }
+    }
+
+    pub fn move_cursor(&mut self, forward: bool) {
+        let len = self.row_count();
+        if len == 0 {
+            return;
+        }
+        self.selected = if forward {
+            (self.selected + 1) % len
+        } else {
+            (self.selected + len - 1) % len
+        };
+    }
+
+    pub fn refresh(&mut self) {
+        self.slots = list();
+        self.selected = self.selected.min(self.row_count().saturating_sub(1));
+    }
+}
+
+pub fn slots_dir() -> Option<PathBuf> {
+    crate::config::state_dir().map(|dir| dir.join(SLOTS_DIR))
+}
+
+/// The autosave session followed by named slots, most recently played first.
+pub fn list() -> Vec<SlotInfo> {
+    let mut slots: Vec<SlotInfo> = crate::state::autosave_path()
+        .and_then(|path| SlotInfo::read(AUTOSAVE_SLOT.to_string(), path).ok())
+        .into_iter()
+        .collect();
+    if let Some(dir) = slots_dir() {
+        slots.extend(list_in(&dir));
+    }
+    slots
+}
+
+/// Unreadable files are left out rather than failing the whole listing.
+pub fn list_in(dir: &Path) -> Vec<SlotInfo> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut slots: Vec<SlotInfo> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_string_lossy().into_owned();
+            SlotInfo::read(name, path).ok()
+        })
+        .collect();
+    slots.sort_by_key(|slot| std::cmp::Reverse(slot.last_played_at));
+    slots
+}
+
+/// Keeps letters, digits, spaces, `-` and `_` so a name is always a safe file stem.
+pub fn sanitize_name(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .filter(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_'))
+        .take(MAX_NAME_LEN)
+        .collect();
+    let cleaned = cleaned.trim();
+    if cleaned.is_empty() || cleaned.eq_ignore_ascii_case(AUTOSAVE_SLOT) {
+        String::new()
+    } else {
+        cleaned.to_string()
+    }
+}
+
+pub fn save_in(dir: &Path, name: &str, state: &GameState) -> io::Result<PathBuf> {
+    let name = sanitize_name(name);
+    if name.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "slot name is empty or reserved",
+        ));
+    }
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{name}.json"));
+    state.save_to_path(&path)?;
+    Ok(path)
+}
+
+pub fn delete(slot: &SlotInfo) -> io::Result<()> {
+    fs::remove_file(&slot.path)
+}
+
+/// Copies a slot to "<name> copy" (or "<name> copy 2", ...) in `dir`.
+pub fn duplicate_in(dir: &Path, slot: &SlotInfo) -> io::Result<String> {
+    fs::create_dir_all(dir)?;
+    let base = format!("{} copy", slot.name);
+    let name = (1..)
+        .map(|n| match n {
+            1 => base.clone(),
+            n => format!("{base} {n}"),
+        })
+        .find(|name| !dir.join(format!("{name}.json")).exists())
+        .unwrap_or(base);
+    fs::copy(&slot.path, dir.join(format!("{name}.json")))?;
+    Ok(name)
+}
+
+pub fn default_name(state: &GameState) -> String {
+    format!(
+        "{} {}",
+        state.difficulty,
+        chrono::Local::now().format("%Y-%m-%d %H%M")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(tag: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sudokui-slots-{tag}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn sanitize_keeps_safe_characters_only() {
+        assert_eq!(sanitize_name("  my/../game: 1 "), "mygame 1");
+        assert_eq!(sanitize_name("autosave"), "");
+        assert_eq!(sanitize_name(&"x".repeat(50)).len(), MAX_NAME_LEN);
+    }
+
+    #[test]
+    fn save_duplicate_and_delete_slots() {
+        let dir = temp_dir("ops");
+        let mut state = GameState::new(Difficulty::Hard);
+        state.last_played_at = SystemTime::now() - Duration::from_secs(60);
+        save_in(&dir, "first", &state).unwrap();
+
+        let slots = list_in(&dir);
+        assert_eq!(slots.len(), 1);
+        assert_eq!(slots[0].name, "first");
+        assert_eq!(slots[0].difficulty, Difficulty::Hard);
+        assert_eq!(slots[0].progress_percent, 0);
+
+        assert_eq!(duplicate_in(&dir, &slots[0]).unwrap(), "first copy");
+        assert_eq!(duplicate_in(&dir, &slots[0]).unwrap(), "first copy 2");
+        assert_eq!(list_in(&dir).len(), 3);
+
+        delete(&slots[0]).unwrap();
+        let names: Vec<String> = list_in(&dir).into_iter().map(|s| s.name).collect();
+        assert!(!names.contains(&"first".to_string()));
+        assert_eq!(names.len(), 2);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn save_mode_reserves_the_first_row_for_a_new_slot() {
+        let dir = temp_dir("rows");
+        let state = GameState::new(Difficulty::Easy);
+        save_in(&dir, "only", &state).unwrap();
+
+        let mut browser = SlotBrowser {
+            mode: BrowserMode::Save,
+            slots: list_in(&dir),
+            ..SlotBrowser::default()
+        };
+        assert_eq!(browser.row_count(), 2);
+        assert!(browser.selected_slot().is_none());
+        browser.move_cursor(true);
+        assert_eq!(browser.selected_slot().unwrap().name, "only");
+        browser.move_cursor(true);
+        assert_eq!(browser.selected, 0);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
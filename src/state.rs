@@ -1,18 +1,32 @@
 use std::{
-    env, fs, io,
+    fs, io,
     path::{Path, PathBuf},
     time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    analysis::Analysis,
+    error::PersistError,
+    history::Move,
+    leaderboard::{Category, Leaderboard, LeaderboardEntry, TOP_DISPLAY_COUNT},
+    migrate::Migration,
+    packs::{PackBrowser, PackRef},
+    puzzle::{Cage, Constraint, Grid, GridShape, Puzzle, Region, Solution, Variant},
+    settings::{CheckMode, Rules, Settings, SettingsField},
+    slots::{BrowserMode, SlotBrowser},
+    theme::ThemeCatalog,
+    tutorial::{lessons, Tutorial},
+};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum InputMode {
     Normal,
     Notes,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Difficulty {
     Easy,
     Medium,
@@ -31,20 +45,106 @@ impl std::fmt::Display for Difficulty {
     }
 }
 
+/// The difficulty, followed by the variant and the grid size when they
+/// aren't classic, e.g. `Hard Killer 6x6`.
+pub fn game_label(difficulty: Difficulty, shape: GridShape, variant: Variant) -> String {
+    let mut label = difficulty.to_string();
+    if variant != Variant::Classic {
+        label.push_str(&format!(" {variant}"));
+    }
+    if shape != GridShape::CLASSIC {
+        label.push_str(&format!(" {shape}"));
+    }
+    label
+}
+
+/// How the clock bounds a game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+pub enum GameMode {
+    /// The clock only counts up.
+    #[default]
+    Standard,
+    /// The puzzle must be solved before `Settings::countdown_minutes` run out.
+    Countdown,
+    /// Easy puzzles one after another until `TIME_ATTACK_LIMIT` runs out.
+    TimeAttack,
+    /// No clock, mistakes or leaderboard, and hints without limit.
+    Zen,
+}
+
+/// How long a time-attack run lasts.
+pub const TIME_ATTACK_LIMIT: Duration = Duration::from_secs(15 * 60);
+
+impl GameMode {
+    pub const ALL: [GameMode; 4] = [
+        GameMode::Standard,
+        GameMode::Countdown,
+        GameMode::TimeAttack,
+        GameMode::Zen,
+    ];
+
+    pub fn cycle(self, forward: bool) -> Self {
+        let all = Self::ALL;
+        let idx = all.iter().position(|m| *m == self).unwrap_or(0);
+        let len = all.len();
+        all[if forward { idx + 1 } else { idx + len - 1 } % len]
+    }
+
+    /// The clock limit for a new game in this mode.
+    pub fn time_limit(self, settings: &Settings) -> Option<Duration> {
+        match self {
+            GameMode::Standard | GameMode::Zen => None,
+            GameMode::Countdown => Some(Duration::from_secs(
+                u64::from(settings.countdown_minutes) * 60,
+            )),
+            GameMode::TimeAttack => Some(TIME_ATTACK_LIMIT),
+        }
+    }
+}
+
+impl std::fmt::Display for GameMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameMode::Standard => write!(f, "Standard"),
+            GameMode::Countdown => write!(f, "Countdown"),
+            GameMode::TimeAttack => write!(f, "Time attack"),
+            GameMode::Zen => write!(f, "Zen"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Screen {
     LevelSelector,
     Playing,
     Win,
+    /// A countdown or time-attack clock ran out.
+    TimeUp,
+    /// A lesson's board, which only takes the placement each step asks for.
+    Tutorial,
+    /// The pack list, or the puzzles of the pack opened from it.
+    Packs,
+    Settings,
+    Slots,
+    Replay,
+    /// The solve path of the game just won.
+    Analysis,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DifficultyOption {
     Resume,
+    ContinuePack,
     Easy,
     Medium,
     Hard,
     Expert,
+    GridSize,
+    Variant,
+    Mode,
+    Packs,
+    Tutorial,
+    Settings,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
@@ -147,7 +247,8 @@ impl Cell {
     }
 
     pub fn toggle_candidate(&mut self, digit: u8) {
-        if !(1..=9).contains(&digit) {
+        // The mask has room for the 16 digits of the largest grid.
+        if !(1..=16).contains(&digit) {
             return;
         }
         let bit = 1u16 << (digit - 1);
@@ -159,7 +260,7 @@ impl Cell {
     }
 
     pub fn candidates(&self) -> Vec<u8> {
-        (1u8..=9)
+        (1u8..=16)
             .filter(|d| (self.candidates_mask & (1u16 << (d - 1))) != 0)
             .collect()
     }
@@ -170,114 +271,1049 @@ pub struct GameState {
     pub difficulty: Difficulty,
     pub screen: Screen,
     pub game_completed: bool,
-    #[allow(dead_code)]
-    pub last_played_at: Instant,
-    pub has_recent_save: bool,
+    /// Wall-clock time of the last input, used for the resume window.
+    pub last_played_at: SystemTime,
+    pub recent_save: Option<SaveSummary>,
     pub selector_selection: DifficultyOption,
-    pub started_at: Instant,
+    pub clock: PlayClock,
+    pub paused: bool,
     pub mistakes: u8,
     pub mistakes_max: u8,
     pub max_mistakes_warning: bool,
+    pub check_mode: CheckMode,
     pub hints_left: u8,
+    pub hints_max: u8,
     pub input_mode: InputMode,
     pub ui_zoom: UiZoom,
     pub show_givens: bool,
     pub help_visible: bool,
     pub selection: Selection,
-    pub grid: [[Cell; 9]; 9],
+    pub shape: GridShape,
+    pub variant: Variant,
+    pub grid: Grid,
+    pub solution: Solution,
+    /// Killer cages; empty for other variants.
+    pub cages: Vec<Cage>,
+    /// Jigsaw boxes; empty for the shape's rectangular ones.
+    pub boxes: Vec<Region>,
+    /// Thermometers, arrows and the like, from an imported puzzle.
+    pub constraints: Vec<Constraint>,
+    /// An imported puzzle's name, shown in place of its difficulty.
+    pub title: Option<String>,
+    pub mode: GameMode,
+    /// How long the clock may run in a countdown or time-attack game.
+    pub time_limit: Option<Duration>,
+    /// Puzzles finished so far in a time-attack run; the clock carries on
+    /// from one to the next.
+    pub run_solved: u32,
+    /// Progress through the lesson being played, if this game is one.
+    pub tutorial: Option<Tutorial>,
+    /// The lesson the selector's Tutorial row starts.
+    pub lesson_choice: usize,
+    /// The pack puzzle this game is, if it is one.
+    pub pack: Option<PackRef>,
+    pub packs: PackBrowser,
+    /// Every value change this game, for the replay.
+    pub moves: Vec<Move>,
+    /// How many of `moves` the replay screen has applied.
+    pub replay_step: usize,
+    /// Worked out when the solve path screen is first opened.
+    pub analysis: Option<Analysis>,
+    /// The solve path step under the cursor.
+    pub analysis_step: usize,
+    pub win: WinView,
     pub last_action: Option<LastAction>,
     pub should_quit: bool,
     pub history: crate::history::ActionHistory,
+    pub settings: Settings,
+    pub settings_draft: Settings,
+    pub settings_field: SettingsField,
+    pub themes: ThemeCatalog,
+    pub browser: SlotBrowser,
+    /// The named slot this game was loaded from or last saved to.
+    pub slot_name: Option<String>,
+    pub toast: Option<Toast>,
+}
+
+/// A status message shown in the footer for a few seconds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Toast {
+    pub message: String,
+    pub is_error: bool,
+    pub expires_at: Instant,
+}
+
+const TOAST_DURATION: Duration = Duration::from_secs(6);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WinAction {
+    NextPuzzle,
+    TryAgain,
+    LevelSelector,
+    ViewReplay,
+    Analysis,
+    Export,
+}
+
+impl WinAction {
+    pub const ALL: [WinAction; 5] = [
+        WinAction::NextPuzzle,
+        WinAction::LevelSelector,
+        WinAction::ViewReplay,
+        WinAction::Analysis,
+        WinAction::Export,
+    ];
+
+    /// The choices once the clock has run out.
+    pub const TIME_UP: [WinAction; 2] = [WinAction::TryAgain, WinAction::LevelSelector];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            WinAction::NextPuzzle => "Next puzzle",
+            WinAction::TryAgain => "Try again",
+            WinAction::LevelSelector => "Level selector",
+            WinAction::ViewReplay => "View replay",
+            WinAction::Analysis => "Solve path",
+            WinAction::Export => "Export result",
+        }
+    }
+}
+
+/// What the win screen shows besides the finished game itself.
+#[derive(Debug, Clone)]
+pub struct WinView {
+    pub selected: WinAction,
+    /// Best results in the game's leaderboard category, or why they
+    /// couldn't be read.
+    pub top: Result<Vec<LeaderboardEntry>, String>,
+}
+
+impl Default for WinView {
+    fn default() -> Self {
+        Self {
+            selected: WinAction::NextPuzzle,
+            top: Ok(Vec::new()),
+        }
+    }
+}
+
+/// Active play time. It only runs while the board is on screen and unpaused,
+/// see `GameState::sync_clock`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PlayClock {
+    accumulated: Duration,
+    running_since: Option<Instant>,
+}
+
+impl PlayClock {
+    pub fn stopped_at(elapsed: Duration) -> Self {
+        Self {
+            accumulated: elapsed,
+            running_since: None,
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.accumulated
+            + self
+                .running_since
+                .map_or(Duration::ZERO, |since| since.elapsed())
+    }
+
+    pub fn set_running(&mut self, running: bool) {
+        match (running, self.running_since) {
+            (true, None) => self.running_since = Some(Instant::now()),
+            (false, Some(since)) => {
+                self.accumulated += since.elapsed();
+                self.running_since = None;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// What the level selector needs to describe a resumable save.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SaveSummary {
+    /// `GameState::label` of the saved game.
+    pub label: String,
+    pub difficulty: Difficulty,
+    pub shape: GridShape,
+    pub variant: Variant,
+    pub elapsed: Duration,
+    pub last_played_at: SystemTime,
+}
+
+impl SaveSummary {
+    pub fn of(state: &GameState) -> Self {
+        Self {
+            label: state.label(),
+            difficulty: state.difficulty,
+            shape: state.shape,
+            variant: state.variant,
+            elapsed: state.clock.elapsed(),
+            last_played_at: state.last_played_at,
+        }
+    }
 }
 
 impl GameState {
+    #[allow(dead_code)]
     pub fn new(difficulty: Difficulty) -> Self {
-        let grid = crate::puzzle::generate_puzzle(difficulty);
+        Self::with_settings(difficulty, Settings::default())
+    }
+
+    pub fn with_settings(difficulty: Difficulty, settings: Settings) -> Self {
+        // Time attack is always played on Easy puzzles.
+        let difficulty = match settings.mode {
+            GameMode::TimeAttack => Difficulty::Easy,
+            _ => difficulty,
+        };
+        let shape = settings.grid_shape;
+        let variant = settings.variant;
+        let puzzle = crate::puzzle::generate_puzzle(difficulty, shape, variant);
+        Self::from_puzzle(difficulty, settings, shape, variant, puzzle)
+    }
+
+    pub fn from_puzzle(
+        difficulty: Difficulty,
+        settings: Settings,
+        shape: GridShape,
+        variant: Variant,
+        puzzle: Puzzle,
+    ) -> Self {
+        let size = shape.size();
         Self {
             difficulty,
             screen: Screen::LevelSelector,
             game_completed: false,
-            last_played_at: Instant::now(),
-            has_recent_save: false,
-            selector_selection: match difficulty {
-                Difficulty::Easy => DifficultyOption::Easy,
-                Difficulty::Medium => DifficultyOption::Medium,
-                Difficulty::Hard => DifficultyOption::Hard,
-                Difficulty::Expert => DifficultyOption::Expert,
-            },
-            started_at: Instant::now(),
+            last_played_at: SystemTime::now(),
+            recent_save: None,
+            selector_selection: difficulty_option(difficulty),
+            clock: PlayClock::default(),
+            paused: false,
             mistakes: 0,
-            mistakes_max: 3,
+            mistakes_max: settings.mistakes_max.max(1),
             max_mistakes_warning: false,
-            hints_left: 2,
+            check_mode: settings.check_mode,
+            hints_left: settings.hints,
+            hints_max: settings.hints,
             input_mode: InputMode::Normal,
-            ui_zoom: UiZoom::default(),
+            ui_zoom: settings.default_zoom,
             show_givens: true,
             help_visible: false,
-            selection: Selection { row: 3, col: 5 },
-            grid,
+            // Row D, column 6 on the classic grid.
+            selection: Selection {
+                row: size / 3,
+                col: size * 5 / 9,
+            },
+            shape,
+            variant,
+            grid: puzzle.grid,
+            solution: puzzle.solution,
+            cages: puzzle.cages,
+            boxes: puzzle.boxes,
+            constraints: puzzle.constraints,
+            title: None,
+            mode: settings.mode,
+            time_limit: settings.mode.time_limit(&settings),
+            run_solved: 0,
+            tutorial: None,
+            lesson_choice: 0,
+            pack: None,
+            packs: PackBrowser::default(),
+            moves: Vec::new(),
+            replay_step: 0,
+            analysis: None,
+            analysis_step: 0,
+            win: WinView::default(),
             last_action: None,
             should_quit: false,
             history: crate::history::ActionHistory::new(),
+            settings_draft: settings.clone(),
+            settings,
+            settings_field: SettingsField::MistakesMax,
+            themes: ThemeCatalog::default(),
+            browser: SlotBrowser::default(),
+            slot_name: None,
+            toast: None,
         }
     }
 
     pub fn new_game(&mut self, difficulty: Difficulty) {
-        let ui_zoom = self.ui_zoom;
-        let show_givens = self.show_givens;
+        let next = GameState::with_settings(difficulty, self.settings.clone());
+        self.start(next);
+    }
 
-        let mut next = GameState::new(difficulty);
-        next.ui_zoom = ui_zoom;
-        next.show_givens = show_givens;
+    /// Starts playing `next`, keeping the zoom, givens toggle, themes and
+    /// packs.
+    pub fn start(&mut self, mut next: GameState) {
+        next.ui_zoom = self.ui_zoom;
+        next.show_givens = self.show_givens;
+        next.themes = std::mem::take(&mut self.themes);
+        next.packs = std::mem::take(&mut self.packs);
+        next.lesson_choice = self.lesson_choice;
         next.screen = Screen::Playing;
         *self = next;
     }
 
-    pub fn load_or_show_selector() -> io::Result<Self> {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+    /// Opens lesson `index` of the tutorial on its board, with nothing to
+    /// time or rank.
+    pub fn start_lesson(&mut self, index: usize) {
+        let lesson = &lessons()[index];
+        let mut next = GameState::from_puzzle(
+            Difficulty::Easy,
+            self.settings.clone(),
+            GridShape::CLASSIC,
+            Variant::Classic,
+            lesson.puzzle.clone(),
+        );
+        next.title = Some(lesson.title.clone());
+        next.mode = GameMode::Zen;
+        next.time_limit = None;
+        next.tutorial = Some(Tutorial {
+            lesson: index,
+            step: 0,
+        });
+        self.start(next);
+        self.lesson_choice = index;
+        self.screen = Screen::Tutorial;
+        self.select_lesson_cell();
+    }
 
-        const RESUME_WINDOW_HOURS: u64 = 8;
+    fn select_lesson_cell(&mut self) {
+        if let Some(step) = self.tutorial.and_then(Tutorial::current) {
+            let (row, col) = step.cell;
+            self.selection = Selection { row, col };
+        }
+    }
+
+    /// A digit typed during a lesson. Notes work as usual; a placement is
+    /// only made when it is the one the current step asks for.
+    pub fn enter_lesson_digit(&mut self, digit: u8) {
+        let Some(tutorial) = self.tutorial else {
+            return;
+        };
+        if tutorial.current().is_none() || self.input_mode == InputMode::Notes {
+            self.enter_digit(digit);
+            return;
+        }
+        let cell = (self.selection.row, self.selection.col);
+        if let Some(message) = tutorial.reject(cell, digit) {
+            self.show_toast(message);
+            return;
+        }
+        self.enter_digit(digit);
+        self.tutorial = Some(Tutorial {
+            step: tutorial.step + 1,
+            ..tutorial
+        });
+        self.select_lesson_cell();
+    }
+
+    /// Leaves a finished lesson for the next one, or for the selector after
+    /// the last.
+    pub fn finish_lesson(&mut self) {
+        let next = self.lesson_choice + 1;
+        if next < lessons().len() {
+            self.start_lesson(next);
+        } else {
+            self.lesson_choice = 0;
+            self.screen = Screen::LevelSelector;
+        }
+    }
+
+    pub fn cycle_lesson(&mut self, forward: bool) {
+        let len = lessons().len();
+        self.lesson_choice = if forward {
+            self.lesson_choice + 1
+        } else {
+            self.lesson_choice + len - 1
+        } % len;
+    }
+
+    pub fn open_packs(&mut self) {
+        self.packs.open = None;
+        self.packs.selected = 0;
+        self.screen = Screen::Packs;
+    }
+
+    /// Steps back from an open pack to the list, or from the list to the
+    /// selector.
+    pub fn close_packs(&mut self) {
+        if !self.packs.close_pack() {
+            self.selector_selection = DifficultyOption::Packs;
+            self.screen = Screen::LevelSelector;
+        }
+    }
 
-        if let Ok(mut loaded) = GameState::load_default() {
-            let hours_elapsed = (now - loaded.started_at.elapsed().as_secs()) / 3600;
+    /// Opens the selected pack, or plays the selected puzzle of an open one.
+    pub fn confirm_pack(&mut self) -> Result<(), PersistError> {
+        let Some(open) = self.packs.open else {
+            if !self.packs.open_selected() {
+                let previous = &self.packs.packs[self.packs.selected - 1];
+                let message = format!(
+                    "Solve {} puzzles of {} to unlock this pack",
+                    crate::packs::unlock_target(previous),
+                    previous.title
+                );
+                self.show_toast(message);
+            }
+            return Ok(());
+        };
+        let puzzle = PackRef {
+            pack: self.packs.packs[open].id.clone(),
+            index: self.packs.selected,
+        };
+        self.play_pack(&puzzle)
+    }
 
-            if !loaded.game_completed && hours_elapsed < RESUME_WINDOW_HOURS {
-                loaded.screen = Screen::Playing;
-                loaded.has_recent_save = true;
-                return Ok(loaded);
+    /// Starts a pack puzzle and remembers it for "Continue pack".
+    pub fn play_pack(&mut self, puzzle: &PackRef) -> Result<(), PersistError> {
+        let game = self
+            .packs
+            .find(&puzzle.pack)
+            .map(|pack| pack.game(puzzle.index, self.settings.clone()));
+        match game {
+            Some(Ok(next)) => self.start(next),
+            Some(Err(message)) => {
+                self.show_toast(message);
+                return Ok(());
             }
+            None => return Ok(()),
         }
+        self.packs.progress.last = Some(puzzle.clone());
+        self.packs.progress.save()
+    }
 
-        let mut state = GameState::new(Difficulty::Easy);
+    /// Starts the puzzle after the last pack puzzle played.
+    pub fn continue_pack(&mut self) -> Result<(), PersistError> {
+        match self.packs.continue_target() {
+            Some(puzzle) => self.play_pack(&puzzle),
+            None => {
+                self.open_packs();
+                Ok(())
+            }
+        }
+    }
+
+    /// Ticks off the pack puzzle just won and saves the progress.
+    pub fn record_pack_win(&mut self) -> Result<(), PersistError> {
+        if self.pack.is_none() {
+            return Ok(());
+        }
+        self.mark_pack_solved();
+        self.packs.progress.save()
+    }
+
+    /// Marks the pack puzzle solved, announcing any pack it unlocks.
+    fn mark_pack_solved(&mut self) {
+        let Some(puzzle) = &self.pack else {
+            return;
+        };
+        let browser = &mut self.packs;
+        let before: Vec<bool> = (0..browser.packs.len())
+            .map(|i| browser.is_unlocked(i))
+            .collect();
+        browser.progress.mark_solved(puzzle);
+        let opened = (0..browser.packs.len()).find(|&i| !before[i] && browser.is_unlocked(i));
+        if let Some(index) = opened {
+            let title = browser.packs[index].title.clone();
+            self.show_toast(format!("{title} unlocked!"));
+        }
+    }
+
+    /// Moves a time-attack run on to a fresh puzzle, keeping its clock.
+    pub fn next_in_run(&mut self) {
+        let mut next = GameState::with_settings(Difficulty::Easy, self.settings.clone());
+        next.mode = self.mode;
+        next.time_limit = self.time_limit;
+        next.run_solved = self.run_solved + 1;
+        next.clock = self.clock;
+        let solved = next.run_solved;
+        self.start(next);
+        self.show_toast(format!(
+            "Solved! {solved} puzzle{} so far",
+            if solved == 1 { "" } else { "s" }
+        ));
+    }
+
+    /// Time left on a countdown or time-attack clock.
+    pub fn time_left(&self) -> Option<Duration> {
+        self.time_limit
+            .map(|limit| limit.saturating_sub(self.clock.elapsed()))
+    }
+
+    /// Whether a game still in play has used up its time limit.
+    pub fn is_out_of_time(&self) -> bool {
+        self.screen == Screen::Playing
+            && !self.game_completed
+            && self.time_left() == Some(Duration::ZERO)
+    }
+
+    /// Shows the time-up screen for the game whose clock ran out.
+    pub fn open_time_up(&mut self) {
+        self.clock.set_running(false);
+        self.help_visible = false;
+        self.win = WinView {
+            selected: WinAction::TryAgain,
+            top: self.load_top(),
+        };
+        self.screen = Screen::TimeUp;
+    }
+
+    /// Replaces the session with a loaded one, keeping the user's settings.
+    pub fn resume_from(&mut self, loaded: GameState) {
+        let settings = self.settings.clone();
+        let themes = std::mem::take(&mut self.themes);
+        let packs = std::mem::take(&mut self.packs);
+        *self = loaded;
+        self.settings_draft = settings.clone();
+        self.settings = settings;
+        self.themes = themes;
+        self.packs = packs;
+    }
+
+    /// Starts on the level selector, offering to resume a save that was
+    /// played within the resume window.
+    pub fn load_or_show_selector(settings: Settings) -> io::Result<Self> {
+        let window = Duration::from_secs(settings.resume_window_hours * 3600);
+        let (loaded, error) = match GameState::load_default() {
+            Ok(loaded) => (Some(loaded), None),
+            Err(e) if e.is_not_found() => (None, None),
+            Err(e) => (None, Some(e)),
+        };
+        let toast = loaded.as_ref().and_then(|loaded| loaded.toast.clone());
+        if let Some(toast) = &toast {
+            crate::error::log("WARN", &toast.message);
+        }
+        let recent_save = loaded
+            .filter(|loaded| !loaded.game_completed && loaded.idle_for() < window)
+            .map(|loaded| SaveSummary::of(&loaded));
+
+        let mut state = GameState::with_settings(Difficulty::Easy, settings);
         state.screen = Screen::LevelSelector;
-        state.has_recent_save = false;
-        state.selector_selection = DifficultyOption::Easy;
+        state.selector_selection = if recent_save.is_some() {
+            DifficultyOption::Resume
+        } else {
+            DifficultyOption::Easy
+        };
+        state.recent_save = recent_save;
+        state.toast = toast;
+        if let Some(e) = error {
+            state.report(e);
+        }
         Ok(state)
     }
 
-    #[allow(dead_code)]
-    pub fn check_win(&mut self) -> bool {
-        for row in 0..9 {
-            for col in 0..9 {
-                if self.grid[row][col].value.is_none() {
-                    return false;
+    pub fn show_toast(&mut self, message: impl Into<String>) {
+        self.toast = Some(Toast {
+            message: message.into(),
+            is_error: false,
+            expires_at: Instant::now() + TOAST_DURATION,
+        });
+    }
+
+    /// Shows a persistence failure in the footer and records it in the log file.
+    pub fn report(&mut self, error: PersistError) {
+        let message = error.to_string();
+        crate::error::log("ERROR", &message);
+        self.toast = Some(Toast {
+            message,
+            is_error: true,
+            expires_at: Instant::now() + TOAST_DURATION,
+        });
+    }
+
+    pub fn active_toast(&self) -> Option<&Toast> {
+        self.toast
+            .as_ref()
+            .filter(|toast| toast.expires_at > Instant::now())
+    }
+
+    /// The selector entries, with Resume first when there is a recent save.
+    pub fn selector_options(&self) -> Vec<DifficultyOption> {
+        let mut options = vec![
+            DifficultyOption::Easy,
+            DifficultyOption::Medium,
+            DifficultyOption::Hard,
+            DifficultyOption::Expert,
+            DifficultyOption::GridSize,
+            DifficultyOption::Variant,
+            DifficultyOption::Mode,
+            DifficultyOption::Packs,
+            DifficultyOption::Tutorial,
+            DifficultyOption::Settings,
+        ];
+        if self.settings.mode == GameMode::TimeAttack {
+            options.retain(|o| {
+                !matches!(
+                    o,
+                    DifficultyOption::Medium | DifficultyOption::Hard | DifficultyOption::Expert
+                )
+            });
+        }
+        if self.packs.continue_target().is_some() {
+            options.insert(0, DifficultyOption::ContinuePack);
+        }
+        if self.recent_save.is_some() {
+            options.insert(0, DifficultyOption::Resume);
+        }
+        options
+    }
+
+    pub fn move_selector(&mut self, forward: bool) {
+        let options = self.selector_options();
+        let len = options.len();
+        let idx = options
+            .iter()
+            .position(|o| *o == self.selector_selection)
+            .unwrap_or(0);
+        let next = if forward { idx + 1 } else { idx + len - 1 };
+        self.selector_selection = options[next % len];
+    }
+
+    /// Switches the size of the next new game and remembers it in the settings.
+    pub fn cycle_grid_shape(&mut self, forward: bool) -> Result<(), PersistError> {
+        self.settings.adjust(SettingsField::GridShape, forward);
+        self.settings_draft.grid_shape = self.settings.grid_shape;
+        self.settings_draft.variant = self.settings.variant;
+        self.settings.save()
+    }
+
+    /// Switches the clock of the next new game and remembers it in the settings.
+    pub fn cycle_mode(&mut self, forward: bool) -> Result<(), PersistError> {
+        self.settings.adjust(SettingsField::Mode, forward);
+        self.settings_draft.mode = self.settings.mode;
+        self.settings.save()
+    }
+
+    /// Switches the rules of the next new game and remembers them in the settings.
+    pub fn cycle_variant(&mut self, forward: bool) -> Result<(), PersistError> {
+        self.settings.adjust(SettingsField::Variant, forward);
+        self.settings_draft.variant = self.settings.variant;
+        self.settings.save()
+    }
+
+    /// The imported puzzle's title, or the difficulty, variant and size,
+    /// followed by the mode unless it is standard.
+    pub fn label(&self) -> String {
+        let mut label = self
+            .title
+            .clone()
+            .unwrap_or_else(|| game_label(self.difficulty, self.shape, self.variant));
+        match (self.mode, self.time_limit) {
+            (GameMode::Countdown, Some(limit)) => {
+                label.push_str(&format!(", countdown {}m", limit.as_secs() / 60));
+            }
+            (GameMode::TimeAttack, _) => label.push_str(", time attack"),
+            (GameMode::Zen, _) => label.push_str(", zen"),
+            _ => {}
+        }
+        label
+    }
+
+    /// The leaderboard list this game's result goes in.
+    pub fn category(&self) -> Category {
+        Category {
+            difficulty: self.difficulty,
+            shape: self.shape,
+            variant: self.variant,
+            mode: self.mode,
+            time_limit_secs: self.time_limit.map(|limit| limit.as_secs()),
+            rules: Some(self.rules()),
+        }
+    }
+
+    /// Whether a win goes on the leaderboard. Imported puzzles don't compare
+    /// with generated ones of their nominal difficulty, and zen games aren't timed.
+    pub fn is_ranked(&self) -> bool {
+        self.title.is_none() && self.mode != GameMode::Zen
+    }
+
+    /// Whether wrong entries are charged as mistakes.
+    pub fn counts_mistakes(&self) -> bool {
+        self.check_mode.counts_mistakes() && self.mode != GameMode::Zen
+    }
+
+    /// Hints taken so far; zen games don't spend them, so they are counted
+    /// from the move history instead.
+    pub fn hints_used(&self) -> usize {
+        if self.mode == GameMode::Zen {
+            self.moves.iter().filter(|m| m.hint).count()
+        } else {
+            usize::from(self.hints_max.saturating_sub(self.hints_left))
+        }
+    }
+
+    /// The regions that must hold every digit once in this game.
+    pub fn regions(&self) -> Vec<Region> {
+        crate::puzzle::regions(self.shape, self.variant, &self.boxes)
+    }
+
+    /// The Killer cage `(row, col)` belongs to, if any.
+    pub fn cage_at(&self, row: usize, col: usize) -> Option<&Cage> {
+        self.cages
+            .iter()
+            .find(|cage| cage.cells.contains(&(row, col)))
+    }
+
+    /// Wall-clock time since the last input; zero if the clock went backwards.
+    pub fn idle_for(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(self.last_played_at)
+            .unwrap_or_default()
+    }
+
+    pub fn touch(&mut self) {
+        self.last_played_at = SystemTime::now();
+    }
+
+    /// Runs the clock only while the board is visible: playing, not paused,
+    /// and not covered by the help overlay.
+    pub fn sync_clock(&mut self) {
+        let running = self.screen == Screen::Playing && !self.paused && !self.help_visible;
+        self.clock.set_running(running);
+    }
+
+    pub fn pause(&mut self) {
+        if self.screen == Screen::Playing {
+            self.paused = true;
+            self.sync_clock();
+        }
+    }
+
+    /// Pauses after `settings.auto_pause_secs` without input (0 disables it).
+    pub fn pause_if_idle(&mut self) {
+        let limit = self.settings.auto_pause_secs;
+        if !self.paused && limit > 0 && self.idle_for() >= Duration::from_secs(limit) {
+            self.pause();
+        }
+    }
+
+    /// Share of the non-given cells that hold a digit.
+    pub fn progress_percent(&self) -> u8 {
+        let open: Vec<&Cell> = self.grid.iter().flatten().filter(|c| !c.given).collect();
+        if open.is_empty() {
+            return 100;
+        }
+        let filled = open.iter().filter(|c| c.value.is_some()).count();
+        (filled * 100 / open.len()) as u8
+    }
+
+    /// Whether there is a real game behind the current screen, as opposed to
+    /// the placeholder kept behind the level selector.
+    pub fn has_game(&self) -> bool {
+        match self.screen {
+            Screen::Playing | Screen::Win | Screen::TimeUp | Screen::Replay | Screen::Analysis => {
+                true
+            }
+            Screen::Slots => self.browser.return_to == Screen::Playing,
+            Screen::LevelSelector | Screen::Settings | Screen::Tutorial | Screen::Packs => false,
+        }
+    }
+
+    /// Shows the win screen for the game just finished.
+    pub fn open_win(&mut self) {
+        self.win = WinView {
+            selected: WinAction::NextPuzzle,
+            top: self.load_top(),
+        };
+        self.screen = Screen::Win;
+    }
+
+    /// The best results in this game's category, for the end screens.
+    fn load_top(&self) -> Result<Vec<LeaderboardEntry>, String> {
+        if !self.is_ranked() {
+            return Ok(Vec::new());
+        }
+        Leaderboard::load()
+            .map(|board| {
+                board
+                    .get_top(self.category(), TOP_DISPLAY_COUNT)
+                    .into_iter()
+                    .cloned()
+                    .collect()
+            })
+            .map_err(|e| e.to_string())
+    }
+
+    /// The choices on the current end screen.
+    pub fn win_actions(&self) -> &'static [WinAction] {
+        if self.screen == Screen::TimeUp {
+            &WinAction::TIME_UP
+        } else {
+            &WinAction::ALL
+        }
+    }
+
+    pub fn move_win_selection(&mut self, forward: bool) {
+        let all = self.win_actions();
+        let idx = all
+            .iter()
+            .position(|a| *a == self.win.selected)
+            .unwrap_or(0);
+        let len = all.len();
+        self.win.selected = if forward {
+            all[(idx + 1) % len]
+        } else {
+            all[(idx + len - 1) % len]
+        };
+    }
+
+    pub fn confirm_win_action(&mut self) -> Result<(), PersistError> {
+        match self.win.selected {
+            WinAction::TryAgain if self.pack.is_some() => {
+                let puzzle = self.pack.clone().expect("checked above");
+                self.play_pack(&puzzle)?;
+            }
+            WinAction::NextPuzzle if self.pack.is_some() => self.continue_pack()?,
+            WinAction::NextPuzzle | WinAction::TryAgain => self.new_game(self.difficulty),
+            WinAction::LevelSelector => {
+                self.selector_selection = difficulty_option(self.difficulty);
+                self.screen = Screen::LevelSelector;
+            }
+            WinAction::ViewReplay => {
+                self.replay_step = 0;
+                self.screen = Screen::Replay;
+            }
+            WinAction::Analysis => {
+                if self.analysis.is_none() {
+                    self.analysis = Some(Analysis::of(self));
                 }
+                self.analysis_step = 0;
+                self.screen = Screen::Analysis;
+            }
+            WinAction::Export => {
+                let path = crate::share::export(self)?;
+                self.show_toast(format!("Result exported to {}", path.display()));
             }
         }
+        Ok(())
+    }
 
-        self.validate_and_count_mistakes();
-        for row in 0..9 {
-            for col in 0..9 {
-                if self.grid[row][col].wrong {
-                    return false;
+    /// Records the value changes since `before` in the move log.
+    pub fn log_moves_since(&mut self, before: &[Vec<Option<u8>>], hint: bool) {
+        let at_secs = self.clock.elapsed().as_secs();
+        for (row, cells) in self.grid.iter().enumerate() {
+            for (col, cell) in cells.iter().enumerate() {
+                if cell.value != before[row][col] {
+                    self.moves.push(Move {
+                        row,
+                        col,
+                        value: cell.value,
+                        at_secs,
+                        hint,
+                    });
                 }
             }
         }
+    }
+
+    pub fn values(&self) -> Vec<Vec<Option<u8>>> {
+        self.grid
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.value).collect())
+            .collect()
+    }
+
+    pub fn step_analysis(&mut self, forward: bool) {
+        let len = self.analysis.as_ref().map_or(0, |a| a.steps.len());
+        self.analysis_step = if forward {
+            (self.analysis_step + 1).min(len.saturating_sub(1))
+        } else {
+            self.analysis_step.saturating_sub(1)
+        };
+    }
+
+    pub fn step_replay(&mut self, forward: bool) {
+        self.replay_step = if forward {
+            (self.replay_step + 1).min(self.moves.len())
+        } else {
+            self.replay_step.saturating_sub(1)
+        };
+    }
+
+    /// The board as it stood after the first `replay_step` moves, with the
+    /// last move selected and the clock at the time it was made.
+    pub fn replay_frame(&self) -> GameState {
+        let mut frame = self.clone();
+        for cell in frame.grid.iter_mut().flatten() {
+            if !cell.given {
+                *cell = Cell::empty();
+            }
+        }
+        let shown = &self.moves[..self.replay_step.min(self.moves.len())];
+        for m in shown {
+            frame.grid[m.row][m.col].value = m.value;
+        }
+        if let Some(last) = shown.last() {
+            frame.selection = Selection {
+                row: last.row,
+                col: last.col,
+            };
+        }
+        frame.clock =
+            PlayClock::stopped_at(Duration::from_secs(shown.last().map_or(0, |m| m.at_secs)));
+        frame
+    }
+
+    pub fn rules(&self) -> Rules {
+        Rules {
+            mistakes_max: self.mistakes_max,
+            hints: self.hints_max,
+            check_mode: self.check_mode,
+        }
+    }
 
-        true
+    pub fn open_settings(&mut self) {
+        self.settings_draft = self.settings.clone();
+        self.settings_field = SettingsField::MistakesMax;
+        self.screen = Screen::Settings;
+    }
+
+    pub fn adjust_setting(&mut self, increase: bool) {
+        if self.settings_field == SettingsField::Theme {
+            self.settings_draft.theme = self.themes.cycle(&self.settings_draft.theme, increase);
+        } else {
+            self.settings_draft.adjust(self.settings_field, increase);
+        }
+    }
+
+    pub fn open_slots(&mut self, mode: BrowserMode) {
+        let mut browser = SlotBrowser {
+            mode,
+            slots: crate::slots::list(),
+            return_to: self.screen,
+            ..SlotBrowser::default()
+        };
+        if let Some(name) = &self.slot_name {
+            browser.select_named(name);
+        }
+        self.browser = browser;
+        self.screen = Screen::Slots;
+    }
+
+    pub fn close_slots(&mut self) {
+        self.browser.name_input = None;
+        self.screen = self.browser.return_to;
+    }
+
+    /// Loads or overwrites the selected slot, or starts naming a new one.
+    pub fn confirm_slot(&mut self) -> Result<(), PersistError> {
+        let Some(slot) = self.browser.selected_slot().cloned() else {
+            if self.browser.mode == BrowserMode::Save {
+                self.browser.name_input = Some(crate::slots::default_name(self));
+            }
+            return Ok(());
+        };
+        let slot_name = (!slot.is_autosave()).then(|| slot.name.clone());
+
+        match self.browser.mode {
+            BrowserMode::Save => {
+                self.save_to_path(&slot.path)
+                    .map_err(|source| PersistError::Save {
+                        path: slot.path.clone(),
+                        source,
+                    })?;
+                self.slot_name = slot_name;
+                self.close_slots();
+            }
+            BrowserMode::Load => {
+                let loaded =
+                    GameState::load_from_path(&slot.path).map_err(|source| PersistError::Load {
+                        path: slot.path.clone(),
+                        source,
+                    })?;
+                self.resume_from(loaded);
+                self.slot_name = slot_name;
+                self.screen = Screen::Playing;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn confirm_slot_name(&mut self) -> Result<(), PersistError> {
+        let Some(name) = self.browser.name_input.take() else {
+            return Ok(());
+        };
+        let dir = crate::slots::slots_dir().ok_or(PersistError::NoStateDir)?;
+        let name = crate::slots::sanitize_name(&name);
+        crate::slots::save_in(&dir, &name, self).map_err(|source| PersistError::Save {
+            path: dir.join(format!("{name}.json")),
+            source,
+        })?;
+        self.slot_name = Some(name);
+        self.close_slots();
+        Ok(())
+    }
+
+    pub fn delete_selected_slot(&mut self) -> Result<(), PersistError> {
+        let Some(slot) = self.browser.selected_slot().cloned() else {
+            return Ok(());
+        };
+        crate::slots::delete(&slot).map_err(|source| PersistError::Delete {
+            path: slot.path.clone(),
+            source,
+        })?;
+        if self.slot_name.as_deref() == Some(slot.name.as_str()) {
+            self.slot_name = None;
+        }
+        self.browser.refresh();
+        Ok(())
+    }
+
+    pub fn duplicate_selected_slot(&mut self) -> Result<(), PersistError> {
+        let Some(slot) = self.browser.selected_slot().cloned() else {
+            return Ok(());
+        };
+        let dir = crate::slots::slots_dir().ok_or(PersistError::NoStateDir)?;
+        let name = crate::slots::duplicate_in(&dir, &slot)
+            .map_err(|source| PersistError::Save { path: dir, source })?;
+        self.browser.refresh();
+        self.browser.select_named(&name);
+        Ok(())
+    }
+
+    /// Leaves the settings screen, keeping the draft when `apply` is set.
+    pub fn close_settings(&mut self, apply: bool) -> Result<(), PersistError> {
+        self.screen = Screen::LevelSelector;
+        if !apply || self.settings_draft == self.settings {
+            return Ok(());
+        }
+
+        self.settings = self.settings_draft.clone();
+        // The placeholder game behind the selector picks up the new defaults.
+        self.mistakes_max = self.settings.mistakes_max.max(1);
+        self.hints_left = self.settings.hints;
+        self.hints_max = self.settings.hints;
+        self.check_mode = self.settings.check_mode;
+        self.ui_zoom = self.settings.default_zoom;
+        self.settings.save()
+    }
+
+    #[allow(dead_code)]
+    pub fn check_win(&mut self) -> bool {
+        if self.grid.iter().flatten().any(|cell| cell.value.is_none()) {
+            return false;
+        }
+
+        if !self.check_mode.counts_mistakes() {
+            // The puzzle has a unique solution, so a full grid that obeys
+            // the rules is that solution.
+            return !crate::puzzle::find_conflicts(
+                &self.grid,
+                &self.regions(),
+                &self.cages,
+                &self.constraints,
+            )
+            .iter()
+            .flatten()
+            .any(|conflict| *conflict);
+        }
+
+        self.mark_wrong_entries();
+        !self.grid.iter().flatten().any(|cell| cell.wrong)
     }
 
     pub fn selected_cell(&self) -> &Cell {
@@ -289,16 +1325,17 @@ impl GameState {
     }
 
     pub fn move_selection(&mut self, dir: MoveDir) {
+        let last = self.shape.size() - 1;
         match dir {
             MoveDir::Left => self.selection.col = self.selection.col.saturating_sub(1),
-            MoveDir::Right => self.selection.col = (self.selection.col + 1).min(8),
+            MoveDir::Right => self.selection.col = (self.selection.col + 1).min(last),
             MoveDir::Up => self.selection.row = self.selection.row.saturating_sub(1),
-            MoveDir::Down => self.selection.row = (self.selection.row + 1).min(8),
+            MoveDir::Down => self.selection.row = (self.selection.row + 1).min(last),
         }
     }
 
     pub fn enter_digit(&mut self, digit: u8) {
-        if !(1..=9).contains(&digit) {
+        if !(1..=self.shape.size() as u8).contains(&digit) {
             return;
         }
 
@@ -436,7 +1473,11 @@ impl GameState {
                         cell.toggle_candidate(digit);
                     }
                 }
-                crate::history::GameAction::ClearCandidates { row, col, old_mask: _ } => {
+                crate::history::GameAction::ClearCandidates {
+                    row,
+                    col,
+                    old_mask: _,
+                } => {
                     let cell = &mut self.grid[row][col];
                     if !cell.given {
                         cell.clear_candidates();
@@ -447,110 +1488,268 @@ impl GameState {
         }
     }
 
-    pub fn validate_and_count_mistakes(&mut self) {
-        let solution = crate::puzzle::get_solution(&self.grid);
+    pub fn validate_and_count_mistakes(&mut self) {
+        // Rule-based modes never compare with the solution before the grid is full.
+        if self.check_mode.is_rules_based() {
+            return;
+        }
+        self.mark_wrong_entries();
+    }
+
+    fn mark_wrong_entries(&mut self) {
+        for (cells, digits) in self.grid.iter_mut().zip(&self.solution) {
+            for (cell, &digit) in cells.iter_mut().zip(digits) {
+                if cell.given {
+                    cell.wrong = false;
+                    continue;
+                }
+
+                let is_wrong = match cell.value {
+                    Some(v) => v != digit,
+                    None => false,
+                };
+
+                if is_wrong && !cell.wrong && self.mode != GameMode::Zen {
+                    self.mistakes = self.mistakes.saturating_add(1).min(self.mistakes_max);
+                    if self.mistakes >= self.mistakes_max {
+                        self.max_mistakes_warning = true;
+                    }
+                }
+                cell.wrong = is_wrong;
+            }
+        }
+    }
+
+    pub fn save_to_path(&self, path: &Path) -> io::Result<()> {
+        let elapsed_secs = self.clock.elapsed().as_secs();
+        let data = SaveData::from_state(self, elapsed_secs);
+        let json = serde_json::to_string_pretty(&data).map_err(io::Error::other)?;
+        crate::storage::write_atomic(path, json.as_bytes())
+    }
+
+    /// Loads `path`, falling back to its newest readable backup and saying so
+    /// in a toast.
+    pub fn load_with_backups(path: &Path) -> io::Result<Self> {
+        let error = match Self::load_from_path(path) {
+            Ok(state) => return Ok(state),
+            Err(e) => e,
+        };
+        if !path.exists() {
+            return Err(error);
+        }
+
+        for n in 1..=SESSION_BACKUPS {
+            let backup = crate::storage::backup_path(path, n);
+            if let Ok(mut state) = Self::load_from_path(&backup) {
+                let message = format!(
+                    "{} could not be read ({error}); restored backup {}",
+                    path.display(),
+                    backup.display()
+                );
+                state.show_toast(message);
+                return Ok(state);
+            }
+        }
+        Err(error)
+    }
+
+    pub fn load_from_path(path: &Path) -> io::Result<Self> {
+        let json = fs::read_to_string(path)?;
+        Self::from_json(&json)
+    }
+
+    /// Parses a save of any supported version, upgrading it first.
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        let mut value: serde_json::Value = serde_json::from_str(json)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        crate::migrate::upgrade(&mut value, SAVE_MIGRATIONS)?;
+        let data: SaveData = serde_json::from_value(value)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        data.into_state()
+    }
+
+    /// Saves to the state directory, or the working directory if that fails.
+    /// The reported error is the one for the preferred location.
+    pub fn save_default(&self) -> Result<(), PersistError> {
+        let save = |path: &Path| {
+            if let Some(e) = self.save_with_backups(path)? {
+                let message = format!("cannot rotate backups of {}: {e}", path.display());
+                crate::error::log("WARN", &message);
+            }
+            Ok(())
+        };
+        let fallback = fallback_session_path();
+        let preferred = match preferred_session_path() {
+            Some(path) => match ensure_parent_dir(&path).and_then(|()| save(&path)) {
+                Ok(()) => return Ok(()),
+                Err(source) => Some(PersistError::Save { path, source }),
+            },
+            None => None,
+        };
 
-        if let Some(solution) = solution {
-            #[allow(clippy::needless_range_loop)]
-            for row in 0..9 {
-                #[allow(clippy::needless_range_loop)]
-                for col in 0..9 {
-                    let cell = &mut self.grid[row][col];
-                    if cell.given {
-                        cell.wrong = false;
-                        continue;
-                    }
+        save(&fallback).map_err(|source| {
+            preferred.unwrap_or(PersistError::Save {
+                path: fallback,
+                source,
+            })
+        })
+    }
 
-                    let is_wrong = match cell.value {
-                        Some(v) => v != solution[row][col],
-                        None => false,
-                    };
+    /// Saves to `path` after rotating its backups. A failed rotation doesn't
+    /// stop the save, as losing an old backup is better than losing the game;
+    /// its error is returned for the caller to log.
+    fn save_with_backups(&self, path: &Path) -> io::Result<Option<io::Error>> {
+        let rotation = crate::storage::rotate_backups(path, SESSION_BACKUPS).err();
+        self.save_to_path(path)?;
+        Ok(rotation)
+    }
 
-                    if is_wrong && !cell.wrong {
-                        self.mistakes = self.mistakes.saturating_add(1).min(self.mistakes_max);
-                        if self.mistakes >= self.mistakes_max {
-                            self.max_mistakes_warning = true;
-                        }
+    /// Loads the preferred session, then the fallback one. A file that exists
+    /// but can't be read is reported over one that is simply missing.
+    pub fn load_default() -> Result<Self, PersistError> {
+        let mut error = None;
+        for path in preferred_session_path()
+            .into_iter()
+            .chain([fallback_session_path()])
+        {
+            match Self::load_with_backups(&path) {
+                Ok(state) => return Ok(state),
+                Err(source) => {
+                    if error.is_none() || path.exists() {
+                        error = Some(PersistError::Load { path, source });
                     }
-                    cell.wrong = is_wrong;
                 }
             }
         }
+        Err(error.unwrap_or(PersistError::NoStateDir))
     }
+}
 
-    pub fn save_to_path(&self, path: &Path) -> io::Result<()> {
-        let elapsed_secs = self.started_at.elapsed().as_secs();
-        let data = SaveData::from_state(self, elapsed_secs);
-        let json = serde_json::to_string_pretty(&data).map_err(io::Error::other)?;
-        fs::write(path, json)
+fn difficulty_option(difficulty: Difficulty) -> DifficultyOption {
+    match difficulty {
+        Difficulty::Easy => DifficultyOption::Easy,
+        Difficulty::Medium => DifficultyOption::Medium,
+        Difficulty::Hard => DifficultyOption::Hard,
+        Difficulty::Expert => DifficultyOption::Expert,
     }
+}
 
-    pub fn load_from_path(path: &Path) -> io::Result<Self> {
-        let json = fs::read_to_string(path)?;
-        let data: SaveData = serde_json::from_str(&json)
+const SAVE_FILE: &str = "sudokui-save.json";
+/// Rotated copies kept next to the session file (`session.json.1` is newest).
+const SESSION_BACKUPS: usize = 3;
+const SAVE_VERSION: u8 = 9;
+
+/// `SAVE_MIGRATIONS[n]` upgrades a version `n + 1` save; the last one produces
+/// `SAVE_VERSION`.
+const SAVE_MIGRATIONS: &[Migration] = &[
+    save_v1_to_v2,
+    save_v2_to_v3,
+    save_v3_to_v4,
+    save_v4_to_v5,
+    save_v5_to_v6,
+    save_v6_to_v7,
+    save_v7_to_v8,
+    save_v8_to_v9,
+];
+
+/// v2 requires the fields v1 filled in with defaults, including the solution,
+/// which v1 saves from before it was cached have to be solved for.
+fn save_v1_to_v2(value: &mut serde_json::Value) -> io::Result<()> {
+    use crate::migrate::insert_default;
+    use serde_json::Value;
+
+    insert_default(value, "hints_max", Value::from(default_hints_max()));
+    insert_default(
+        value,
+        "check_mode",
+        serde_json::to_value(CheckMode::default())?,
+    );
+    insert_default(value, "ui_zoom", serde_json::to_value(UiZoom::default())?);
+    if value.get("solution").is_none_or(Value::is_null) {
+        let cells: Vec<Vec<CellSave>> = serde_json::from_value(value["grid"].clone())
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-        data.into_state()
+        let grid: Grid = cells
+            .into_iter()
+            .map(|row| row.into_iter().map(CellSave::into_cell).collect())
+            .collect();
+        if grid.len() != 9 || grid.iter().any(|row| row.len() != 9) {
+            return Err(crate::migrate::invalid("saved grid is not 9x9"));
+        }
+        let solution = crate::puzzle::get_solution(
+            &grid,
+            &crate::puzzle::regions(GridShape::CLASSIC, Variant::Classic, &[]),
+            &[],
+        )
+        .ok_or_else(|| crate::migrate::invalid("saved puzzle has no solution"))?;
+        value["solution"] = serde_json::to_value(solution)?;
     }
+    Ok(())
+}
 
-    pub fn save_default(&self) -> io::Result<()> {
-        let preferred = preferred_session_path();
-        let fallback = fallback_session_path();
+/// v3 adds the move log. Older games start with an empty one, so their
+/// replay only covers moves made after the upgrade.
+fn save_v2_to_v3(value: &mut serde_json::Value) -> io::Result<()> {
+    crate::migrate::insert_default(value, "moves", serde_json::Value::Array(Vec::new()));
+    Ok(())
+}
 
-        if let Some(path) = preferred.as_deref() {
-            if ensure_parent_dir(path).is_ok() && self.save_to_path(path).is_ok() {
-                return Ok(());
-            }
-        }
+/// v4 records the grid size; every earlier save is a classic 9x9 game.
+fn save_v3_to_v4(value: &mut serde_json::Value) -> io::Result<()> {
+    crate::migrate::insert_default(value, "shape", serde_json::to_value(GridShape::CLASSIC)?);
+    Ok(())
+}
 
-        self.save_to_path(&fallback)
-    }
+/// v5 adds the variant and its Killer cages; earlier saves are classic games.
+fn save_v4_to_v5(value: &mut serde_json::Value) -> io::Result<()> {
+    use crate::migrate::insert_default;
 
-    pub fn load_default() -> io::Result<Self> {
-        let preferred = preferred_session_path();
-        let fallback = fallback_session_path();
+    insert_default(value, "variant", serde_json::to_value(Variant::Classic)?);
+    insert_default(value, "cages", serde_json::Value::Array(Vec::new()));
+    Ok(())
+}
 
-        if let Some(path) = preferred.as_deref() {
-            if let Ok(state) = Self::load_from_path(path) {
-                return Ok(state);
-            }
-        }
+/// v6 adds Jigsaw boxes; earlier saves use the rectangular ones.
+fn save_v5_to_v6(value: &mut serde_json::Value) -> io::Result<()> {
+    crate::migrate::insert_default(value, "boxes", serde_json::Value::Array(Vec::new()));
+    Ok(())
+}
 
-        Self::load_from_path(&fallback)
-    }
+/// v7 adds imported puzzles' titles and constraints; earlier saves are
+/// generated games with neither.
+fn save_v6_to_v7(value: &mut serde_json::Value) -> io::Result<()> {
+    use crate::migrate::insert_default;
+
+    insert_default(value, "title", serde_json::Value::Null);
+    insert_default(value, "constraints", serde_json::Value::Array(Vec::new()));
+    Ok(())
 }
 
-const SAVE_FILE: &str = "sudokui-save.json";
-const SAVE_VERSION: u8 = 1;
+/// v8 adds the countdown and time-attack modes; earlier games are standard.
+fn save_v7_to_v8(value: &mut serde_json::Value) -> io::Result<()> {
+    use crate::migrate::insert_default;
 
-fn preferred_session_path() -> Option<PathBuf> {
-    if let Some(xdg_state_home) = env::var_os("XDG_STATE_HOME") {
-        return Some(
-            PathBuf::from(xdg_state_home)
-                .join("sudokui")
-                .join("session.json"),
-        );
-    }
+    insert_default(value, "mode", serde_json::to_value(GameMode::Standard)?);
+    insert_default(value, "time_limit_secs", serde_json::Value::Null);
+    insert_default(value, "run_solved", 0.into());
+    Ok(())
+}
 
-    if let Some(home) = env::var_os("HOME") {
-        return Some(
-            PathBuf::from(home)
-                .join(".local")
-                .join("state")
-                .join("sudokui")
-                .join("session.json"),
-        );
-    }
+/// v9 records which pack puzzle a game is; earlier games aren't from one.
+fn save_v8_to_v9(value: &mut serde_json::Value) -> io::Result<()> {
+    crate::migrate::insert_default(value, "pack", serde_json::Value::Null);
+    Ok(())
+}
 
-    #[cfg(windows)]
-    if let Some(local_app_data) = env::var_os("LOCALAPPDATA") {
-        return Some(
-            PathBuf::from(local_app_data)
-                .join("sudokui")
-                .join("session.json"),
-        );
-    }
+fn preferred_session_path() -> Option<PathBuf> {
+    crate::config::state_dir().map(|dir| dir.join("session.json"))
+}
 
-    None
+/// The session file `load_default` would read, if there is one.
+pub fn autosave_path() -> Option<PathBuf> {
+    preferred_session_path()
+        .into_iter()
+        .chain([fallback_session_path()])
+        .find(|path| path.exists())
 }
 
 fn fallback_session_path() -> PathBuf {
@@ -577,15 +1776,32 @@ struct SaveData {
     mistakes: u8,
     mistakes_max: u8,
     hints_left: u8,
+    hints_max: u8,
+    check_mode: CheckMode,
     input_mode: InputModeSave,
-    #[serde(default)]
     ui_zoom: UiZoom,
     show_givens: bool,
     help_visible: bool,
     screen: ScreenSave,
     selector_selection: DifficultyOptionSave,
     selection: SelectionSave,
-    grid: [[CellSave; 9]; 9],
+    shape: GridShape,
+    variant: Variant,
+    grid: Vec<Vec<CellSave>>,
+    solution: Solution,
+    cages: Vec<Cage>,
+    boxes: Vec<Region>,
+    constraints: Vec<Constraint>,
+    title: Option<String>,
+    mode: GameMode,
+    time_limit_secs: Option<u64>,
+    run_solved: u32,
+    pack: Option<PackRef>,
+    moves: Vec<Move>,
+}
+
+fn default_hints_max() -> u8 {
+    Settings::default().hints
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -599,15 +1815,23 @@ enum ScreenSave {
     LevelSelector,
     Playing,
     Win,
+    TimeUp,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 enum DifficultyOptionSave {
     Resume,
+    ContinuePack,
     Easy,
     Medium,
     Hard,
     Expert,
+    GridSize,
+    Variant,
+    Mode,
+    Packs,
+    Tutorial,
+    Settings,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -624,20 +1848,34 @@ struct CellSave {
     wrong: bool,
 }
 
+impl CellSave {
+    fn into_cell(self) -> Cell {
+        Cell {
+            given: self.given,
+            value: self.value,
+            candidates_mask: self.candidates_mask,
+            wrong: self.wrong,
+        }
+    }
+}
+
 impl SaveData {
     fn from_state(state: &GameState, elapsed_secs: u64) -> Self {
         Self {
             version: SAVE_VERSION,
             difficulty: state.difficulty,
             elapsed_secs,
-            last_played_at: SystemTime::now()
+            last_played_at: state
+                .last_played_at
                 .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
             game_completed: state.game_completed,
             mistakes: state.mistakes,
             mistakes_max: state.mistakes_max,
             hints_left: state.hints_left,
+            hints_max: state.hints_max,
+            check_mode: state.check_mode,
             input_mode: match state.input_mode {
                 InputMode::Normal => InputModeSave::Normal,
                 InputMode::Notes => InputModeSave::Notes,
@@ -646,29 +1884,60 @@ impl SaveData {
             show_givens: state.show_givens,
             help_visible: state.help_visible,
             screen: match state.screen {
-                Screen::LevelSelector => ScreenSave::LevelSelector,
-                Screen::Playing => ScreenSave::Playing,
-                Screen::Win => ScreenSave::Win,
+                Screen::LevelSelector | Screen::Settings | Screen::Tutorial | Screen::Packs => {
+                    ScreenSave::LevelSelector
+                }
+                Screen::Slots if state.browser.return_to != Screen::Playing => {
+                    ScreenSave::LevelSelector
+                }
+                Screen::Playing | Screen::Slots => ScreenSave::Playing,
+                Screen::Win | Screen::Replay | Screen::Analysis => ScreenSave::Win,
+                Screen::TimeUp => ScreenSave::TimeUp,
             },
             selector_selection: match state.selector_selection {
                 DifficultyOption::Resume => DifficultyOptionSave::Resume,
+                DifficultyOption::ContinuePack => DifficultyOptionSave::ContinuePack,
                 DifficultyOption::Easy => DifficultyOptionSave::Easy,
                 DifficultyOption::Medium => DifficultyOptionSave::Medium,
                 DifficultyOption::Hard => DifficultyOptionSave::Hard,
                 DifficultyOption::Expert => DifficultyOptionSave::Expert,
+                DifficultyOption::GridSize => DifficultyOptionSave::GridSize,
+                DifficultyOption::Variant => DifficultyOptionSave::Variant,
+                DifficultyOption::Mode => DifficultyOptionSave::Mode,
+                DifficultyOption::Packs => DifficultyOptionSave::Packs,
+                DifficultyOption::Tutorial => DifficultyOptionSave::Tutorial,
+                DifficultyOption::Settings => DifficultyOptionSave::Settings,
             },
             selection: SelectionSave {
                 row: state.selection.row,
                 col: state.selection.col,
             },
-            grid: state.grid.map(|row| {
-                row.map(|cell| CellSave {
-                    given: cell.given,
-                    value: cell.value,
-                    candidates_mask: cell.candidates_mask,
-                    wrong: cell.wrong,
+            shape: state.shape,
+            variant: state.variant,
+            grid: state
+                .grid
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|cell| CellSave {
+                            given: cell.given,
+                            value: cell.value,
+                            candidates_mask: cell.candidates_mask,
+                            wrong: cell.wrong,
+                        })
+                        .collect()
                 })
-            }),
+                .collect(),
+            solution: state.solution.clone(),
+            cages: state.cages.clone(),
+            boxes: state.boxes.clone(),
+            constraints: state.constraints.clone(),
+            title: state.title.clone(),
+            mode: state.mode,
+            time_limit_secs: state.time_limit.map(|limit| limit.as_secs()),
+            run_solved: state.run_solved,
+            pack: state.pack.clone(),
+            moves: state.moves.clone(),
         }
     }
 
@@ -680,11 +1949,90 @@ impl SaveData {
             ));
         }
 
-        let mut state = GameState::new(self.difficulty);
+        let size = self.shape.size();
+        if !is_square(&self.grid, size) || !is_square(&self.solution, size) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("saved grid does not match its size {}", self.shape),
+            ));
+        }
+        let mut caged = vec![vec![false; size]; size];
+        let cages_fit = self
+            .cages
+            .iter()
+            .flat_map(|cage| &cage.cells)
+            .all(|&(row, col)| {
+                row < size && col < size && !std::mem::replace(&mut caged[row][col], true)
+            });
+        if !cages_fit {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "saved cages do not fit the grid",
+            ));
+        }
+        if !boxes_tile(&self.boxes, size)
+            || (self.variant == Variant::Jigsaw) == self.boxes.is_empty()
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "saved boxes do not tile the grid",
+            ));
+        }
+        for constraint in &self.constraints {
+            constraint.validate(size).map_err(|e| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("saved constraint: {e}"))
+            })?;
+        }
+        let timed = matches!(self.mode, GameMode::Countdown | GameMode::TimeAttack);
+        if timed == self.time_limit_secs.is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "saved time limit does not match the game mode",
+            ));
+        }
+
+        let grid: Grid = self
+            .grid
+            .into_iter()
+            .map(|row| row.into_iter().map(CellSave::into_cell).collect())
+            .collect();
+        let solution = self.solution;
+        let givens_match = grid
+            .iter()
+            .flatten()
+            .zip(solution.iter().flatten())
+            .all(|(cell, &digit)| !cell.given || cell.value == Some(digit));
+        if !givens_match {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "saved solution does not match the givens",
+            ));
+        }
+
+        let mut state = GameState::from_puzzle(
+            self.difficulty,
+            Settings::default(),
+            self.shape,
+            self.variant,
+            Puzzle {
+                grid,
+                solution,
+                cages: self.cages,
+                boxes: self.boxes,
+                constraints: self.constraints,
+            },
+        );
+        state.title = self.title;
+        state.mode = self.mode;
+        state.time_limit = self.time_limit_secs.map(Duration::from_secs);
+        state.run_solved = self.run_solved;
+        state.pack = self.pack;
         state.game_completed = self.game_completed;
         state.mistakes_max = self.mistakes_max.max(1);
         state.mistakes = self.mistakes.min(state.mistakes_max);
         state.hints_left = self.hints_left;
+        state.hints_max = self.hints_max;
+        state.check_mode = self.check_mode;
         state.input_mode = match self.input_mode {
             InputModeSave::Normal => InputMode::Normal,
             InputModeSave::Notes => InputMode::Notes,
@@ -696,40 +2044,59 @@ impl SaveData {
             ScreenSave::LevelSelector => Screen::LevelSelector,
             ScreenSave::Playing => Screen::Playing,
             ScreenSave::Win => Screen::Win,
+            ScreenSave::TimeUp => Screen::TimeUp,
         };
         state.selector_selection = match self.selector_selection {
             DifficultyOptionSave::Resume => DifficultyOption::Resume,
+            DifficultyOptionSave::ContinuePack => DifficultyOption::ContinuePack,
             DifficultyOptionSave::Easy => DifficultyOption::Easy,
             DifficultyOptionSave::Medium => DifficultyOption::Medium,
             DifficultyOptionSave::Hard => DifficultyOption::Hard,
             DifficultyOptionSave::Expert => DifficultyOption::Expert,
+            DifficultyOptionSave::GridSize => DifficultyOption::GridSize,
+            DifficultyOptionSave::Variant => DifficultyOption::Variant,
+            DifficultyOptionSave::Mode => DifficultyOption::Mode,
+            DifficultyOptionSave::Packs => DifficultyOption::Packs,
+            DifficultyOptionSave::Tutorial => DifficultyOption::Tutorial,
+            DifficultyOptionSave::Settings => DifficultyOption::Settings,
         };
         state.selection = Selection {
-            row: self.selection.row.min(8),
-            col: self.selection.col.min(8),
+            row: self.selection.row.min(size - 1),
+            col: self.selection.col.min(size - 1),
         };
 
-        state.started_at = Instant::now()
-            .checked_sub(Duration::from_secs(self.elapsed_secs))
-            .unwrap_or_else(Instant::now);
-
-        // Restore non-given data only; givens are defined by the puzzle.
-        for row in 0..9 {
-            for col in 0..9 {
-                if state.grid[row][col].given {
-                    continue;
-                }
-                let saved = self.grid[row][col];
-                state.grid[row][col].value = saved.value;
-                state.grid[row][col].candidates_mask = saved.candidates_mask;
-                state.grid[row][col].wrong = saved.wrong;
-            }
-        }
+        state.clock = PlayClock::stopped_at(Duration::from_secs(self.elapsed_secs));
+        state.last_played_at = UNIX_EPOCH + Duration::from_secs(self.last_played_at);
+        state.moves = self
+            .moves
+            .into_iter()
+            .filter(|m| m.row < size && m.col < size)
+            .collect();
 
         Ok(state)
     }
 }
 
+fn is_square<T>(rows: &[Vec<T>], size: usize) -> bool {
+    rows.len() == size && rows.iter().all(|row| row.len() == size)
+}
+
+/// Whether `boxes` is empty, or `size` boxes of `size` cells that cover the
+/// grid once.
+fn boxes_tile(boxes: &[Region], size: usize) -> bool {
+    if boxes.is_empty() {
+        return true;
+    }
+    let mut covered = vec![vec![false; size]; size];
+    boxes.len() == size
+        && boxes.iter().all(|cells| {
+            cells.len() == size
+                && cells.iter().all(|&(row, col)| {
+                    row < size && col < size && !std::mem::replace(&mut covered[row][col], true)
+                })
+        })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -774,7 +2141,7 @@ mod tests {
     fn save_and_load_round_trip() {
         let mut state = GameState::new(Difficulty::Easy);
         state.ui_zoom = UiZoom::Small;
-        state.started_at = Instant::now() - Duration::from_secs(123);
+        state.clock = PlayClock::stopped_at(Duration::from_secs(123));
 
         // Find a non-given cell for testing values
         let mut value_cell = None;
@@ -797,7 +2164,7 @@ mod tests {
         let mut candidate_cell = None;
         for row in 0..9 {
             for col in 0..9 {
-                if !state.grid[row][col].given && !state.grid[row][col].value.is_some() {
+                if !state.grid[row][col].given && state.grid[row][col].value.is_none() {
                     state.selection = Selection { row, col };
                     state.enter_digit(2);
                     state.enter_digit(8);
@@ -818,6 +2185,12 @@ mod tests {
 
         assert_eq!(loaded.ui_zoom, UiZoom::Small);
         assert_eq!(loaded.input_mode, InputMode::Notes);
+        assert_eq!(loaded.solution, state.solution);
+        for row in 0..9 {
+            for col in 0..9 {
+                assert_eq!(loaded.grid[row][col].given, state.grid[row][col].given);
+            }
+        }
 
         // Check that the cell with a value was saved (if it wasn't a given in the loaded puzzle)
         if let Some((row, col)) = value_cell {
@@ -835,8 +2208,7 @@ mod tests {
             }
         }
 
-        let elapsed = loaded.started_at.elapsed().as_secs();
-        assert!(elapsed >= 123 && elapsed <= 126);
+        assert_eq!(loaded.clock.elapsed().as_secs(), 123);
     }
 
     #[test]
@@ -861,11 +2233,7 @@ mod tests {
         state.selection = Selection { row, col };
 
         // Get the correct value first
-        let correct_value = crate::puzzle::get_correct_value_for_cell(&state.grid, row, col);
-        assert!(
-            correct_value.is_some(),
-            "Should have a solution for the cell"
-        );
+        let correct_value = Some(state.solution[row][col]);
 
         // Apply hint
         // Note: The hint function itself doesn't decrement hints_left - that's done in input.rs
@@ -985,8 +2353,7 @@ mod tests {
                 if !state.grid[row][col].given && tested < 5 {
                     state.selection = Selection { row, col };
 
-                    let expected = crate::puzzle::get_correct_value_for_cell(&state.grid, row, col);
-                    assert!(expected.is_some(), "Cell should have correct value");
+                    let expected = Some(state.solution[row][col]);
 
                     let result = crate::puzzle::apply_hint(&mut state);
                     assert!(result, "Hint should succeed");
@@ -1117,4 +2484,688 @@ mod tests {
         state.redo();
         assert_eq!(state.grid[row][col].value, None);
     }
+
+    /// Fills the grid with the solution, then swaps two entered digits in one row.
+    fn fill_with_swapped_pair(state: &mut GameState) {
+        let solution = state.solution.clone();
+        fill_with(state, &solution);
+        for (cells, digits) in state.grid.iter_mut().zip(solution) {
+            let open: Vec<usize> = (0..9).filter(|&c| !cells[c].given).collect();
+            if let [a, b, ..] = open[..] {
+                cells[a].value = Some(digits[b]);
+                cells[b].value = Some(digits[a]);
+                return;
+            }
+        }
+    }
+
+    fn fill_with(state: &mut GameState, solution: &[Vec<u8>]) {
+        for (cells, digits) in state.grid.iter_mut().zip(solution) {
+            for (cell, digit) in cells.iter_mut().zip(digits) {
+                cell.value = Some(*digit);
+            }
+        }
+    }
+
+    #[test]
+    fn rules_only_mode_wins_only_without_conflicts() {
+        let settings = Settings {
+            check_mode: CheckMode::RulesOnly,
+            ..Settings::default()
+        };
+        let mut state = GameState::with_settings(Difficulty::Easy, settings);
+        fill_with_swapped_pair(&mut state);
+
+        state.validate_and_count_mistakes();
+        assert!(!state.check_win());
+        assert_eq!(state.mistakes, 0);
+        assert!(state.grid.iter().flatten().all(|cell| !cell.wrong));
+
+        let mut solved = GameState::with_settings(Difficulty::Easy, state.settings.clone());
+        let solution = solved.solution.clone();
+        fill_with(&mut solved, &solution);
+        assert!(solved.check_win());
+    }
+
+    #[test]
+    fn rule_modes_never_reveal_wrong_entries_while_playing() {
+        let settings = Settings {
+            check_mode: CheckMode::RulesCountAtEnd,
+            ..Settings::default()
+        };
+        let mut state = GameState::with_settings(Difficulty::Easy, settings);
+        let (row, col) = (0..81)
+            .map(|i| (i / 9, i % 9))
+            .find(|&(r, c)| !state.grid[r][c].given)
+            .unwrap();
+        let solution = state.solution.clone();
+        state.selection = Selection { row, col };
+        state.enter_digit(solution[row][col] % 9 + 1);
+
+        state.validate_and_count_mistakes();
+        assert!(!state.grid[row][col].wrong);
+        assert_eq!(state.mistakes, 0);
+        assert_eq!(state.rules().check_mode, CheckMode::RulesCountAtEnd);
+    }
+
+    /// A digit that breaks no rule yet differs from the solution.
+    fn consistent_wrong_entry(state: &GameState) -> (usize, usize, u8) {
+        (0..81)
+            .map(|i| (i / 9, i % 9))
+            .filter(|&(r, c)| state.grid[r][c].value.is_none())
+            .find_map(|(row, col)| {
+                (1..=9)
+                    .filter(|&d| d != state.solution[row][col])
+                    .find(|&d| {
+                        let mut grid = state.grid.clone();
+                        grid[row][col].value = Some(d);
+                        !crate::puzzle::find_conflicts(
+                            &grid,
+                            &state.regions(),
+                            &state.cages,
+                            &state.constraints,
+                        )[row][col]
+                    })
+                    .map(|d| (row, col, d))
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn wrong_but_consistent_entry_is_caught() {
+        let mut state = GameState::new(Difficulty::Medium);
+        let (row, col, digit) = consistent_wrong_entry(&state);
+        state.selection = Selection { row, col };
+        state.enter_digit(digit);
+
+        state.validate_and_count_mistakes();
+        assert!(state.grid[row][col].wrong);
+        assert_eq!(state.mistakes, 1);
+
+        // Hints elsewhere still come from the cached solution.
+        let (hint_row, hint_col) = (0..81)
+            .map(|i| (i / 9, i % 9))
+            .find(|&(r, c)| state.grid[r][c].value.is_none())
+            .unwrap();
+        state.selection = Selection {
+            row: hint_row,
+            col: hint_col,
+        };
+        assert!(crate::puzzle::apply_hint(&mut state));
+        assert_eq!(
+            state.grid[hint_row][hint_col].value,
+            Some(state.solution[hint_row][hint_col])
+        );
+    }
+
+    #[test]
+    fn full_grid_with_wrong_entries_is_not_a_win() {
+        let mut state = GameState::new(Difficulty::Easy);
+        fill_with_swapped_pair(&mut state);
+
+        assert!(!state.check_win());
+        assert_eq!(state.grid.iter().flatten().filter(|c| c.wrong).count(), 2);
+        assert_eq!(state.mistakes, 2);
+
+        let settings = Settings {
+            check_mode: CheckMode::RulesCountAtEnd,
+            ..Settings::default()
+        };
+        let mut state = GameState::with_settings(Difficulty::Easy, settings);
+        fill_with_swapped_pair(&mut state);
+        assert!(!state.check_win());
+        assert_eq!(state.mistakes, 2);
+    }
+
+    #[test]
+    fn v1_fixtures_upgrade_to_the_current_version() {
+        let baseline = GameState::from_json(include_str!(
+            "../tests/fixtures/migrations/session-v1-baseline.json"
+        ))
+        .unwrap();
+        assert_eq!(baseline.solution[0], [2, 7, 8, 5, 1, 9, 4, 3, 6]);
+        assert_eq!(baseline.grid[0][0].value, Some(2));
+        assert!(!baseline.grid[0][0].given);
+        assert_eq!(baseline.grid[0][5].candidates_mask, 0b1_0000_0001);
+        assert_eq!(baseline.difficulty, Difficulty::Medium);
+        assert_eq!(baseline.shape, GridShape::CLASSIC);
+        assert_eq!(baseline.mistakes, 1);
+        assert_eq!(baseline.hints_max, Settings::default().hints);
+        assert_eq!(baseline.check_mode, CheckMode::Solution);
+        assert_eq!(baseline.ui_zoom, UiZoom::default());
+        assert_eq!(baseline.input_mode, InputMode::Notes);
+        assert_eq!(baseline.clock.elapsed().as_secs(), 754);
+
+        let cached = GameState::from_json(include_str!(
+            "../tests/fixtures/migrations/session-v1-with-solution.json"
+        ))
+        .unwrap();
+        assert_eq!(cached.solution, baseline.solution);
+        assert_eq!(cached.hints_max, 4);
+        assert_eq!(cached.check_mode, CheckMode::RulesCountAtEnd);
+        assert_eq!(cached.ui_zoom, UiZoom::Small);
+        assert_eq!(cached.selector_selection, DifficultyOption::Resume);
+
+        let resaved = serde_json::to_value(SaveData::from_state(&baseline, 754)).unwrap();
+        assert_eq!(resaved["version"], SAVE_VERSION);
+        let reloaded = GameState::from_json(&resaved.to_string()).unwrap();
+        assert_eq!(reloaded.grid, baseline.grid);
+
+        let v2 = GameState::from_json(include_str!("../tests/fixtures/migrations/session-v2.json"))
+            .unwrap();
+        assert_eq!(v2.difficulty, Difficulty::Hard);
+        assert_eq!(v2.solution, baseline.solution);
+        assert!(v2.moves.is_empty());
+    }
+
+    #[test]
+    fn every_session_version_upgrades_with_defaults_for_later_fields() {
+        let fixtures = [
+            (
+                3,
+                include_str!("../tests/fixtures/migrations/session-v3.json"),
+            ),
+            (
+                4,
+                include_str!("../tests/fixtures/migrations/session-v4.json"),
+            ),
+            (
+                5,
+                include_str!("../tests/fixtures/migrations/session-v5.json"),
+            ),
+            (
+                6,
+                include_str!("../tests/fixtures/migrations/session-v6.json"),
+            ),
+            (
+                7,
+                include_str!("../tests/fixtures/migrations/session-v7.json"),
+            ),
+            (
+                8,
+                include_str!("../tests/fixtures/migrations/session-v8.json"),
+            ),
+            (
+                9,
+                include_str!("../tests/fixtures/migrations/session-v9.json"),
+            ),
+        ];
+        // Each field and the version that started saving it.
+        let added = [
+            ("moves", 3),
+            ("shape", 4),
+            ("variant", 5),
+            ("cages", 5),
+            ("boxes", 6),
+            ("title", 7),
+            ("constraints", 7),
+            ("mode", 8),
+            ("time_limit_secs", 8),
+            ("run_solved", 8),
+            ("pack", 9),
+        ];
+        for (version, json) in fixtures {
+            let raw: serde_json::Value = serde_json::from_str(json).unwrap();
+            assert_eq!(raw["version"], version);
+            for (field, since) in added {
+                assert_eq!(
+                    raw.get(field).is_some(),
+                    version >= since,
+                    "{field} in v{version}"
+                );
+            }
+
+            let state = GameState::from_json(json).unwrap();
+            assert_eq!(state.difficulty, Difficulty::Hard);
+            assert_eq!(state.hints_max, 4);
+            assert_eq!(state.clock.elapsed().as_secs(), 754);
+            assert!(state.moves.is_empty());
+            assert_eq!(state.shape, GridShape::CLASSIC);
+            assert_eq!(state.variant, Variant::Classic);
+            assert!(state.cages.is_empty());
+            assert!(state.boxes.is_empty());
+            assert_eq!(state.title, None);
+            assert!(state.constraints.is_empty());
+            assert_eq!(state.mode, GameMode::Standard);
+            assert_eq!(state.time_limit, None);
+            assert_eq!(state.run_solved, 0);
+            assert_eq!(state.pack, None);
+        }
+    }
+
+    #[test]
+    fn move_log_replays_entries_and_undos() {
+        let mut state = GameState::new(Difficulty::Easy);
+        let (row, col, digit) = consistent_wrong_entry(&state);
+        state.selection = Selection { row, col };
+
+        let before = state.values();
+        state.enter_digit(digit);
+        state.log_moves_since(&before, false);
+        let before = state.values();
+        state.undo();
+        state.log_moves_since(&before, false);
+        assert_eq!(state.moves.len(), 2);
+        assert_eq!(state.moves[1].value, None);
+
+        state.replay_step = 1;
+        assert_eq!(state.replay_frame().grid[row][col].value, Some(digit));
+        state.step_replay(true);
+        state.step_replay(true);
+        assert_eq!(state.replay_step, 2);
+        assert_eq!(state.replay_frame().grid[row][col].value, None);
+
+        let data = SaveData::from_state(&state, 0);
+        assert_eq!(data.into_state().unwrap().moves, state.moves);
+    }
+
+    #[test]
+    fn smaller_grids_limit_digits_and_movement_and_survive_a_save() {
+        let settings = Settings {
+            grid_shape: GridShape::ALL[1],
+            ..Settings::default()
+        };
+        let mut state = GameState::with_settings(Difficulty::Easy, settings);
+        assert_eq!(state.grid.len(), 6);
+        let (row, col) = (0..36)
+            .map(|i| (i / 6, i % 6))
+            .find(|&(r, c)| !state.grid[r][c].given)
+            .unwrap();
+        state.selection = Selection { row, col };
+        state.enter_digit(7);
+        assert_eq!(state.selected_cell().value, None);
+        state.enter_digit(6);
+        assert_eq!(state.selected_cell().value, Some(6));
+        for _ in 0..10 {
+            state.move_selection(MoveDir::Right);
+            state.move_selection(MoveDir::Down);
+        }
+        assert_eq!(state.selection, Selection { row: 5, col: 5 });
+
+        let json = serde_json::to_string(&SaveData::from_state(&state, 42)).unwrap();
+        let loaded = GameState::from_json(&json).unwrap();
+        assert_eq!(loaded.shape, GridShape::ALL[1]);
+        assert_eq!(loaded.grid[row][col].value, Some(6));
+        assert_eq!(loaded.solution, state.solution);
+
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value["shape"] = serde_json::json!("9x9");
+        assert!(GameState::from_json(&value.to_string()).is_err());
+    }
+
+    #[test]
+    fn killer_games_keep_their_cages_and_win_by_the_sums() {
+        let settings = Settings {
+            grid_shape: GridShape::ALL[0],
+            variant: Variant::Killer,
+            check_mode: CheckMode::RulesOnly,
+            ..Settings::default()
+        };
+        let mut state = GameState::with_settings(Difficulty::Expert, settings);
+        assert_eq!(state.variant, Variant::Killer);
+        assert!(!state.cages.is_empty());
+
+        let json = serde_json::to_string(&SaveData::from_state(&state, 5)).unwrap();
+        let loaded = GameState::from_json(&json).unwrap();
+        assert_eq!(loaded.variant, Variant::Killer);
+        assert_eq!(loaded.cages, state.cages);
+
+        let solution = state.solution.clone();
+        fill_with(&mut state, &solution);
+        assert!(state.check_win());
+
+        // Swapping two digits between cages keeps every row, column and box
+        // valid but breaks both cages' sums.
+        let mut swapped = solution;
+        for row in &mut swapped {
+            row.swap(0, 1);
+        }
+        fill_with(&mut state, &swapped);
+        let cages_hold = state.cages.iter().all(|cage| {
+            cage.cells
+                .iter()
+                .map(|&(r, c)| swapped[r][c] as u32)
+                .sum::<u32>()
+                == cage.sum
+        });
+        assert_eq!(state.check_win(), cages_hold);
+
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value["cages"][0]["cells"][0] = serde_json::json!([4, 0]);
+        assert!(GameState::from_json(&value.to_string()).is_err());
+    }
+
+    #[test]
+    fn jigsaw_games_keep_their_boxes_and_check_against_them() {
+        let settings = Settings {
+            grid_shape: GridShape::ALL[1],
+            variant: Variant::Jigsaw,
+            check_mode: CheckMode::RulesOnly,
+            ..Settings::default()
+        };
+        let mut state = GameState::with_settings(Difficulty::Hard, settings);
+        assert_eq!(state.boxes.len(), 6);
+        assert!(state
+            .boxes
+            .iter()
+            .all(|cells| state.regions().contains(cells)));
+
+        let json = serde_json::to_string(&SaveData::from_state(&state, 5)).unwrap();
+        let loaded = GameState::from_json(&json).unwrap();
+        assert_eq!(loaded.variant, Variant::Jigsaw);
+        assert_eq!(loaded.boxes, state.boxes);
+
+        let solution = state.solution.clone();
+        fill_with(&mut state, &solution);
+        assert!(state.check_win());
+
+        // Swapping two columns keeps every row and column valid, so only
+        // the boxes decide.
+        let mut swapped = solution;
+        for row in &mut swapped {
+            row.swap(0, 1);
+        }
+        fill_with(&mut state, &swapped);
+        let boxes_hold = state.boxes.iter().all(|cells| {
+            let digits: std::collections::HashSet<u8> =
+                cells.iter().map(|&(r, c)| swapped[r][c]).collect();
+            digits.len() == cells.len()
+        });
+        assert_eq!(state.check_win(), boxes_hold);
+
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let mut overlapping = value.clone();
+        overlapping["boxes"][0][0] = overlapping["boxes"][1][0].clone();
+        assert!(GameState::from_json(&overlapping.to_string()).is_err());
+        let mut missing = value;
+        missing["boxes"] = serde_json::json!([]);
+        assert!(GameState::from_json(&missing.to_string()).is_err());
+    }
+
+    #[test]
+    fn imported_games_keep_their_title_and_constraints() {
+        let mut state = GameState::new(Difficulty::Medium);
+        assert!(state.is_ranked());
+        state.title = Some("Warm-up".to_string());
+        state.constraints = vec![
+            Constraint::Thermo(vec![(0, 0), (0, 1), (0, 2)]),
+            Constraint::X([(8, 7), (8, 8)]),
+        ];
+        assert_eq!(state.label(), "Warm-up");
+        assert!(!state.is_ranked());
+
+        let json = serde_json::to_string(&SaveData::from_state(&state, 5)).unwrap();
+        let loaded = GameState::from_json(&json).unwrap();
+        assert_eq!(loaded.title, state.title);
+        assert_eq!(loaded.constraints, state.constraints);
+
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value["constraints"][1]["X"][1] = serde_json::json!([8, 9]);
+        assert!(GameState::from_json(&value.to_string()).is_err());
+    }
+
+    #[test]
+    fn countdown_games_end_when_the_clock_runs_out() {
+        let settings = Settings {
+            mode: GameMode::Countdown,
+            countdown_minutes: 5,
+            ..Settings::default()
+        };
+        let mut state = GameState::with_settings(Difficulty::Medium, settings);
+        state.screen = Screen::Playing;
+        assert_eq!(state.time_limit, Some(Duration::from_secs(300)));
+        assert_eq!(state.label(), "Medium, countdown 5m");
+
+        state.clock = PlayClock::stopped_at(Duration::from_secs(299));
+        assert_eq!(state.time_left(), Some(Duration::from_secs(1)));
+        assert!(!state.is_out_of_time());
+        state.clock = PlayClock::stopped_at(Duration::from_secs(300));
+        assert!(state.is_out_of_time());
+        state.open_time_up();
+        assert_eq!(state.screen, Screen::TimeUp);
+        assert_eq!(state.win_actions(), WinAction::TIME_UP);
+        assert!(!state.is_out_of_time());
+
+        let json = serde_json::to_string(&SaveData::from_state(&state, 300)).unwrap();
+        let loaded = GameState::from_json(&json).unwrap();
+        assert_eq!(loaded.mode, GameMode::Countdown);
+        assert_eq!(loaded.time_limit, state.time_limit);
+        assert_eq!(loaded.screen, Screen::TimeUp);
+
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value["time_limit_secs"] = serde_json::Value::Null;
+        assert!(GameState::from_json(&value.to_string()).is_err());
+    }
+
+    #[test]
+    fn zen_mode_is_kept_in_the_save() {
+        let mut state = GameState::new(Difficulty::Easy);
+        state.mode = GameMode::Zen;
+        let json = serde_json::to_string(&SaveData::from_state(&state, 60)).unwrap();
+        let loaded = GameState::from_json(&json).unwrap();
+        assert_eq!(loaded.mode, GameMode::Zen);
+        assert_eq!(loaded.time_limit, None);
+
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value["time_limit_secs"] = serde_json::Value::from(600);
+        assert!(GameState::from_json(&value.to_string()).is_err());
+    }
+
+    #[test]
+    fn pack_games_keep_their_puzzle_and_offer_to_continue() {
+        let mut state = GameState::new(Difficulty::Easy);
+        state.packs = PackBrowser::campaign();
+        assert!(!state
+            .selector_options()
+            .contains(&DifficultyOption::ContinuePack));
+        let puzzle = PackRef {
+            pack: "1-first-steps".to_string(),
+            index: 2,
+        };
+        state.packs.progress.last = Some(puzzle.clone());
+        assert_eq!(state.selector_options()[0], DifficultyOption::ContinuePack);
+
+        let game = state.packs.packs[0].game(2, Settings::default()).unwrap();
+        let json = serde_json::to_string(&SaveData::from_state(&game, 60)).unwrap();
+        let loaded = GameState::from_json(&json).unwrap();
+        assert_eq!(loaded.pack, Some(puzzle));
+        assert_eq!(loaded.title.as_deref(), Some("First Steps #3"));
+    }
+
+    #[test]
+    fn unlocking_a_campaign_pack_names_it_alongside_user_packs() {
+        let mut state = GameState::new(Difficulty::Easy);
+        state.packs = PackBrowser::campaign();
+        let source = state.packs.packs[0]
+            .puzzles
+            .iter()
+            .map(|puzzle| format!("[[puzzle]]\ngrid = \"\"\"\n{}\"\"\"\n", puzzle.grid))
+            .collect::<String>();
+        let mine = crate::packs::Pack::parse(
+            "mine",
+            &format!("title = \"Mine\"\nauthor = \"me\"\nrating = 3\n{source}"),
+            false,
+        )
+        .unwrap();
+        state.packs.packs.push(mine);
+
+        for index in 0..4 {
+            state.pack = Some(PackRef {
+                pack: "1-first-steps".to_string(),
+                index,
+            });
+            state.mark_pack_solved();
+            let toast = state.toast.take().map(|toast| toast.message);
+            let expected = (index == 3).then(|| "Warming Up unlocked!".to_string());
+            assert_eq!(toast, expected);
+        }
+    }
+
+    #[test]
+    fn time_attack_runs_carry_the_clock_across_easy_puzzles() {
+        let settings = Settings {
+            mode: GameMode::TimeAttack,
+            ..Settings::default()
+        };
+        let mut state = GameState::with_settings(Difficulty::Hard, settings);
+        assert_eq!(state.difficulty, Difficulty::Easy);
+        assert_eq!(state.time_limit, Some(TIME_ATTACK_LIMIT));
+        assert!(!state.selector_options().contains(&DifficultyOption::Hard));
+
+        state.screen = Screen::Playing;
+        state.clock = PlayClock::stopped_at(Duration::from_secs(100));
+        let first = state.solution.clone();
+        state.next_in_run();
+        assert_eq!(state.run_solved, 1);
+        assert_eq!(state.screen, Screen::Playing);
+        assert_ne!(state.solution, first);
+        assert_eq!(state.clock.elapsed(), Duration::from_secs(100));
+        assert_eq!(
+            state.category().time_limit_secs,
+            Some(TIME_ATTACK_LIMIT.as_secs())
+        );
+        assert_eq!(
+            state.active_toast().map(|t| t.message.as_str()),
+            Some("Solved! 1 puzzle so far")
+        );
+    }
+
+    #[test]
+    fn saves_from_a_newer_version_are_rejected() {
+        let state = GameState::new(Difficulty::Easy);
+        let mut data = serde_json::to_value(SaveData::from_state(&state, 0)).unwrap();
+        data["version"] = (SAVE_VERSION + 1).into();
+        assert!(GameState::from_json(&data.to_string()).is_err());
+    }
+
+    #[test]
+    fn saves_with_misshapen_constraints_are_rejected() {
+        let state = GameState::new(Difficulty::Easy);
+        let mut data = serde_json::to_value(SaveData::from_state(&state, 0)).unwrap();
+        data["title"] = "thermo".into();
+        for constraints in [
+            serde_json::json!([{ "Thermo": [] }]),
+            serde_json::json!([{ "Thermo": [[0, 0], [1, 1]] }]),
+            serde_json::json!([{ "Thermo": [[0, 0], [0, 1], [0, 0]] }]),
+            serde_json::json!([{ "Arrow": { "circle": [4, 4], "shaft": [] } }]),
+            serde_json::json!([{ "X": [[0, 0], [0, 9]] }]),
+        ] {
+            data["constraints"] = constraints;
+            let error = GameState::from_json(&data.to_string()).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+
+        data["constraints"] = serde_json::json!([{ "Thermo": [[0, 0], [0, 1]] }]);
+        assert!(GameState::from_json(&data.to_string()).is_ok());
+    }
+
+    #[test]
+    fn old_save_without_solution_is_solved_from_givens() {
+        let mut state = GameState::new(Difficulty::Easy);
+        let (row, col, digit) = consistent_wrong_entry(&state);
+        state.selection = Selection { row, col };
+        state.enter_digit(digit);
+
+        let mut data = serde_json::to_value(SaveData::from_state(&state, 0)).unwrap();
+        data["version"] = 1.into();
+        data.as_object_mut().unwrap().remove("solution");
+        let loaded = GameState::from_json(&data.to_string()).unwrap();
+
+        assert_eq!(loaded.solution, state.solution);
+        assert_eq!(loaded.grid[row][col].value, Some(digit));
+    }
+
+    #[test]
+    fn last_played_at_survives_a_save() {
+        let mut state = GameState::new(Difficulty::Medium);
+        state.last_played_at = SystemTime::now() - Duration::from_secs(2 * 3600 + 30);
+        let data = SaveData::from_state(&state, 754);
+        let loaded = data.into_state().unwrap();
+
+        let idle = loaded.idle_for().as_secs();
+        assert!((7230..=7233).contains(&idle));
+        assert_eq!(SaveSummary::of(&loaded).elapsed.as_secs(), 754);
+    }
+
+    #[test]
+    fn selector_only_cycles_through_resume_when_offered() {
+        let mut state = GameState::new(Difficulty::Easy);
+        state.selector_selection = DifficultyOption::Easy;
+        state.move_selector(false);
+        assert_eq!(state.selector_selection, DifficultyOption::Settings);
+
+        state.recent_save = Some(SaveSummary::of(&state));
+        state.move_selector(true);
+        assert_eq!(state.selector_selection, DifficultyOption::Resume);
+        state.move_selector(true);
+        assert_eq!(state.selector_selection, DifficultyOption::Easy);
+    }
+
+    #[test]
+    fn progress_counts_only_open_cells() {
+        let mut state = GameState::new(Difficulty::Easy);
+        assert_eq!(state.progress_percent(), 0);
+        let solution = state.solution.clone();
+        fill_with(&mut state, &solution);
+        assert_eq!(state.progress_percent(), 100);
+
+        assert!(!state.has_game());
+        state.screen = Screen::Playing;
+        state.open_slots(BrowserMode::Load);
+        assert!(state.has_game());
+        state.close_slots();
+        assert_eq!(state.screen, Screen::Playing);
+    }
+
+    #[test]
+    fn damaged_session_falls_back_to_newest_valid_backup() {
+        let dir = std::env::temp_dir().join(format!("sudokui-backups-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("session.json");
+
+        for difficulty in [Difficulty::Hard, Difficulty::Medium, Difficulty::Easy] {
+            let rotation = GameState::new(difficulty).save_with_backups(&path).unwrap();
+            assert!(rotation.is_none());
+        }
+        assert!(GameState::load_with_backups(&path).unwrap().toast.is_none());
+
+        // A torn write of the newest save, and the newest backup damaged too.
+        fs::write(&path, "{\"version\": 1, \"diffic").unwrap();
+        fs::write(crate::storage::backup_path(&path, 1), "").unwrap();
+
+        let restored = GameState::load_with_backups(&path).unwrap();
+        assert_eq!(restored.difficulty, Difficulty::Hard);
+        let toast = restored.toast.unwrap();
+        assert!(toast.message.contains("restored backup"));
+        assert!(!toast.is_error);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn session_is_saved_even_when_backups_cannot_rotate() {
+        let dir = std::env::temp_dir().join(format!("sudokui-rotate-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("session.json");
+        GameState::new(Difficulty::Hard)
+            .save_with_backups(&path)
+            .unwrap();
+        // Directories in every backup slot can't be shifted along.
+        for n in 1..=SESSION_BACKUPS {
+            let slot = crate::storage::backup_path(&path, n);
+            fs::create_dir_all(&slot).unwrap();
+            fs::write(slot.join("keep"), "").unwrap();
+        }
+
+        let rotation = GameState::new(Difficulty::Easy)
+            .save_with_backups(&path)
+            .unwrap();
+        assert!(rotation.is_some());
+        let loaded = GameState::load_from_path(&path).unwrap();
+        assert_eq!(loaded.difficulty, Difficulty::Easy);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
 }
@@ -0,0 +1,86 @@
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Writes `contents` to a temp file next to `path`, syncs it, then renames it
+/// over `path`, so a crash leaves either the old file or the new one.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let tmp = sibling(path, ".tmp");
+    let written = File::create(&tmp).and_then(|mut file| {
+        file.write_all(contents)?;
+        file.sync_all()
+    });
+    if let Err(e) = written.and_then(|()| fs::rename(&tmp, path)) {
+        let _ = fs::remove_file(&tmp);
+        return Err(e);
+    }
+
+    // Make the rename itself durable.
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        let dir = if parent.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            parent
+        };
+        if let Ok(dir) = File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+    Ok(())
+}
+
+/// `session.json.1` is the newest backup, `session.json.<keep>` the oldest.
+pub fn backup_path(path: &Path, n: usize) -> PathBuf {
+    sibling(path, &format!(".{n}"))
+}
+
+/// Shifts the existing backups up by one and copies `path` into slot 1.
+pub fn rotate_backups(path: &Path, keep: usize) -> io::Result<()> {
+    if keep == 0 || !path.exists() {
+        return Ok(());
+    }
+    for n in (1..keep).rev() {
+        let from = backup_path(path, n);
+        if from.exists() {
+            fs::rename(&from, backup_path(path, n + 1))?;
+        }
+    }
+    fs::copy(path, backup_path(path, 1))?;
+    Ok(())
+}
+
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn atomic_write_replaces_and_backups_rotate() {
+        let dir = std::env::temp_dir().join(format!("sudokui-storage-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("session.json");
+
+        for i in 0..5 {
+            rotate_backups(&path, 3).unwrap();
+            write_atomic(&path, format!("save {i}").as_bytes()).unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "save 4");
+        assert_eq!(fs::read_to_string(backup_path(&path, 1)).unwrap(), "save 3");
+        assert_eq!(fs::read_to_string(backup_path(&path, 3)).unwrap(), "save 1");
+        assert!(!backup_path(&path, 4).exists());
+        assert!(!sibling(&path, ".tmp").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,363 @@
+use std::{fs, path::Path, str::FromStr};
+
+use ratatui::style::{Color, Modifier, Style};
+use serde::Deserialize;
+
+pub const DEFAULT_THEME: &str = "dark";
+pub const THEMES_DIR: &str = "themes";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    pub name: String,
+    pub given: Style,
+    pub user_digit: Style,
+    pub wrong: Style,
+    pub conflict: Style,
+    pub selection: Style,
+    pub peer: Style,
+    pub highlight: Style,
+    pub notes: Style,
+    pub border: Style,
+    /// Shading for a variant's extra regions, such as Sudoku-X diagonals.
+    pub region: Style,
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Self {
+            name: "dark".to_string(),
+            given: Style::default().add_modifier(Modifier::BOLD),
+            user_digit: Style::default(),
+            wrong: Style::default().fg(Color::Red),
+            conflict: Style::default()
+                .fg(Color::LightRed)
+                .add_modifier(Modifier::UNDERLINED),
+            selection: Style::default().add_modifier(Modifier::REVERSED),
+            peer: Style::default().bg(Color::Indexed(236)),
+            highlight: Style::default().bg(Color::DarkGray),
+            notes: Style::default().fg(Color::Gray),
+            border: Style::default(),
+            region: Style::default().bg(Color::Indexed(238)),
+        }
+    }
+
+    pub fn light() -> Self {
+        Self {
+            name: "light".to_string(),
+            given: Style::default()
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+            user_digit: Style::default().fg(Color::Blue),
+            wrong: Style::default().fg(Color::Red),
+            conflict: Style::default()
+                .fg(Color::Magenta)
+                .add_modifier(Modifier::UNDERLINED),
+            selection: Style::default().add_modifier(Modifier::REVERSED),
+            peer: Style::default().bg(Color::Indexed(254)),
+            highlight: Style::default().bg(Color::Indexed(250)),
+            notes: Style::default().fg(Color::DarkGray),
+            border: Style::default().fg(Color::DarkGray),
+            region: Style::default().bg(Color::Indexed(252)),
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        Self {
+            name: "high-contrast".to_string(),
+            given: Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+            user_digit: Style::default()
+                .fg(Color::LightCyan)
+                .add_modifier(Modifier::BOLD),
+            wrong: Style::default()
+                .fg(Color::White)
+                .bg(Color::Red)
+                .add_modifier(Modifier::BOLD),
+            conflict: Style::default()
+                .fg(Color::Black)
+                .bg(Color::LightMagenta)
+                .add_modifier(Modifier::BOLD),
+            selection: Style::default()
+                .fg(Color::Black)
+                .bg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+            peer: Style::default().bg(Color::Blue),
+            highlight: Style::default().fg(Color::Black).bg(Color::LightGreen),
+            notes: Style::default().fg(Color::LightYellow),
+            border: Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+            region: Style::default().bg(Color::DarkGray),
+        }
+    }
+
+    /// Okabe-Ito colours, so wrong cells never rely on red against green.
+    pub fn deuteranopia() -> Self {
+        Self {
+            name: "deuteranopia".to_string(),
+            given: Style::default().add_modifier(Modifier::BOLD),
+            user_digit: Style::default().fg(Color::Rgb(86, 180, 233)),
+            wrong: Style::default()
+                .fg(Color::Rgb(230, 159, 0))
+                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            conflict: Style::default()
+                .fg(Color::Rgb(204, 121, 167))
+                .add_modifier(Modifier::UNDERLINED),
+            selection: Style::default().add_modifier(Modifier::REVERSED),
+            peer: Style::default().bg(Color::Indexed(236)),
+            highlight: Style::default().bg(Color::Rgb(0, 90, 140)),
+            notes: Style::default().fg(Color::Rgb(240, 228, 66)),
+            border: Style::default(),
+            region: Style::default().bg(Color::Indexed(238)),
+        }
+    }
+
+    pub fn monochrome() -> Self {
+        Self {
+            name: "monochrome".to_string(),
+            given: Style::default().add_modifier(Modifier::BOLD),
+            user_digit: Style::default(),
+            wrong: Style::default().add_modifier(Modifier::CROSSED_OUT | Modifier::UNDERLINED),
+            conflict: Style::default().add_modifier(Modifier::BOLD | Modifier::ITALIC),
+            selection: Style::default().add_modifier(Modifier::REVERSED),
+            peer: Style::default().add_modifier(Modifier::DIM),
+            highlight: Style::default().add_modifier(Modifier::UNDERLINED),
+            notes: Style::default().add_modifier(Modifier::ITALIC),
+            border: Style::default(),
+            region: Style::default().add_modifier(Modifier::ITALIC),
+        }
+    }
+
+    pub fn built_in() -> Vec<Self> {
+        vec![
+            Self::dark(),
+            Self::light(),
+            Self::high_contrast(),
+            Self::deuteranopia(),
+            Self::monochrome(),
+        ]
+    }
+
+    /// Parses a user theme; elements left out are taken from `base`.
+    pub fn from_toml(source: &str, fallback_name: &str, catalog: &[Theme]) -> Result<Self, String> {
+        let file: ThemeFile = toml::from_str(source).map_err(|e| e.message().to_string())?;
+        let base_name = file.base.as_deref().unwrap_or(DEFAULT_THEME);
+        let mut theme = catalog
+            .iter()
+            .find(|t| t.name == base_name)
+            .cloned()
+            .ok_or_else(|| format!("unknown base theme `{base_name}`"))?;
+        theme.name = file.name.unwrap_or_else(|| fallback_name.to_string());
+
+        let slots = [
+            (file.given, &mut theme.given),
+            (file.user_digit, &mut theme.user_digit),
+            (file.wrong, &mut theme.wrong),
+            (file.conflict, &mut theme.conflict),
+            (file.selection, &mut theme.selection),
+            (file.peer, &mut theme.peer),
+            (file.highlight, &mut theme.highlight),
+            (file.notes, &mut theme.notes),
+            (file.border, &mut theme.border),
+            (file.region, &mut theme.region),
+        ];
+        for (spec, slot) in slots {
+            if let Some(spec) = spec {
+                *slot = spec.to_style()?;
+            }
+        }
+
+        Ok(theme)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ThemeFile {
+    name: Option<String>,
+    base: Option<String>,
+    given: Option<StyleSpec>,
+    user_digit: Option<StyleSpec>,
+    wrong: Option<StyleSpec>,
+    conflict: Option<StyleSpec>,
+    selection: Option<StyleSpec>,
+    peer: Option<StyleSpec>,
+    highlight: Option<StyleSpec>,
+    notes: Option<StyleSpec>,
+    border: Option<StyleSpec>,
+    region: Option<StyleSpec>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct StyleSpec {
+    fg: Option<String>,
+    bg: Option<String>,
+    bold: bool,
+    dim: bool,
+    italic: bool,
+    underline: bool,
+    reversed: bool,
+    crossed_out: bool,
+}
+
+impl StyleSpec {
+    fn to_style(&self) -> Result<Style, String> {
+        let color =
+            |name: &str| Color::from_str(name).map_err(|_| format!("unknown color `{name}`"));
+
+        let mut style = Style::default();
+        if let Some(fg) = &self.fg {
+            style = style.fg(color(fg)?);
+        }
+        if let Some(bg) = &self.bg {
+            style = style.bg(color(bg)?);
+        }
+        for (enabled, modifier) in [
+            (self.bold, Modifier::BOLD),
+            (self.dim, Modifier::DIM),
+            (self.italic, Modifier::ITALIC),
+            (self.underline, Modifier::UNDERLINED),
+            (self.reversed, Modifier::REVERSED),
+            (self.crossed_out, Modifier::CROSSED_OUT),
+        ] {
+            if enabled {
+                style = style.add_modifier(modifier);
+            }
+        }
+        Ok(style)
+    }
+}
+
+/// Built-in themes followed by any found in the config `themes` directory.
+#[derive(Debug, Clone)]
+pub struct ThemeCatalog {
+    themes: Vec<Theme>,
+    pub problems: Vec<String>,
+}
+
+impl ThemeCatalog {
+    pub fn load() -> Self {
+        let mut catalog = Self::default();
+        if let Some(dir) = crate::config::config_dir() {
+            catalog.load_dir(&dir.join(THEMES_DIR));
+        }
+        catalog
+    }
+
+    fn load_dir(&mut self, dir: &Path) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let stem = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let parsed = fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|source| Theme::from_toml(&source, &stem, &self.themes));
+            match parsed {
+                Ok(theme) => self.insert(theme),
+                Err(e) => self.problems.push(format!("{}: {e}", path.display())),
+            }
+        }
+    }
+
+    fn insert(&mut self, theme: Theme) {
+        match self.themes.iter_mut().find(|t| t.name == theme.name) {
+            Some(existing) => *existing = theme,
+            None => self.themes.push(theme),
+        }
+    }
+
+    /// The named theme, or the default one if it no longer exists.
+    pub fn get(&self, name: &str) -> &Theme {
+        self.themes
+            .iter()
+            .find(|t| t.name == name)
+            .or_else(|| self.themes.iter().find(|t| t.name == DEFAULT_THEME))
+            .unwrap_or(&self.themes[0])
+    }
+
+    pub fn cycle(&self, current: &str, forward: bool) -> String {
+        let len = self.themes.len();
+        let idx = self.themes.iter().position(|t| t.name == current);
+        let next = match (idx, forward) {
+            (None, _) => 0,
+            (Some(i), true) => (i + 1) % len,
+            (Some(i), false) => (i + len - 1) % len,
+        };
+        self.themes[next].name.clone()
+    }
+}
+
+impl Default for ThemeCatalog {
+    fn default() -> Self {
+        Self {
+            themes: Theme::built_in(),
+            problems: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_theme_inherits_from_base() {
+        let catalog = ThemeCatalog::default();
+        let theme = Theme::from_toml(
+            r##"
+            base = "light"
+
+            [wrong]
+            fg = "#ff8800"
+            bold = true
+            "##,
+            "sunset",
+            &catalog.themes,
+        )
+        .unwrap();
+
+        assert_eq!(theme.name, "sunset");
+        assert_eq!(
+            theme.wrong,
+            Style::default()
+                .fg(Color::Rgb(0xff, 0x88, 0x00))
+                .add_modifier(Modifier::BOLD)
+        );
+        assert_eq!(theme.given, Theme::light().given);
+    }
+
+    #[test]
+    fn rejects_unknown_colors_and_bases() {
+        let catalog = ThemeCatalog::default();
+        let err = Theme::from_toml("[given]\nfg = \"chartreuse\"", "x", &catalog.themes);
+        assert_eq!(err, Err("unknown color `chartreuse`".to_string()));
+
+        let err = Theme::from_toml("base = \"solarized\"", "x", &catalog.themes);
+        assert_eq!(err, Err("unknown base theme `solarized`".to_string()));
+    }
+
+    #[test]
+    fn catalog_cycles_and_falls_back() {
+        let mut catalog = ThemeCatalog::default();
+        assert_eq!(catalog.cycle("dark", true), "light");
+        assert_eq!(catalog.cycle("dark", false), "monochrome");
+        assert_eq!(catalog.get("missing").name, DEFAULT_THEME);
+
+        let mut custom = Theme::monochrome();
+        custom.name = "dark".to_string();
+        catalog.insert(custom.clone());
+        assert_eq!(catalog.get("dark"), &custom);
+        assert_eq!(catalog.themes.len(), Theme::built_in().len());
+    }
+}
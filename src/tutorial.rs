@@ -0,0 +1,221 @@
+use std::sync::OnceLock;
+
+use serde::Deserialize;
+
+use crate::{
+    import::{cell_name, parse_cell, parse_grid},
+    puzzle::{GridShape, Puzzle, Variant},
+};
+
+/// The built-in lessons, in the order they are taught.
+const LESSON_FILES: [&str; 5] = [
+    include_str!("../lessons/01-full-house.toml"),
+    include_str!("../lessons/02-naked-single.toml"),
+    include_str!("../lessons/03-hidden-single.toml"),
+    include_str!("../lessons/04-naked-pair.toml"),
+    include_str!("../lessons/05-hidden-pair.toml"),
+];
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LessonFile {
+    title: String,
+    /// Written like a puzzle file's grid.
+    grid: String,
+    /// Shown once the last step is done.
+    summary: String,
+    step: Vec<StepFile>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StepFile {
+    text: String,
+    #[serde(default)]
+    highlight: Vec<String>,
+    cell: String,
+    digit: u8,
+}
+
+/// A technique taught on a curated 9x9 board, one placement at a time.
+#[derive(Debug, Clone)]
+pub struct Lesson {
+    pub title: String,
+    pub puzzle: Puzzle,
+    pub steps: Vec<Step>,
+    pub summary: String,
+}
+
+/// One explanation and the placement that completes it.
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub text: String,
+    /// Cells the explanation points at.
+    pub highlight: Vec<(usize, usize)>,
+    pub cell: (usize, usize),
+    pub digit: u8,
+}
+
+impl Lesson {
+    /// Reads a lesson file, checking that each step places the solution's
+    /// digit in a cell that is still empty.
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let file: LessonFile = toml::from_str(source).map_err(|e| e.message().to_string())?;
+        let shape = GridShape::CLASSIC;
+        let grid = parse_grid(&file.grid, shape)?;
+        let regions = crate::puzzle::regions(shape, Variant::Classic, &[]);
+        let solution =
+            crate::puzzle::unique_solution(&grid, &regions, &[]).map_err(str::to_string)?;
+
+        let mut filled: Vec<Vec<bool>> = grid
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.value.is_some()).collect())
+            .collect();
+        let mut steps = Vec::new();
+        for step in file.step {
+            let cell = parse_cell(&step.cell, shape)?;
+            let (row, col) = cell;
+            if filled[row][col] {
+                return Err(format!("{} is already filled", step.cell));
+            }
+            if solution[row][col] != step.digit {
+                return Err(format!(
+                    "{} is {}, not {}",
+                    step.cell, solution[row][col], step.digit
+                ));
+            }
+            filled[row][col] = true;
+            let highlight = step
+                .highlight
+                .iter()
+                .map(|name| parse_cell(name, shape))
+                .collect::<Result<_, _>>()?;
+            steps.push(Step {
+                text: step.text.trim().to_string(),
+                highlight,
+                cell,
+                digit: step.digit,
+            });
+        }
+        if steps.is_empty() {
+            return Err("a lesson needs at least one step".to_string());
+        }
+
+        Ok(Lesson {
+            title: file.title,
+            puzzle: Puzzle {
+                grid,
+                solution,
+                cages: Vec::new(),
+                boxes: Vec::new(),
+                constraints: Vec::new(),
+            },
+            steps,
+            summary: file.summary.trim().to_string(),
+        })
+    }
+}
+
+/// The built-in lessons, parsed on first use.
+pub fn lessons() -> &'static [Lesson] {
+    static LESSONS: OnceLock<Vec<Lesson>> = OnceLock::new();
+    LESSONS.get_or_init(|| {
+        LESSON_FILES
+            .iter()
+            .map(|source| Lesson::parse(source).expect("built-in lessons are checked by the tests"))
+            .collect()
+    })
+}
+
+/// How far the player is through a lesson.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tutorial {
+    /// Index into `lessons()`.
+    pub lesson: usize,
+    pub step: usize,
+}
+
+impl Tutorial {
+    pub fn lesson(self) -> &'static Lesson {
+        &lessons()[self.lesson]
+    }
+
+    /// The step waiting for its placement, or `None` once the lesson is done.
+    pub fn current(self) -> Option<&'static Step> {
+        self.lesson().steps.get(self.step)
+    }
+
+    /// The footer's answer to a placement the current step doesn't want,
+    /// or `None` when it is the one.
+    pub fn reject(self, cell: (usize, usize), digit: u8) -> Option<String> {
+        let step = self.current()?;
+        if cell != step.cell {
+            Some(format!("This step is about {}", cell_name(step.cell)))
+        } else if digit != step.digit {
+            Some(format!(
+                "{digit} doesn't belong in {}; read the lesson again",
+                cell_name(cell)
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_lessons_parse_and_place_the_solution() {
+        let lessons = lessons();
+        assert_eq!(lessons.len(), LESSON_FILES.len());
+        assert_eq!(lessons[0].title, "Full house");
+        assert_eq!(lessons[4].title, "Hidden pair");
+        for lesson in lessons {
+            for step in &lesson.steps {
+                let (row, col) = step.cell;
+                assert_eq!(lesson.puzzle.grid[row][col].value, None);
+                assert!(step.highlight.iter().all(|&cell| cell != step.cell));
+            }
+        }
+    }
+
+    #[test]
+    fn explains_what_is_wrong_with_a_lesson() {
+        let source = LESSON_FILES[0];
+        let error = |source: &str| Lesson::parse(source).unwrap_err();
+        assert_eq!(
+            error(&source.replace("digit = 1", "digit = 2")),
+            "A5 is 1, not 2"
+        );
+        assert_eq!(
+            error(&source.replace("cell = \"A5\"", "cell = \"A1\"")),
+            "A1 is already filled"
+        );
+        assert_eq!(
+            error(&source.replace("cell = \"H8\"", "cell = \"A5\"")),
+            "A5 is already filled"
+        );
+        assert!(
+            error(&source.replace("digit = 3", "digit = 3\nhint = 1")).contains("unknown field")
+        );
+    }
+
+    #[test]
+    fn rejections_name_the_cell_the_step_is_about() {
+        let tutorial = Tutorial { lesson: 0, step: 0 };
+        assert_eq!(
+            tutorial.reject((0, 0), 1).as_deref(),
+            Some("This step is about A5")
+        );
+        assert_eq!(
+            tutorial.reject((0, 4), 2).as_deref(),
+            Some("2 doesn't belong in A5; read the lesson again")
+        );
+        assert_eq!(tutorial.reject((0, 4), 1), None);
+        let done = Tutorial { lesson: 0, step: 3 };
+        assert_eq!(done.current().map(|step| step.digit), None);
+        assert_eq!(done.reject((0, 4), 1), None);
+    }
+}
@@ -1,38 +1,77 @@
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     text::{Line, Span, Text},
-    widgets::{Block, Borders, Clear, Paragraph},
+    widgets::{Block, Borders, Clear, Padding, Paragraph, Wrap},
     Frame,
 };
 
 #[allow(unused_imports)]
-use crate::state::{Difficulty, DifficultyOption, GameState, InputMode, UiZoom};
+use crate::state::{
+    game_label, Difficulty, DifficultyOption, GameMode, GameState, InputMode, LastAction, MoveDir,
+    SaveSummary, Screen, UiZoom, WinAction,
+};
+use crate::{
+    analysis::{Filled, Technique},
+    input::Command,
+    keymap::{Keymap, Preset, KEYMAP_FILE},
+    puzzle::{GridShape, Region, Variant},
+    settings::SettingsField,
+    slots::BrowserMode,
+    theme::Theme,
+    tutorial::{lessons, Tutorial},
+};
 
-pub fn render(frame: &mut Frame, state: &GameState) {
+pub fn render(frame: &mut Frame, state: &GameState, keymap: &Keymap) {
     let header_line = header_line(state);
-    let footer_line =
-        "Arrows/HJKL Move  1-9 Enter  n Notes  Ctrl+n New  +/- Zoom  ? Help".to_string();
-
-    let grid_w = grid_width(state.ui_zoom) as u16;
-    let grid_h = grid_height(state.ui_zoom) as u16;
-    let side_w = side_panel_width(state) as u16;
-    let side_h = side_panel_height() as u16;
-
-    let body_w = grid_w + 1 + side_w;
-    let body_h = grid_h.max(side_h);
-
-    let inner_w = body_w
-        .max(header_line.chars().count() as u16)
-        .max(footer_line.chars().count() as u16);
-    let inner_h = 2 + body_h + 2;
+    let footer_line = if state.screen == Screen::Replay {
+        format!(
+            "{} / {} Step  {} Back to results",
+            keymap.label_for(Command::Move(MoveDir::Left)),
+            keymap.label_for(Command::Move(MoveDir::Right)),
+            keymap.label_for(Command::Back)
+        )
+    } else if state.screen == Screen::Tutorial {
+        match state.tutorial.and_then(Tutorial::current) {
+            Some(_) => format!(
+                "{} Move  {} Enter  {} Notes  {} Menu  {} Help",
+                move_keys_short(keymap),
+                state.shape.symbol_range(),
+                keymap.label_for(Command::ToggleNotes),
+                keymap.label_for(Command::Back),
+                keymap.label_for(Command::ToggleHelp)
+            ),
+            None => format!(
+                "{} Next lesson  {} Menu  {} Quit",
+                keymap.label_for(Command::SelectDifficulty),
+                keymap.label_for(Command::Back),
+                keymap.label_for(Command::Quit)
+            ),
+        }
+    } else {
+        format!(
+            "{} Move  {} Enter  {} Notes  {} New  {}/{} Zoom  {} Help",
+            move_keys_short(keymap),
+            state.shape.symbol_range(),
+            keymap.label_for(Command::ToggleNotes),
+            keymap.label_for(Command::NewGame),
+            keymap.label_for(Command::ZoomIn),
+            keymap.label_for(Command::ZoomOut),
+            keymap.label_for(Command::ToggleHelp)
+        )
+    };
 
-    let outer_w = inner_w.saturating_add(2);
-    let outer_h = inner_h.saturating_add(2);
+    let area = frame.size();
+    let text_w = (header_line.chars().count() as u16).max(footer_line.chars().count() as u16);
+    let Some(layout) = play_layout(state, area, text_w) else {
+        let smallest = smallest_play_layout(state, area);
+        render_too_small(frame, keymap, smallest.outer_w, smallest.outer_h);
+        return;
+    };
 
-    let outer_area = top_left_rect_exact(outer_w, outer_h, frame.size());
+    let outer_area = centered_rect_exact(layout.outer_w, layout.outer_h, area);
     frame.render_widget(Block::default().borders(Borders::ALL), outer_area);
 
     let inner = Rect {
@@ -42,13 +81,6 @@ pub fn render(frame: &mut Frame, state: &GameState) {
         height: outer_area.height.saturating_sub(2),
     };
 
-    let grid_w = grid_w.min(inner.width);
-    let (_sep_w, side_w) = match inner.width.saturating_sub(grid_w) {
-        0 => (0, 0),
-        1 => (1, 0),
-        remaining => (1, side_w.min(remaining - 1)),
-    };
-
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
@@ -59,25 +91,217 @@ pub fn render(frame: &mut Frame, state: &GameState) {
         .split(inner);
 
     render_header(frame, &header_line, chunks[0]);
-    render_body(frame, state, chunks[1], grid_w, side_w);
-    render_footer(frame, &footer_line, chunks[2]);
+    render_body(frame, state, chunks[1], &layout);
+    let footer = toast_line(state).unwrap_or_else(|| Line::from(footer_line));
+    render_footer(frame, footer, chunks[2]);
 
     if state.help_visible {
-        render_help(frame, inner);
+        render_help(frame, keymap, area);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PanelPlacement {
+    Side,
+    /// Condensed to a few lines beside the grid when the terminal is short.
+    SideCompact,
+    /// Condensed to a few lines under the grid when the terminal is narrow.
+    Below,
+}
+
+impl PanelPlacement {
+    const ALL: [PanelPlacement; 3] = [
+        PanelPlacement::Side,
+        PanelPlacement::SideCompact,
+        PanelPlacement::Below,
+    ];
+}
+
+/// The placements worth trying; a lesson's text needs the full side panel.
+fn placements(state: &GameState) -> &'static [PanelPlacement] {
+    if state.tutorial.is_some() {
+        &PanelPlacement::ALL[..1]
+    } else {
+        &PanelPlacement::ALL
+    }
+}
+
+/// Sizes for the play screen at one zoom and panel placement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PlayLayout {
+    zoom: UiZoom,
+    panel: PanelPlacement,
+    grid_w: u16,
+    grid_h: u16,
+    panel_w: u16,
+    outer_w: u16,
+    outer_h: u16,
+}
+
+/// The largest zoom up to the player's choice that fits `area`, keeping the
+/// side panel beside the grid where possible.
+fn play_layout(state: &GameState, area: Rect, text_w: u16) -> Option<PlayLayout> {
+    let mut zoom = state.ui_zoom;
+    loop {
+        for &panel in placements(state) {
+            let layout =
+                play_layout_for(state, zoom, panel, text_w.min(area.width.saturating_sub(2)));
+            if layout.outer_w <= area.width && layout.outer_h <= area.height {
+                return Some(layout);
+            }
+        }
+        if zoom == zoom.zoom_out() {
+            return None;
+        }
+        zoom = zoom.zoom_out();
+    }
+}
+
+/// The smallest layout to ask for: the shortest one that fits the current
+/// width, or failing that the narrowest.
+fn smallest_play_layout(state: &GameState, area: Rect) -> PlayLayout {
+    let layouts: Vec<PlayLayout> = placements(state)
+        .iter()
+        .map(|&panel| play_layout_for(state, UiZoom::Small, panel, 0))
+        .collect();
+    layouts
+        .iter()
+        .filter(|layout| layout.outer_w <= area.width)
+        .min_by_key(|layout| layout.outer_h)
+        .or_else(|| layouts.iter().min_by_key(|layout| layout.outer_w))
+        .copied()
+        .unwrap_or(layouts[0])
+}
+
+fn play_layout_for(
+    state: &GameState,
+    zoom: UiZoom,
+    panel: PanelPlacement,
+    text_w: u16,
+) -> PlayLayout {
+    let grid_w = grid_width(BoxEdges::of(state), zoom) as u16;
+    let grid_h = grid_height(BoxEdges::of(state), zoom) as u16;
+    let (panel_w, body_w, body_h) = match panel {
+        PanelPlacement::Side => {
+            let side_w = side_panel_width(state) as u16;
+            (
+                side_w,
+                grid_w + 1 + side_w,
+                grid_h.max(side_panel_height() as u16),
+            )
+        }
+        PanelPlacement::SideCompact => {
+            let compact_w = compact_panel_width(state);
+            (compact_w, grid_w + 1 + compact_w, grid_h)
+        }
+        PanelPlacement::Below => {
+            let compact_w = compact_panel_width(state);
+            (
+                compact_w,
+                grid_w.max(compact_w),
+                grid_h + COMPACT_PANEL_HEIGHT,
+            )
+        }
+    };
+    // The header and footer may widen the frame, but are clipped rather than
+    // forcing a smaller zoom.
+    let inner_w = body_w.max(text_w);
+    PlayLayout {
+        zoom,
+        panel,
+        grid_w,
+        grid_h,
+        panel_w,
+        outer_w: inner_w + 2,
+        outer_h: 2 + body_h + 2 + 2,
+    }
+}
+
+/// Replaces a screen that would be clipped beyond use.
+fn render_too_small(frame: &mut Frame, keymap: &Keymap, need_w: u16, need_h: u16) {
+    let area = frame.size();
+    let lines = vec![
+        Line::from("Terminal too small"),
+        Line::from(format!(
+            "Need {need_w}x{need_h}, have {}x{}",
+            area.width, area.height
+        )),
+        Line::from(format!(
+            "Enlarge the window or press {} to quit",
+            keymap.label_for(Command::Quit)
+        )),
+    ];
+    let mut text = vec![Line::from(""); (area.height / 2).saturating_sub(2) as usize];
+    text.extend(lines);
+    frame.render_widget(
+        Paragraph::new(Text::from(text))
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true }),
+        area,
+    );
+}
+
+/// Zen games don't run out of hints.
+fn hints_left(state: &GameState) -> String {
+    if state.mode == GameMode::Zen {
+        "unlimited".to_string()
+    } else {
+        state.hints_left.to_string()
     }
 }
 
 fn header_line(state: &GameState) -> String {
-    let elapsed = state.started_at.elapsed();
-    let timer = format_hhmmss(elapsed);
-    let warning = if state.max_mistakes_warning {
+    if let Some(tutorial) = state.tutorial {
+        return format!(
+            "TUTORIAL  Lesson {}/{}: {}",
+            tutorial.lesson + 1,
+            lessons().len(),
+            tutorial.lesson().title
+        );
+    }
+    let timer = format_hhmmss(state.clock.elapsed());
+    if state.screen == Screen::Replay {
+        return format!(
+            "REPLAY  {}  {}  Move {}/{}",
+            state.label(),
+            timer,
+            state.replay_step.min(state.moves.len()),
+            state.moves.len()
+        );
+    }
+    let warning = if state.paused {
+        " [PAUSED]"
+    } else if state.max_mistakes_warning {
         " [MAX MISTAKES!]"
     } else {
         ""
     };
+    // Zen games keep the clock and mistakes out of sight.
+    if state.mode == GameMode::Zen {
+        return format!("SUDOKUI  {}{}", state.label(), warning);
+    }
+    let mistakes = if state.check_mode.counts_mistakes() {
+        format!("{}/{}", state.mistakes, state.mistakes_max)
+    } else {
+        "off".to_string()
+    };
+    // Timed modes count down instead.
+    let timer = match state.time_left() {
+        Some(left) => format!("{} left", format_hhmmss(left)),
+        None => timer,
+    };
+    let solved = if state.mode == GameMode::TimeAttack {
+        format!("  Solved: {}", state.run_solved)
+    } else {
+        String::new()
+    };
     format!(
-        "SUDOKUI  {}  {}  Mistakes: {}/{}{}",
-        state.difficulty, timer, state.mistakes, state.mistakes_max, warning
+        "SUDOKUI  {}  {}{}  Mistakes: {}{}",
+        state.label(),
+        timer,
+        solved,
+        mistakes,
+        warning
     )
 }
 
@@ -87,40 +311,98 @@ fn render_header(frame: &mut Frame, header: &str, area: Rect) {
     frame.render_widget(Paragraph::new(text), area);
 }
 
-fn render_footer(frame: &mut Frame, footer: &str, area: Rect) {
+fn render_footer(frame: &mut Frame, footer: Line<'static>, area: Rect) {
     let sep = "─".repeat(area.width as usize);
-    let text = Text::from(vec![Line::from(sep), Line::from(footer.to_string())]);
+    let text = Text::from(vec![Line::from(sep), footer]);
     frame.render_widget(Paragraph::new(text), area);
 }
 
-fn render_body(frame: &mut Frame, state: &GameState, area: Rect, grid_w: u16, side_w: u16) {
-    let body = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Length(grid_w),
-            Constraint::Min(1),
-            Constraint::Length(side_w),
-        ])
-        .split(area);
+/// The current toast, styled like a wrong entry when it reports a failure.
+fn toast_line(state: &GameState) -> Option<Line<'static>> {
+    let toast = state.active_toast()?;
+    let style = if toast.is_error {
+        state.themes.get(&state.settings.theme).wrong
+    } else {
+        Style::default()
+    };
+    Some(Line::styled(format!("! {}", toast.message), style))
+}
+
+fn render_body(frame: &mut Frame, state: &GameState, area: Rect, layout: &PlayLayout) {
+    if state.paused {
+        render_paused(frame, area);
+        return;
+    }
 
-    render_grid(frame, state, body[0]);
-    if side_w > 0 {
-        render_side_panel(frame, state, body[2]);
+    match layout.panel {
+        PanelPlacement::Side => {
+            let body = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([
+                    Constraint::Length(layout.grid_w),
+                    Constraint::Min(1),
+                    Constraint::Length(layout.panel_w),
+                ])
+                .split(area);
+            render_grid(frame, state, layout.zoom, body[0]);
+            match state.tutorial {
+                Some(tutorial) => render_lesson_panel(frame, tutorial, body[2]),
+                None => render_side_panel(frame, state, body[2]),
+            }
+        }
+        PanelPlacement::SideCompact => {
+            let body = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([
+                    Constraint::Length(layout.grid_w),
+                    Constraint::Min(1),
+                    Constraint::Length(layout.panel_w),
+                ])
+                .split(area);
+            render_grid(frame, state, layout.zoom, body[0]);
+            render_compact_panel(frame, state, body[2]);
+        }
+        PanelPlacement::Below => {
+            let body = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Length(layout.grid_h),
+                    Constraint::Length(COMPACT_PANEL_HEIGHT),
+                ])
+                .split(area);
+            render_grid(frame, state, layout.zoom, body[0]);
+            render_compact_panel(frame, state, body[1]);
+        }
     }
 }
 
-fn render_grid(frame: &mut Frame, state: &GameState, area: Rect) {
-    frame.render_widget(Paragraph::new(Text::from(grid_text(state))), area);
+/// Stands in for the grid and side panel so the board can't be studied off the clock.
+fn render_paused(frame: &mut Frame, area: Rect) {
+    let mut lines = vec![Line::from(""); (area.height / 2).saturating_sub(1) as usize];
+    lines.push(Line::from("PAUSED"));
+    lines.push(Line::from("Press any key to resume"));
+    frame.render_widget(
+        Paragraph::new(Text::from(lines)).alignment(Alignment::Center),
+        area,
+    );
+}
+
+fn render_grid(frame: &mut Frame, state: &GameState, zoom: UiZoom, area: Rect) {
+    frame.render_widget(Paragraph::new(Text::from(grid_text(state, zoom))), area);
 }
 
-fn grid_text(state: &GameState) -> Vec<Line<'static>> {
+fn grid_text(state: &GameState, zoom: UiZoom) -> Vec<Line<'static>> {
     let mut lines: Vec<Line> = Vec::new();
 
-    let cell_w = state.ui_zoom.cell_w();
-    let cell_h = state.ui_zoom.cell_h();
+    let theme = state.themes.get(&state.settings.theme);
+    let shape = state.shape;
+    let edges = BoxEdges::of(state);
+    let size = shape.size();
+    let cell_w = zoom.cell_w();
+    let cell_h = zoom.cell_h();
 
-    lines.push(Line::from(column_header_line(cell_w)));
-    lines.push(Line::from(top_border_line(cell_w)));
+    lines.push(Line::from(column_header_line(edges, cell_w)));
+    lines.push(Line::styled(top_border_line(edges, cell_w), theme.border));
 
     let selected_cell = state.selected_cell();
     let selected_visible_value = if selected_cell.given && !state.show_givens {
@@ -128,63 +410,144 @@ fn grid_text(state: &GameState) -> Vec<Line<'static>> {
     } else {
         selected_cell.value
     };
-    let highlight_value = selected_visible_value;
+    let regions = state.regions();
+    let (glyph_right, glyph_below) = constraint_glyphs(state);
+    let marks = GridMarks {
+        highlight_value: selected_visible_value,
+        conflicts: if state.settings.highlight_conflicts || state.check_mode.is_rules_based() {
+            crate::puzzle::find_conflicts(&state.grid, &regions, &state.cages, &state.constraints)
+        } else {
+            vec![vec![false; size]; size]
+        },
+        peers: peer_grid(state, &regions),
+        shaded: cell_grid(
+            size,
+            crate::puzzle::extra_regions(shape, state.variant).iter(),
+        ),
+        cage_of: cage_index_grid(state),
+        box_of: box_index_grid(state),
+        glyph_right,
+        glyph_below,
+        lesson: lesson_grid(state),
+    };
 
-    for row in 0..9 {
+    for row in 0..size {
         let digit_line = cell_h / 2;
         for subrow in 0..cell_h {
+            // Cage sums go on the line above the digit when the zoom has one,
+            // and cage walls between rows underline the cell's last line.
+            let part = CellLines {
+                label: subrow == 0 && subrow != digit_line,
+                last: subrow + 1 == cell_h,
+                only: cell_h == 1,
+            };
             if subrow == digit_line {
-                lines.push(row_value_line(state, row, highlight_value, cell_w));
+                lines.push(row_value_line(state, theme, &marks, row, cell_w, part));
             } else {
-                lines.push(row_fill_line(state, row, highlight_value, cell_w));
+                lines.push(row_fill_line(state, theme, &marks, row, cell_w, part));
             }
         }
 
-        if row == 2 || row == 5 {
-            lines.push(Line::from(mid_border_line(cell_w)));
+        if (row + 1).is_multiple_of(edges.rows) && row + 1 < size {
+            lines.push(Line::styled(mid_border_line(edges, cell_w), theme.border));
         }
     }
 
-    lines.push(Line::from(bottom_border_line(cell_w)));
+    lines.push(Line::styled(
+        bottom_border_line(edges, cell_w),
+        theme.border,
+    ));
 
     lines
 }
 
-fn column_header_line(cell_w: usize) -> String {
+/// Where the grid's fixed box borders fall, every `rows` rows and `cols`
+/// columns. Jigsaw boxes don't line up, so their grid has none and their
+/// walls are drawn between the cells instead.
+#[derive(Debug, Clone, Copy)]
+struct BoxEdges {
+    size: usize,
+    rows: usize,
+    cols: usize,
+}
+
+impl BoxEdges {
+    fn of(state: &GameState) -> Self {
+        let shape = state.shape;
+        let size = shape.size();
+        if state.boxes.is_empty() {
+            Self {
+                size,
+                rows: shape.box_rows,
+                cols: shape.box_cols,
+            }
+        } else {
+            Self {
+                size,
+                rows: size,
+                cols: size,
+            }
+        }
+    }
+}
+
+/// What follows a column: a box border, the gap between cells, or the edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColumnEnd {
+    Box,
+    Gap,
+    Edge,
+}
+
+fn column_end(edges: BoxEdges, col: usize) -> ColumnEnd {
+    if col + 1 == edges.size {
+        ColumnEnd::Edge
+    } else if (col + 1).is_multiple_of(edges.cols) {
+        ColumnEnd::Box
+    } else {
+        ColumnEnd::Gap
+    }
+}
+
+fn column_header_line(edges: BoxEdges, cell_w: usize) -> String {
     let mut out = String::from("    ");
-    for col in 0..9 {
-        let digit = (col + 1).to_string();
-        out.push_str(&format!("{digit:^width$}", width = cell_w));
-        if col == 2 || col == 5 {
-            out.push_str("   ");
-        } else if col != 8 {
-            out.push(' ');
+    for col in 0..edges.size {
+        let label = (col + 1).to_string();
+        // One-column cells keep the last digit of 10 and up, like a ruler.
+        let label = &label[label.len().saturating_sub(cell_w.max(1))..];
+        out.push_str(&format!("{label:^width$}", width = cell_w));
+        match column_end(edges, col) {
+            ColumnEnd::Box => out.push_str("   "),
+            ColumnEnd::Gap => out.push(' '),
+            ColumnEnd::Edge => {}
         }
     }
     out
 }
 
-fn top_border_line(cell_w: usize) -> String {
-    border_line('┌', '┬', '┐', cell_w)
+fn top_border_line(edges: BoxEdges, cell_w: usize) -> String {
+    border_line('┌', '┬', '┐', edges, cell_w)
 }
 
-fn mid_border_line(cell_w: usize) -> String {
-    border_line('├', '┼', '┤', cell_w)
+fn mid_border_line(edges: BoxEdges, cell_w: usize) -> String {
+    border_line('├', '┼', '┤', edges, cell_w)
 }
 
-fn bottom_border_line(cell_w: usize) -> String {
-    border_line('└', '┴', '┘', cell_w)
+fn bottom_border_line(edges: BoxEdges, cell_w: usize) -> String {
+    border_line('└', '┴', '┘', edges, cell_w)
 }
 
-fn border_line(left: char, mid: char, right: char, cell_w: usize) -> String {
-    let block_w = (cell_w * 3) + 4;
+fn border_line(left: char, mid: char, right: char, edges: BoxEdges, cell_w: usize) -> String {
+    let block_w = (cell_w * edges.cols) + edges.cols + 1;
+    let boxes = edges.size / edges.cols;
     let mut out = String::from("  ");
     out.push(left);
-    out.push_str(&"─".repeat(block_w));
-    out.push(mid);
-    out.push_str(&"─".repeat(block_w));
-    out.push(mid);
-    out.push_str(&"─".repeat(block_w));
+    for i in 0..boxes {
+        if i > 0 {
+            out.push(mid);
+        }
+        out.push_str(&"─".repeat(block_w));
+    }
     out.push(right);
     out
 }
@@ -198,56 +561,258 @@ fn cell_visible_value(state: &GameState, row: usize, col: usize) -> Option<u8> {
     }
 }
 
+/// Per-frame highlighting shared by every cell of the grid.
+struct GridMarks {
+    highlight_value: Option<u8>,
+    conflicts: Vec<Vec<bool>>,
+    /// Cells sharing a region with the selection.
+    peers: Vec<Vec<bool>>,
+    /// Cells in the variant's extra regions.
+    shaded: Vec<Vec<bool>>,
+    /// Each cell's Killer cage, as an index into `GameState::cages`.
+    cage_of: Vec<Vec<Option<usize>>>,
+    /// Each cell's box, which only needs drawing cell by cell for Jigsaw.
+    box_of: Vec<Vec<usize>>,
+    /// Constraint glyphs between each cell and the one to its right.
+    glyph_right: GlyphGrid,
+    /// Constraint glyphs between each cell and the one below it.
+    glyph_below: GlyphGrid,
+    /// Cells the current lesson step points at.
+    lesson: Vec<Vec<bool>>,
+}
+
+impl GridMarks {
+    /// What the gap between the cell and the one to its right shows: a box
+    /// wall, a dotted cage wall, or nothing.
+    fn gap_right(&self, row: usize, col: usize) -> &'static str {
+        let (boxes, cages) = (&self.box_of[row], &self.cage_of[row]);
+        if col + 1 < boxes.len() && boxes[col] != boxes[col + 1] {
+            "│"
+        } else if col + 1 < cages.len() && cages[col] != cages[col + 1] {
+            "┊"
+        } else {
+            " "
+        }
+    }
+
+    /// Whether a box or cage wall runs between the cell and the one below it.
+    fn wall_below(&self, row: usize, col: usize) -> bool {
+        row + 1 < self.cage_of.len()
+            && (self.box_of[row][col] != self.box_of[row + 1][col]
+                || self.cage_of[row][col] != self.cage_of[row + 1][col])
+    }
+
+    /// The sum to print in the cell, which is the first of its cage.
+    fn cage_label(&self, state: &GameState, row: usize, col: usize) -> Option<u32> {
+        let cage = &state.cages[self.cage_of[row][col]?];
+        (cage.cells.first() == Some(&(row, col))).then_some(cage.sum)
+    }
+}
+
+fn lesson_grid(state: &GameState) -> Vec<Vec<bool>> {
+    let size = state.shape.size();
+    let mut marked = vec![vec![false; size]; size];
+    if let Some(step) = state.tutorial.and_then(Tutorial::current) {
+        for &(row, col) in &step.highlight {
+            marked[row][col] = true;
+        }
+    }
+    marked
+}
+
+/// Marks the cells of every region in `regions`.
+fn cell_grid<'a>(size: usize, regions: impl Iterator<Item = &'a Region>) -> Vec<Vec<bool>> {
+    let mut marked = vec![vec![false; size]; size];
+    for &(row, col) in regions.flatten() {
+        marked[row][col] = true;
+    }
+    marked
+}
+
+fn peer_grid(state: &GameState, regions: &[Region]) -> Vec<Vec<bool>> {
+    let sel = (state.selection.row, state.selection.col);
+    let peer_regions = regions.iter().filter(|region| region.contains(&sel));
+    cell_grid(state.shape.size(), peer_regions)
+}
+
+fn box_index_grid(state: &GameState) -> Vec<Vec<usize>> {
+    let size = state.shape.size();
+    let standard;
+    let boxes = if state.boxes.is_empty() {
+        standard = crate::puzzle::standard_boxes(state.shape);
+        &standard
+    } else {
+        &state.boxes
+    };
+    let mut box_of = vec![vec![0; size]; size];
+    for (i, cells) in boxes.iter().enumerate() {
+        for &(row, col) in cells {
+            box_of[row][col] = i;
+        }
+    }
+    box_of
+}
+
+/// A glyph per cell, for the gap on one side of it.
+type GlyphGrid = Vec<Vec<Option<char>>>;
+
+/// The glyphs drawn between neighbouring cells: dots and X/V for pairs,
+/// `<` and `∧` pointing at the smaller end of each thermometer step, and
+/// arrows leading away from each arrow's circle. Returns the glyphs right of
+/// and below each cell.
+fn constraint_glyphs(state: &GameState) -> (GlyphGrid, GlyphGrid) {
+    use crate::puzzle::Constraint as Rule;
+
+    let size = state.shape.size();
+    let mut right = vec![vec![None; size]; size];
+    let mut below = vec![vec![None; size]; size];
+    // `glyphs` are for a step towards the right and a step downwards, then
+    // the same steps taken the other way.
+    let mut mark = |a: (usize, usize), b: (usize, usize), glyphs: [char; 4]| {
+        let (first, second) = (a.min(b), a.max(b));
+        let flipped = usize::from(a > b) * 2;
+        if first.0 == second.0 {
+            right[first.0][first.1] = Some(glyphs[flipped]);
+        } else {
+            below[first.0][first.1] = Some(glyphs[flipped + 1]);
+        }
+    };
+    for constraint in &state.constraints {
+        match constraint {
+            Rule::Thermo(cells) => {
+                for step in cells.windows(2) {
+                    mark(step[0], step[1], ['<', '∧', '>', '∨']);
+                }
+            }
+            Rule::Arrow { circle, shaft } => {
+                let path: Vec<_> = std::iter::once(circle).chain(shaft).collect();
+                for step in path.windows(2) {
+                    mark(*step[0], *step[1], ['→', '↓', '←', '↑']);
+                }
+            }
+            Rule::WhiteDot([a, b]) => mark(*a, *b, ['○'; 4]),
+            Rule::BlackDot([a, b]) => mark(*a, *b, ['●'; 4]),
+            Rule::X([a, b]) => mark(*a, *b, ['X'; 4]),
+            Rule::V([a, b]) => mark(*a, *b, ['V'; 4]),
+        }
+    }
+    (right, below)
+}
+
+fn cage_index_grid(state: &GameState) -> Vec<Vec<Option<usize>>> {
+    let size = state.shape.size();
+    let mut cage_of = vec![vec![None; size]; size];
+    for (i, cage) in state.cages.iter().enumerate() {
+        for &(row, col) in &cage.cells {
+            cage_of[row][col] = Some(i);
+        }
+    }
+    cage_of
+}
+
+/// Which of a cell's text lines is being drawn.
+#[derive(Debug, Clone, Copy)]
+struct CellLines {
+    /// The top line, when it isn't also the digit's.
+    label: bool,
+    last: bool,
+    /// The cell has no other line.
+    only: bool,
+}
+
+/// Underlines the cell's last line when a box or cage wall runs below it,
+/// unless a box border line already does.
+fn wall_edge_style(
+    state: &GameState,
+    marks: &GridMarks,
+    row: usize,
+    col: usize,
+    lines: CellLines,
+    style: Style,
+) -> Style {
+    let on_box_edge = (row + 1).is_multiple_of(BoxEdges::of(state).rows);
+    if lines.last && !on_box_edge && marks.wall_below(row, col) {
+        style.add_modifier(Modifier::UNDERLINED)
+    } else {
+        style
+    }
+}
+
 fn cell_style(
     state: &GameState,
+    theme: &Theme,
+    marks: &GridMarks,
     row: usize,
     col: usize,
-    highlight_value: Option<u8>,
     value_line: bool,
 ) -> Style {
     let cell = &state.grid[row][col];
     let selected = row == state.selection.row && col == state.selection.col;
     let visible_value = cell_visible_value(state, row, col);
+    let highlight_value = marks.highlight_value;
 
     let mut style = Style::default();
     if value_line && cell.given && state.show_givens {
-        style = style.add_modifier(Modifier::BOLD);
+        style = style.patch(theme.given);
+    } else if value_line && !cell.given && cell.value.is_some() {
+        style = style.patch(theme.user_digit);
     }
     if value_line && cell.wrong {
-        style = style.fg(Color::Red);
+        style = style.patch(theme.wrong);
+    }
+    if value_line && visible_value.is_some() && marks.conflicts[row][col] {
+        style = style.patch(theme.conflict);
+    }
+    if marks.shaded[row][col] {
+        style = style.patch(theme.region);
+    }
+    if !selected && state.settings.highlight_peers && marks.peers[row][col] {
+        style = style.patch(theme.peer);
     }
-    if !selected && highlight_value.is_some() && visible_value == highlight_value {
-        style = style.bg(Color::DarkGray);
+    if !selected
+        && (marks.lesson[row][col] || highlight_value.is_some() && visible_value == highlight_value)
+    {
+        style = style.patch(theme.highlight);
     }
     if selected {
-        style = style.add_modifier(Modifier::REVERSED);
+        style = style.patch(theme.selection);
     }
     style
 }
 
 fn row_value_line(
     state: &GameState,
+    theme: &Theme,
+    marks: &GridMarks,
     row: usize,
-    highlight_value: Option<u8>,
     cell_w: usize,
+    lines: CellLines,
 ) -> Line<'static> {
     let row_label = (b'A' + row as u8) as char;
     let mut spans = Vec::<Span>::new();
-    spans.push(Span::raw(format!("{row_label} │ ")));
+    spans.push(Span::raw(format!("{row_label} ")));
+    spans.push(Span::styled("│ ", theme.border));
 
-    for col in 0..9 {
+    for col in 0..state.shape.size() {
         let visible_value = cell_visible_value(state, row, col);
-        let ch = visible_value.map(|d| char::from(b'0' + d)).unwrap_or('.');
-        let style = cell_style(state, row, col, highlight_value, true);
-        spans.push(Span::styled(format!("{ch:^width$}", width = cell_w), style));
-
-        if col == 2 || col == 5 {
-            spans.push(Span::raw(" │ "));
-        } else if col != 8 {
-            spans.push(Span::raw(" "));
-        } else {
-            spans.push(Span::raw(" │"));
+        let ch = visible_value.map_or('.', |d| state.shape.symbol(d));
+        let style = cell_style(state, theme, marks, row, col, true);
+        let style = wall_edge_style(state, marks, row, col, lines, style);
+        // With one line per cell, a glyph for the cell below takes the
+        // cell's trailing column when it has one to spare.
+        match marks.glyph_below[row][col].filter(|_| lines.only && cell_w >= 2) {
+            Some(glyph) => {
+                let width = cell_w - 1;
+                spans.push(Span::styled(format!("{ch:^width$}"), style));
+                spans.push(Span::styled(glyph.to_string(), style.patch(theme.border)));
+            }
+            None => spans.push(Span::styled(format!("{ch:^width$}", width = cell_w), style)),
         }
+        let edges = BoxEdges::of(state);
+        spans.push(match marks.glyph_right[row][col] {
+            Some(glyph) => glyph_gap_span(edges, col, theme, glyph),
+            None => column_end_span(edges, col, theme, marks.gap_right(row, col)),
+        });
     }
 
     Line::from(spans)
@@ -255,29 +820,68 @@ fn row_value_line(
 
 fn row_fill_line(
     state: &GameState,
+    theme: &Theme,
+    marks: &GridMarks,
     row: usize,
-    highlight_value: Option<u8>,
     cell_w: usize,
+    lines: CellLines,
 ) -> Line<'static> {
     let mut spans = Vec::<Span>::new();
-    spans.push(Span::raw("  │ ".to_string()));
-
-    for col in 0..9 {
-        let style = cell_style(state, row, col, highlight_value, false);
-        spans.push(Span::styled(" ".repeat(cell_w), style));
-
-        if col == 2 || col == 5 {
-            spans.push(Span::raw(" │ "));
-        } else if col != 8 {
-            spans.push(Span::raw(" "));
-        } else {
-            spans.push(Span::raw(" │"));
+    spans.push(Span::raw("  "));
+    spans.push(Span::styled("│ ", theme.border));
+
+    for col in 0..state.shape.size() {
+        let style = cell_style(state, theme, marks, row, col, false);
+        let style = wall_edge_style(state, marks, row, col, lines, style);
+        let label = marks
+            .cage_label(state, row, col)
+            .filter(|_| lines.label)
+            .map(|sum| sum.to_string())
+            .filter(|sum| sum.len() <= cell_w);
+        // A glyph for the cell above sits centred on the cell's top line.
+        let glyph = (row > 0 && lines.label)
+            .then(|| marks.glyph_below[row - 1][col])
+            .flatten();
+        match (label, glyph) {
+            (Some(sum), _) => spans.push(Span::styled(
+                format!("{sum:<cell_w$}"),
+                style.patch(theme.notes),
+            )),
+            (None, Some(glyph)) => spans.push(Span::styled(
+                format!("{glyph:^cell_w$}"),
+                style.patch(theme.border),
+            )),
+            (None, None) => spans.push(Span::styled(" ".repeat(cell_w), style)),
         }
+        spans.push(column_end_span(
+            BoxEdges::of(state),
+            col,
+            theme,
+            marks.gap_right(row, col),
+        ));
     }
 
     Line::from(spans)
 }
 
+/// `gap` is what the gap between two cells shows, from `GridMarks::gap_right`.
+fn column_end_span(edges: BoxEdges, col: usize, theme: &Theme, gap: &'static str) -> Span<'static> {
+    match column_end(edges, col) {
+        ColumnEnd::Box => Span::styled(" │ ", theme.border),
+        ColumnEnd::Gap if gap != " " => Span::styled(gap, theme.border),
+        ColumnEnd::Gap => Span::raw(" "),
+        ColumnEnd::Edge => Span::styled(" │", theme.border),
+    }
+}
+
+/// A constraint glyph in the gap after `col`, in place of any wall there.
+fn glyph_gap_span(edges: BoxEdges, col: usize, theme: &Theme, glyph: char) -> Span<'static> {
+    match column_end(edges, col) {
+        ColumnEnd::Box => Span::styled(format!(" {glyph} "), theme.border),
+        _ => Span::styled(glyph.to_string(), theme.border),
+    }
+}
+
 fn render_side_panel(frame: &mut Frame, state: &GameState, area: Rect) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
@@ -295,20 +899,24 @@ fn render_side_panel(frame: &mut Frame, state: &GameState, area: Rect) {
         selected.value
     };
     let value = visible_value
-        .map(|d| d.to_string())
+        .map(|d| state.shape.symbol(d).to_string())
         .unwrap_or_else(|| ".".into());
     let candidates = selected.candidates();
+    let notes_style = state.themes.get(&state.settings.theme).notes;
     let candidates_line = if candidates.is_empty() {
-        "Candidates:".to_string()
+        Line::from("Candidates:")
     } else {
-        format!(
-            "Candidates: {}",
-            candidates
-                .iter()
-                .map(|d| d.to_string())
-                .collect::<Vec<_>>()
-                .join(" ")
-        )
+        Line::from(vec![
+            Span::raw("Candidates: "),
+            Span::styled(
+                candidates
+                    .iter()
+                    .map(|d| state.shape.symbol(*d).to_string())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                notes_style,
+            ),
+        ])
     };
 
     let selected_block = Block::default().borders(Borders::ALL);
@@ -318,8 +926,8 @@ fn render_side_panel(frame: &mut Frame, state: &GameState, area: Rect) {
             state.selection.row + 1,
             state.selection.col + 1
         )),
-        Line::from(format!("Value: {value}")),
-        Line::from(candidates_line),
+        Line::from(value_label(state, &value)),
+        candidates_line,
     ]);
     frame.render_widget(
         Paragraph::new(selected_text).block(selected_block),
@@ -336,7 +944,7 @@ fn render_side_panel(frame: &mut Frame, state: &GameState, area: Rect) {
             "Input: {}",
             candidates
                 .iter()
-                .map(|d| d.to_string())
+                .map(|d| state.shape.symbol(*d).to_string())
                 .collect::<Vec<_>>()
                 .join(" ")
         )
@@ -348,7 +956,7 @@ fn render_side_panel(frame: &mut Frame, state: &GameState, area: Rect) {
     let mode_text = Text::from(vec![
         Line::from(format!("Mode: {mode}")),
         Line::from(input_line),
-        Line::from(format!("Hints left: {}", state.hints_left)),
+        Line::from(format!("Hints left: {}", hints_left(state))),
     ]);
     frame.render_widget(Paragraph::new(mode_text).block(mode_block), chunks[1]);
 
@@ -363,32 +971,115 @@ fn render_side_panel(frame: &mut Frame, state: &GameState, area: Rect) {
     frame.render_widget(Paragraph::new(actions_text).block(actions_block), chunks[2]);
 }
 
-fn render_help(frame: &mut Frame, area: Rect) {
-    let help_lines: [&str; 19] = [
-        "Shortcuts",
-        "",
-        "Arrows / H J K L : Move",
-        "1-9             : Enter digit",
-        "n               : Notes mode",
-        "Ctrl+n          : New game (reset)",
-        "g               : Toggle givens",
-        "v               : Check mistakes",
-        "+ / -           : Zoom in/out",
-        "?               : Toggle this help",
-        "u/r             : Undo / Redo",
-        "h               : Hint",
-        "c               : Clear",
-        "s/o             : Save / Load",
-        "q               : Quit",
-        "",
-        "Persistence",
-        "Startup         : Auto-resume last session",
-        "Quit            : Auto-save session",
-    ];
+/// Width of the panel that explains a lesson, borders included.
+const LESSON_PANEL_WIDTH: usize = 32;
+
+/// The current step's explanation, or the lesson's summary once it is done.
+fn render_lesson_panel(frame: &mut Frame, tutorial: Tutorial, area: Rect) {
+    let lesson = tutorial.lesson();
+    let mut lines = Vec::new();
+    match tutorial.current() {
+        Some(step) => {
+            lines.push(Line::from(format!(
+                "Step {} of {}",
+                tutorial.step + 1,
+                lesson.steps.len()
+            )));
+            lines.push(Line::from(""));
+            lines.extend(step.text.lines().map(|line| Line::from(line.to_string())));
+        }
+        None => {
+            lines.push(Line::from("Lesson complete!"));
+            lines.push(Line::from(""));
+            lines.extend(
+                lesson
+                    .summary
+                    .lines()
+                    .map(|line| Line::from(line.to_string())),
+            );
+            lines.push(Line::from(""));
+            lines.push(Line::from(if tutorial.lesson + 1 < lessons().len() {
+                "Press Enter for the next lesson."
+            } else {
+                "That was the last lesson."
+            }));
+        }
+    }
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(lesson.title.clone());
+    frame.render_widget(
+        Paragraph::new(Text::from(lines))
+            .block(block)
+            .wrap(Wrap { trim: true }),
+        area,
+    );
+}
+
+/// The selected cell's value, and its cage sum in a Killer game.
+fn value_label(state: &GameState, value: &str) -> String {
+    match state.cage_at(state.selection.row, state.selection.col) {
+        Some(cage) => format!("Value: {value}  Cage: {}", cage.sum),
+        None => format!("Value: {value}"),
+    }
+}
+
+const COMPACT_PANEL_HEIGHT: u16 = 3;
+
+/// The side panel's essentials, for when it has to go under the grid.
+fn compact_panel_lines(state: &GameState) -> Vec<String> {
+    let selected = state.selected_cell();
+    let value = if selected.given && !state.show_givens {
+        None
+    } else {
+        selected.value
+    };
+    let candidates: Vec<String> = selected
+        .candidates()
+        .iter()
+        .map(|d| state.shape.symbol(*d).to_string())
+        .collect();
+    let mode = match state.input_mode {
+        InputMode::Normal => "Normal",
+        InputMode::Notes => "Notes",
+    };
+    let value = value.map_or(".".to_string(), |d| state.shape.symbol(d).to_string());
+    vec![
+        format!(
+            "R{} C{}  {}",
+            state.selection.row + 1,
+            state.selection.col + 1,
+            value_label(state, &value)
+        ),
+        format!("Candidates: {}", candidates.join(" "))
+            .trim_end()
+            .to_string(),
+        format!("Mode: {mode}  Hints: {}", hints_left(state)),
+    ]
+}
+
+fn compact_panel_width(state: &GameState) -> u16 {
+    compact_panel_lines(state)
+        .iter()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0) as u16
+}
+
+fn render_compact_panel(frame: &mut Frame, state: &GameState, area: Rect) {
+    let lines: Vec<Line> = compact_panel_lines(state)
+        .into_iter()
+        .map(Line::from)
+        .collect();
+    frame.render_widget(Paragraph::new(Text::from(lines)), area);
+}
+
+fn render_help(frame: &mut Frame, keymap: &Keymap, area: Rect) {
+    let help_lines = help_lines(keymap);
     let help_text = Text::from(
         help_lines
             .iter()
-            .map(|s| Line::from((*s).to_string()))
+            .map(|s| Line::from(s.clone()))
             .collect::<Vec<_>>(),
     );
     let popup_area = help_rect(&help_lines, area);
@@ -401,7 +1092,117 @@ fn render_help(frame: &mut Frame, area: Rect) {
     frame.render_widget(Paragraph::new(help_text).block(block), popup_area);
 }
 
-fn help_rect(lines: &[&str], area: Rect) -> Rect {
+fn help_lines(keymap: &Keymap) -> Vec<String> {
+    let key = |command: Command| keymap.label_for(command);
+    let pair = |a: Command, b: Command| format!("{} / {}", key(a), key(b));
+    let entry = |keys: String, description: &str| format!("{keys:<16}: {description}");
+
+    let mut lines = vec![
+        match keymap.preset {
+            Preset::Default => "Shortcuts".to_string(),
+            preset => format!("Shortcuts ({preset} preset)"),
+        },
+        String::new(),
+        entry(move_keys_label(keymap), "Move"),
+        entry("1-9".to_string(), "Enter digit"),
+        entry(key(Command::ToggleNotes), "Notes mode"),
+        entry(key(Command::NewGame), "New game (reset)"),
+        entry(key(Command::ToggleGivens), "Toggle givens"),
+        entry(key(Command::Validate), "Check mistakes"),
+        entry(key(Command::Pause), "Pause (any key resumes)"),
+        entry(pair(Command::ZoomIn, Command::ZoomOut), "Zoom in/out"),
+        entry(key(Command::ToggleHelp), "Toggle this help"),
+        entry(key(Command::TogglePeers), "Highlight row/col/box"),
+        entry(key(Command::ToggleConflicts), "Show rule conflicts"),
+        entry(key(Command::Back), "Close help / cancel"),
+        entry(
+            pair(
+                Command::Action(LastAction::Undo),
+                Command::Action(LastAction::Redo),
+            ),
+            "Undo / Redo",
+        ),
+        entry(key(Command::Action(LastAction::Hint)), "Hint"),
+        entry(key(Command::Action(LastAction::Clear)), "Clear"),
+        entry(
+            pair(
+                Command::Action(LastAction::Save),
+                Command::Action(LastAction::Load),
+            ),
+            "Save / Load slots",
+        ),
+        entry(
+            pair(Command::DeleteSlot, Command::DuplicateSlot),
+            "Delete / Duplicate slot",
+        ),
+        entry(key(Command::Quit), "Quit"),
+        String::new(),
+        "Persistence".to_string(),
+        entry("Startup".to_string(), "Offer to resume last session"),
+        entry("Quit".to_string(), "Auto-save session"),
+    ];
+
+    if !keymap.problems.is_empty() {
+        lines.push(String::new());
+        lines.push(format!("Key binding problems ({KEYMAP_FILE})"));
+        lines.extend(keymap.problems.iter().map(|problem| format!("- {problem}")));
+    }
+
+    lines
+}
+
+/// Movement keys grouped per scheme, e.g. `← ↓ ↑ → / H J K L`.
+fn move_keys_label(keymap: &Keymap) -> String {
+    move_key_schemes(keymap)
+        .iter()
+        .map(|scheme| scheme.join(" "))
+        .collect::<Vec<_>>()
+        .join(" / ")
+}
+
+/// The move keys for a footer, e.g. `Arrows/HJKL`.
+fn move_keys_short(keymap: &Keymap) -> String {
+    move_key_schemes(keymap)
+        .iter()
+        .map(|scheme| {
+            if scheme.concat() == "←↓↑→" {
+                "Arrows".to_string()
+            } else if scheme.iter().all(|label| label.chars().count() == 1) {
+                scheme.concat()
+            } else {
+                scheme.join(" ")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The move keys in left, down, up, right order, one list per set of
+/// bindings: the first key of each direction, then the second, and so on.
+fn move_key_schemes(keymap: &Keymap) -> Vec<Vec<String>> {
+    let per_dir: Vec<Vec<String>> = [MoveDir::Left, MoveDir::Down, MoveDir::Up, MoveDir::Right]
+        .into_iter()
+        .map(|dir| {
+            keymap
+                .keys_for(Command::Move(dir))
+                .iter()
+                .map(|binding| binding.label())
+                .collect()
+        })
+        .collect();
+    let schemes = per_dir.iter().map(Vec::len).max().unwrap_or(0);
+
+    (0..schemes)
+        .map(|i| {
+            per_dir
+                .iter()
+                .filter_map(|labels| labels.get(i).cloned())
+                .collect()
+        })
+        .collect()
+}
+
+fn help_rect(lines: &[String], area: Rect) -> Rect {
     let max_line = lines
         .iter()
         .map(|s| s.chars().count() as u16)
@@ -420,6 +1221,39 @@ fn format_hhmmss(duration: Duration) -> String {
     format!("{h:02}:{m:02}:{s:02}")
 }
 
+/// `12:34`, or `1:02:03` past the hour.
+fn format_clock(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (h, m, s) = (secs / 3600, (secs % 3600) / 60, secs % 60);
+    if h > 0 {
+        format!("{h}:{m:02}:{s:02}")
+    } else {
+        format!("{m:02}:{s:02}")
+    }
+}
+
+fn format_ago(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{}m ago", secs / 60),
+        3600..=86399 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
+
+fn resume_label(save: &SaveSummary) -> String {
+    let ago = SystemTime::now()
+        .duration_since(save.last_played_at)
+        .unwrap_or_default();
+    format!(
+        "Resume ({}, {}, saved {})",
+        save.label,
+        format_clock(save.elapsed),
+        format_ago(ago)
+    )
+}
+
 fn centered_rect_exact(width: u16, height: u16, area: Rect) -> Rect {
     let w = width.min(area.width);
     let h = height.min(area.height);
@@ -433,23 +1267,17 @@ fn centered_rect_exact(width: u16, height: u16, area: Rect) -> Rect {
     }
 }
 
-fn top_left_rect_exact(width: u16, height: u16, area: Rect) -> Rect {
-    Rect {
-        x: area.x,
-        y: area.y,
-        width: width.min(area.width),
-        height: height.min(area.height),
-    }
+fn grid_width(edges: BoxEdges, zoom: UiZoom) -> usize {
+    // Row label and left border (4), each cell and the gap after it, two more
+    // per box border, less one for the right border's missing trailing space.
+    let size = edges.size;
+    3 + size * (zoom.cell_w() + 1) + 2 * (size / edges.cols)
 }
 
-fn grid_width(zoom: UiZoom) -> usize {
-    // Row width is: 18 + (9 * cell_w).
-    18 + (9 * zoom.cell_w())
-}
-
-fn grid_height(zoom: UiZoom) -> usize {
-    // 1 col header + 1 top border + (9 * cell_h) + 2 inner borders + 1 bottom border
-    5 + (9 * zoom.cell_h())
+fn grid_height(edges: BoxEdges, zoom: UiZoom) -> usize {
+    // Column header, top border, the cells, and one border under each box row.
+    let size = edges.size;
+    2 + size * zoom.cell_h() + size / edges.rows
 }
 
 fn side_panel_height() -> usize {
@@ -458,6 +1286,9 @@ fn side_panel_height() -> usize {
 }
 
 fn side_panel_width(state: &GameState) -> usize {
+    if state.tutorial.is_some() {
+        return LESSON_PANEL_WIDTH;
+    }
     let selected = state.selected_cell();
     let visible_value = if selected.given && !state.show_givens {
         None
@@ -465,7 +1296,7 @@ fn side_panel_width(state: &GameState) -> usize {
         selected.value
     };
     let value = visible_value
-        .map(|d| d.to_string())
+        .map(|d| state.shape.symbol(d).to_string())
         .unwrap_or_else(|| ".".into());
     let candidates = selected.candidates();
     let candidates_line = if candidates.is_empty() {
@@ -475,7 +1306,7 @@ fn side_panel_width(state: &GameState) -> usize {
             "Candidates: {}",
             candidates
                 .iter()
-                .map(|d| d.to_string())
+                .map(|d| state.shape.symbol(*d).to_string())
                 .collect::<Vec<_>>()
                 .join(" ")
         )
@@ -486,7 +1317,7 @@ fn side_panel_width(state: &GameState) -> usize {
             "Input: {}",
             candidates
                 .iter()
-                .map(|d| d.to_string())
+                .map(|d| state.shape.symbol(*d).to_string())
                 .collect::<Vec<_>>()
                 .join(" ")
         )
@@ -505,11 +1336,11 @@ fn side_panel_width(state: &GameState) -> usize {
             state.selection.row + 1,
             state.selection.col + 1
         ),
-        format!("Value: {value}"),
+        value_label(state, &value),
         candidates_line,
         format!("Mode: {mode_str}"),
         input_line,
-        format!("Hints left: {}", state.hints_left),
+        format!("Hints left: {}", hints_left(state)),
         "Actions".to_string(),
         "u Undo   r Redo".to_string(),
         "h Hint   c Clear".to_string(),
@@ -526,45 +1357,95 @@ fn side_panel_width(state: &GameState) -> usize {
     max_inner.saturating_add(2)
 }
 
-pub fn render_selector(frame: &mut Frame, state: &GameState) {
-
-    let mut options_list: Vec<DifficultyOption> = vec![
-        DifficultyOption::Easy,
-        DifficultyOption::Medium,
-        DifficultyOption::Hard,
-        DifficultyOption::Expert,
-    ];
-
-    if state.has_recent_save {
-        options_list.insert(0, DifficultyOption::Resume);
-    }
+pub fn render_selector(frame: &mut Frame, state: &GameState, keymap: &Keymap) {
+    let options_list = state.selector_options();
 
-    let options_map = |opt: &DifficultyOption| -> &'static str {
+    let shape = state.settings.grid_shape;
+    let variant = state.settings.variant;
+    // The classic ranges are what generation actually produces; other games
+    // show the target, which uniqueness can only raise.
+    let level = |difficulty: Difficulty, classic: &str| {
+        if shape == GridShape::CLASSIC && variant == Variant::Classic {
+            format!("{difficulty} ({classic} givens)")
+        } else {
+            let givens = crate::puzzle::target_givens(difficulty, shape, variant);
+            format!("{difficulty} ({givens}+ givens)")
+        }
+    };
+    let options_map = |opt: &DifficultyOption| -> String {
         match opt {
-            DifficultyOption::Easy => "Easy (36-38 givens)",
-            DifficultyOption::Medium => "Medium (30-32 givens)",
-            DifficultyOption::Hard => "Hard (24-27 givens)",
-            DifficultyOption::Expert => "Very Hard (17-22 givens)",
-            DifficultyOption::Resume => "Resume Game",
+            DifficultyOption::Easy => level(Difficulty::Easy, "36-38"),
+            DifficultyOption::Medium => level(Difficulty::Medium, "30-32"),
+            DifficultyOption::Hard => level(Difficulty::Hard, "24-27"),
+            DifficultyOption::Expert => level(Difficulty::Expert, "17-22"),
+            DifficultyOption::GridSize => format!("Grid size: < {shape} >"),
+            DifficultyOption::Variant => format!("Variant: < {variant} >"),
+            DifficultyOption::Mode => match state.settings.mode {
+                GameMode::Standard => "Mode: < Standard >".to_string(),
+                GameMode::Countdown => format!(
+                    "Mode: < Countdown {} min >",
+                    state.settings.countdown_minutes
+                ),
+                GameMode::TimeAttack => format!(
+                    "Mode: < Time attack, Easy {} min >",
+                    crate::state::TIME_ATTACK_LIMIT.as_secs() / 60
+                ),
+                GameMode::Zen => "Mode: < Zen, no clock >".to_string(),
+            },
+            DifficultyOption::Resume => match &state.recent_save {
+                Some(save) => resume_label(save),
+                None => "Resume Game".to_string(),
+            },
+            DifficultyOption::ContinuePack => match state.packs.continue_target() {
+                Some(next) => format!(
+                    "Continue pack: {} #{}",
+                    state
+                        .packs
+                        .find(&next.pack)
+                        .map_or("", |pack| pack.title.as_str()),
+                    next.index + 1
+                ),
+                None => "Continue pack".to_string(),
+            },
+            DifficultyOption::Packs => "Puzzle packs".to_string(),
+            DifficultyOption::Tutorial => format!(
+                "Tutorial: < {}. {} >",
+                state.lesson_choice + 1,
+                lessons()[state.lesson_choice].title
+            ),
+            DifficultyOption::Settings => "Settings".to_string(),
         }
     };
 
+    let selection_style = state.themes.get(&state.settings.theme).selection;
     let selected_style = |opt: &DifficultyOption| -> Style {
         let mut style = Style::default();
         if *opt == state.selector_selection {
-            style = style.add_modifier(Modifier::REVERSED);
+            style = style.patch(selection_style);
         }
         style
     };
 
     let option_count = options_list.len();
 
-    let box_w = 25u16;
+    let box_w = options_list
+        .iter()
+        .map(|opt| options_map(opt).chars().count() as u16 + 5)
+        .max()
+        .unwrap_or(0)
+        .max(25);
     let box_h = 2u16 + option_count as u16;
-    let box_x = 80u16.saturating_sub(box_w) / 2;
+    // Two footer lines follow the options after a blank one.
+    let need_h = box_h + 3;
+    let area = frame.size();
+    if area.width < box_w || area.height < need_h {
+        render_too_small(frame, keymap, box_w, need_h);
+        return;
+    }
+    let box_x = area.x + (area.width - box_w) / 2;
     let box_area = Rect {
         x: box_x,
-        y: frame.size().height.saturating_sub(box_h) / 2,
+        y: area.y + (area.height - need_h) / 2,
         width: box_w,
         height: box_h,
     };
@@ -572,9 +1453,6 @@ pub fn render_selector(frame: &mut Frame, state: &GameState) {
     frame.render_widget(Clear, box_area);
 
     for (i, opt) in options_list.iter().enumerate() {
-        let is_resume = matches!(opt, DifficultyOption::Resume);
-        let can_select = is_resume || !state.has_recent_save;
-
         let label = options_map(opt);
         let label = format!(
             "  {}  {}",
@@ -586,11 +1464,7 @@ pub fn render_selector(frame: &mut Frame, state: &GameState) {
             label
         );
 
-        let style = if can_select {
-            selected_style(opt)
-        } else {
-            Style::default()
-        };
+        let style = selected_style(opt);
 
         frame.render_widget(
             Paragraph::new(Line::from(label)).style(style),
@@ -604,8 +1478,23 @@ pub fn render_selector(frame: &mut Frame, state: &GameState) {
     }
 
     let footer_lines = [
-        Line::from("Arrows to move, Enter to select, q to quit"),
-        Line::from(""),
+        toast_line(state).unwrap_or_else(|| {
+            Line::from(format!(
+                "{} to move, {} to select, {} to quit",
+                move_keys_short(keymap),
+                keymap.label_for(Command::SelectDifficulty),
+                keymap.label_for(Command::Quit)
+            ))
+        }),
+        if keymap.problems.is_empty() {
+            Line::from("")
+        } else {
+            Line::from(format!(
+                "{} key binding problem(s), press {} for details",
+                keymap.problems.len(),
+                keymap.label_for(Command::ToggleHelp)
+            ))
+        },
     ];
 
     for (i, line) in footer_lines.iter().enumerate() {
@@ -614,123 +1503,1212 @@ pub fn render_selector(frame: &mut Frame, state: &GameState) {
             Rect {
                 x: box_x,
                 y: box_area.y + 2 + option_count as u16 + 1 + i as u16,
-                width: box_w,
+                width: frame.size().width.saturating_sub(box_x),
                 height: 1,
             },
         );
     }
-}
 
-pub fn render_win(frame: &mut Frame, state: &GameState) {
-    #[allow(unused_imports)]
-    use crate::state::Difficulty;
+    if state.help_visible {
+        render_help(frame, keymap, frame.size());
+    }
+}
 
-    let elapsed = state.started_at.elapsed();
-    let timer = format_hhmmss(elapsed);
-    let difficulty = state.difficulty.to_string();
+pub fn render_win(frame: &mut Frame, state: &GameState, keymap: &Keymap) {
+    if state.mode == GameMode::Zen {
+        let lines = vec![
+            Line::from("Puzzle Solved!"),
+            Line::from(""),
+            Line::from(format!("Difficulty:  {}", state.label())),
+            Line::from(format!("Hints used:  {}", state.hints_used())),
+            Line::from(""),
+        ];
+        render_end_screen(frame, state, keymap, lines);
+        return;
+    }
+    let mistakes = if state.check_mode.counts_mistakes() {
+        format!("{}/{}", state.mistakes, state.mistakes_max)
+    } else {
+        "off".to_string()
+    };
 
-    let stats_lines = [
+    let mut lines = vec![
         Line::from("Congratulations! Puzzle Solved!"),
         Line::from(""),
-        Line::from(format!("Time:      {}", timer)),
-        Line::from(format!("Difficulty: {}", difficulty)),
         Line::from(format!(
-            "Mistakes:   {}/{}",
-            state.mistakes, state.mistakes_max
+            "Time:        {}",
+            format_hhmmss(state.clock.elapsed())
         )),
-        Line::from(""),
     ];
+    if let Some(left) = state.time_left() {
+        lines.push(Line::from(format!("Time left:   {}", format_hhmmss(left))));
+    }
+    lines.extend([
+        Line::from(format!("Difficulty:  {}", state.label())),
+        Line::from(format!("Mistakes:    {mistakes}")),
+        Line::from(format!(
+            "Hints used:  {}/{}",
+            state.hints_used(),
+            state.hints_max
+        )),
+        Line::from(""),
+    ]);
+    lines.extend(top_lines(state));
+    render_end_screen(frame, state, keymap, lines);
+}
 
-    let leaderboard_lines = match crate::leaderboard::Leaderboard::load() {
-        Ok(leaderboard) => {
-            let top = leaderboard.get_top_for_difficulty(state.difficulty, 5);
-            if top.is_empty() {
-                vec![
-                    Line::from("Top 5 - This difficulty:".to_string()),
-                    Line::from(" (no completed games yet)"),
-                ]
-            } else {
-                let mut lines = vec![Line::from(format!("Top 5 - {}:", difficulty))];
-                for (i, entry) in top.iter().enumerate() {
-                    let time_str = format_hhmmss(Duration::from_secs(entry.time_seconds));
-                    let date_str = &entry.completed_at;
-                    lines.push(Line::from(format!(
-                        "{}.  {}   {}",
-                        i + 1,
-                        time_str,
-                        date_str
-                    )));
-                }
-                lines
+/// The end of a countdown that ran out, or of a time-attack run.
+pub fn render_time_up(frame: &mut Frame, state: &GameState, keymap: &Keymap) {
+    let mut lines = if state.mode == GameMode::TimeAttack {
+        vec![
+            Line::from("Time attack over!"),
+            Line::from(""),
+            Line::from(format!("Puzzles solved:  {}", state.run_solved)),
+            Line::from(format!("Difficulty:      {}", state.label())),
+        ]
+    } else {
+        vec![
+            Line::from("Time's up!"),
+            Line::from(""),
+            Line::from(format!("Difficulty:  {}", state.label())),
+            Line::from(format!("Filled in:   {}%", state.progress_percent())),
+        ]
+    };
+    lines.push(Line::from(""));
+    lines.extend(top_lines(state));
+    render_end_screen(frame, state, keymap, lines);
+}
+
+/// The best results in the finished game's leaderboard category.
+fn top_lines(state: &GameState) -> Vec<Line<'static>> {
+    if !state.is_ranked() {
+        return vec![Line::from("Imported puzzles are not ranked.")];
+    }
+    let mut lines = vec![Line::from(format!("Top 5 - {}:", state.label()))];
+    match &state.win.top {
+        Ok(top) if top.is_empty() => lines.push(Line::from(" (no completed games yet)")),
+        Ok(top) => {
+            for (i, entry) in top.iter().enumerate() {
+                let rules = entry.rules.map(|r| r.to_string()).unwrap_or_default();
+                let result = if entry.mode == GameMode::TimeAttack {
+                    format!("{:>3} solved", entry.solved)
+                } else {
+                    format_hhmmss(Duration::from_secs(entry.time_seconds))
+                };
+                lines.push(Line::from(format!(
+                    "{}.  {}   {}   {}",
+                    i + 1,
+                    result,
+                    entry.completed_at,
+                    rules
+                )));
             }
         }
-        Err(_) => {
-            vec![
-                Line::from(format!("Top 5 - {}:", difficulty)),
-                Line::from("(leaderboard unavailable)"),
-            ]
+        Err(e) => lines.push(Line::from(format!(" (unavailable: {e})"))),
+    }
+    lines
+}
+
+/// Boxes `lines` in the middle of the screen, followed by the end screen's
+/// actions and key hints.
+fn render_end_screen(
+    frame: &mut Frame,
+    state: &GameState,
+    keymap: &Keymap,
+    mut lines: Vec<Line<'static>>,
+) {
+    let selection = state.themes.get(&state.settings.theme).selection;
+    lines.push(Line::from(""));
+    for &action in state.win_actions() {
+        let selected = action == state.win.selected;
+        let text = format!("{} {}", if selected { ">" } else { " " }, action.label());
+        let style = if selected {
+            selection
+        } else {
+            Style::default()
+        };
+        lines.push(Line::from(Span::styled(text, style)));
+    }
+
+    lines.push(Line::from(""));
+    lines.push(toast_line(state).unwrap_or_else(|| {
+        Line::from(format!(
+            "{} select  {} menu  {} quit",
+            keymap.label_for(Command::SelectDifficulty),
+            keymap.label_for(Command::Back),
+            keymap.label_for(Command::Quit)
+        ))
+    }));
+
+    let width = lines.iter().map(|l| l.width()).max().unwrap_or(0) as u16 + 4;
+    let height = lines.len() as u16 + 2;
+    if frame.size().width < width || frame.size().height < height {
+        render_too_small(frame, keymap, width, height);
+        return;
+    }
+    let area = centered_rect_exact(width, height, frame.size());
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(Text::from(lines)).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .padding(Padding::horizontal(1)),
+        ),
+        area,
+    );
+
+    if state.help_visible {
+        render_help(frame, keymap, frame.size());
+    }
+}
+
+/// The play screen showing the finished game as it stood partway through.
+pub fn render_replay(frame: &mut Frame, state: &GameState, keymap: &Keymap) {
+    render(frame, &state.replay_frame(), keymap);
+}
+
+/// Lines of `items` joined by two spaces, each at most `width` wide.
+fn wrap_items(items: &[String], width: usize) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for item in items {
+        if !line.is_empty() && line.chars().count() + 2 + item.chars().count() > width {
+            lines.push(Line::from(std::mem::take(&mut line)));
+        }
+        if !line.is_empty() {
+            line.push_str("  ");
         }
+        line.push_str(item);
+    }
+    lines.push(Line::from(line));
+    lines
+}
+
+pub fn render_analysis(frame: &mut Frame, state: &GameState, keymap: &Keymap) {
+    const NEED_W: u16 = 60;
+    const NEED_H: u16 = 14;
+    let area = frame.size();
+    if area.width < NEED_W || area.height < NEED_H {
+        render_too_small(frame, keymap, NEED_W, NEED_H);
+        return;
+    }
+    let Some(analysis) = &state.analysis else {
+        return;
     };
+    let theme = state.themes.get(&state.settings.theme);
+    let inner_w = area.width as usize - 2;
+
+    let mut lines = vec![Line::from(format!("Solve path: {}", state.label()))];
+    let counts: Vec<String> = analysis
+        .counts()
+        .into_iter()
+        .map(|(technique, count)| format!("{} {count}", technique.label()))
+        .collect();
+    lines.extend(wrap_items(&counts, inner_w));
+    let wrong = analysis.wrong;
+    lines.push(Line::from(format!(
+        "You filled {} cells: {} logical, {} guessed, {} hinted; {wrong} wrong {}",
+        analysis.placements.len(),
+        analysis.count_filled(Filled::Logic),
+        analysis.count_filled(Filled::Guess),
+        analysis.count_filled(Filled::Hint),
+        if wrong == 1 { "entry" } else { "entries" }
+    )));
+    lines.push(Line::from(""));
+    lines.push(Line::from(format!(
+        "  {:>3}  {:<17}  {:<9}  Step",
+        "#", "Technique", "You"
+    )));
+
+    // Keep the cursor in view, scrolling once it reaches the bottom.
+    let footer_h = 2;
+    let rows = (area.height as usize - 2).saturating_sub(lines.len() + footer_h);
+    let first = state.analysis_step.saturating_sub(rows.saturating_sub(1));
+    for (i, step) in analysis.steps.iter().enumerate().skip(first).take(rows) {
+        let selected = i == state.analysis_step;
+        let filled = step
+            .placement
+            .and_then(|(cell, _)| analysis.filled_at(cell));
+        let (you, you_style) = match filled {
+            Some((n, Filled::Logic)) => (format!("#{n}"), Style::default()),
+            Some((n, Filled::Guess)) => (format!("#{n} guess"), theme.wrong),
+            Some((n, Filled::Hint)) => (format!("#{n} hint"), theme.notes),
+            None => (String::new(), Style::default()),
+        };
+        let row_style = if selected {
+            theme.selection
+        } else {
+            Style::default()
+        };
+        let technique_style = if step.technique == Technique::Guess {
+            row_style.patch(theme.wrong)
+        } else {
+            row_style
+        };
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("{} {:>3}  ", if selected { ">" } else { " " }, i + 1),
+                row_style,
+            ),
+            Span::styled(format!("{:<17}  ", step.technique.label()), technique_style),
+            Span::styled(format!("{you:<9}"), row_style.patch(you_style)),
+            Span::styled(format!("  {}", step.detail), row_style),
+        ]));
+    }
 
-    let footer_lines = vec![
-        Line::from("Press Enter to continue"),
-        Line::from("Press q to quit"),
-    ];
+    lines.push(Line::from(""));
+    lines.push(Line::from(format!(
+        "Step {}/{}  ↑/↓ move  {} back",
+        (state.analysis_step + 1).min(analysis.steps.len()),
+        analysis.steps.len(),
+        keymap.label_for(Command::Back)
+    )));
 
-    let header_w = stats_lines
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(Text::from(lines)).block(Block::default().borders(Borders::ALL)),
+        area,
+    );
+}
+
+pub fn render_settings(frame: &mut Frame, state: &GameState, keymap: &Keymap) {
+    let draft = &state.settings_draft;
+    // Preview the theme being picked rather than the one in effect.
+    let theme = state.themes.get(&draft.theme);
+    let label_w = SettingsField::ALL
         .iter()
-        .chain(leaderboard_lines.iter())
-        .map(|l| l.width())
+        .map(|field| field.label().chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut lines = vec![Line::from("Settings"), Line::from("")];
+    for field in SettingsField::ALL {
+        let selected = field == state.settings_field;
+        let text = format!(
+            "{} {:<label_w$}  < {} >",
+            if selected { ">" } else { " " },
+            field.label(),
+            draft.value_label(field),
+        );
+        let style = if selected {
+            theme.selection
+        } else {
+            Style::default()
+        };
+        lines.push(Line::from(Span::styled(text, style)));
+    }
+    lines.push(Line::from(""));
+    lines.extend(theme_preview_lines(theme));
+    for problem in &state.themes.problems {
+        lines.push(Line::from(format!("! {problem}")));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(format!(
+        "←/→ change  {} save  {} cancel",
+        keymap.label_for(Command::SelectDifficulty),
+        keymap.label_for(Command::Back)
+    )));
+
+    let width = lines.iter().map(|l| l.width()).max().unwrap_or(0) as u16 + 4;
+    let height = lines.len() as u16 + 2;
+    let area = centered_rect_exact(width, height, frame.size());
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(Text::from(lines)).block(Block::default().borders(Borders::ALL)),
+        area,
+    );
+
+    if state.help_visible {
+        render_help(frame, keymap, frame.size());
+    }
+}
+
+fn stars(rating: u8) -> String {
+    (1..=5)
+        .map(|star| if star <= rating { '★' } else { '☆' })
+        .collect()
+}
+
+pub fn render_packs(frame: &mut Frame, state: &GameState, keymap: &Keymap) {
+    let browser = &state.packs;
+    let progress = &browser.progress;
+    let selection = state.themes.get(&state.settings.theme).selection;
+
+    let mut lines = Vec::new();
+    let mut rows = Vec::new();
+    match browser.open {
+        None => {
+            lines.push(Line::from("Puzzle packs"));
+            let title_w = browser
+                .packs
+                .iter()
+                .map(|pack| pack.title.chars().count())
+                .max()
+                .unwrap_or(0);
+            for (i, pack) in browser.packs.iter().enumerate() {
+                let solved = progress.solved_count(pack);
+                let total = pack.puzzles.len();
+                rows.push(if browser.is_unlocked(i) {
+                    format!(
+                        "{} {:<title_w$}  {solved:>2}/{total:<2}  {}  by {}",
+                        if solved == total { "✓" } else { " " },
+                        pack.title,
+                        stars(pack.rating),
+                        pack.author,
+                    )
+                } else {
+                    let previous = &browser.packs[i - 1];
+                    format!(
+                        "  {:<title_w$}  locked: solve {} of {}",
+                        pack.title,
+                        crate::packs::unlock_target(previous),
+                        previous.title,
+                    )
+                });
+            }
+        }
+        Some(open) => {
+            let pack = &browser.packs[open];
+            lines.push(Line::from(format!(
+                "{}  {}  by {}",
+                pack.title,
+                stars(pack.rating),
+                pack.author
+            )));
+            let label_w = (0..pack.puzzles.len())
+                .map(|i| pack.puzzle_label(i).chars().count())
+                .max()
+                .unwrap_or(0);
+            for (i, puzzle) in pack.puzzles.iter().enumerate() {
+                rows.push(format!(
+                    "{} {:<label_w$}  {}",
+                    if progress.is_solved(pack, i) {
+                        "✓"
+                    } else {
+                        " "
+                    },
+                    pack.puzzle_label(i),
+                    puzzle.difficulty,
+                ));
+            }
+        }
+    }
+    lines.push(Line::from(""));
+    for (i, row) in rows.into_iter().enumerate() {
+        let selected = i == browser.selected;
+        let text = format!("{} {row}", if selected { ">" } else { " " });
+        let style = if selected {
+            selection
+        } else {
+            Style::default()
+        };
+        lines.push(Line::from(Span::styled(text, style)));
+    }
+
+    lines.push(Line::from(""));
+    for problem in &browser.problems {
+        lines.push(Line::from(format!("! {problem}")));
+    }
+    lines.extend(toast_line(state));
+    let confirm = if browser.open.is_some() {
+        "play"
+    } else {
+        "open"
+    };
+    lines.push(Line::from(format!(
+        "{} {confirm}  {} back",
+        keymap.label_for(Command::SelectDifficulty),
+        keymap.label_for(Command::Back)
+    )));
+
+    let width = lines.iter().map(|l| l.width()).max().unwrap_or(0) as u16 + 4;
+    let height = lines.len() as u16 + 2;
+    let area = centered_rect_exact(width, height, frame.size());
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(Text::from(lines)).block(Block::default().borders(Borders::ALL)),
+        area,
+    );
+
+    if state.help_visible {
+        render_help(frame, keymap, frame.size());
+    }
+}
+
+pub fn render_slots(frame: &mut Frame, state: &GameState, keymap: &Keymap) {
+    let browser = &state.browser;
+    let selection = state.themes.get(&state.settings.theme).selection;
+    let name_w = browser
+        .slots
+        .iter()
+        .map(|slot| slot.name.chars().count())
         .max()
         .unwrap_or(0)
-        .max(60)
-        .saturating_add(4);
+        .max(12);
 
-    let body_w = header_w.saturating_add(2);
-    let body_h = stats_lines.len() as u16 + leaderboard_lines.len() as u16;
+    let title = match browser.mode {
+        BrowserMode::Save => "Save game",
+        BrowserMode::Load => "Load game",
+    };
+    let mut lines = vec![Line::from(title), Line::from("")];
+    let mut rows = Vec::new();
+    if browser.mode == BrowserMode::Save {
+        rows.push("+ New slot".to_string());
+    }
+    for slot in &browser.slots {
+        let ago = SystemTime::now()
+            .duration_since(slot.last_played_at)
+            .unwrap_or_default();
+        rows.push(format!(
+            "{:<name_w$}  {:<19}  {:>3}%  {:>8}  {}",
+            slot.name,
+            slot.label,
+            slot.progress_percent,
+            format_clock(slot.elapsed),
+            format_ago(ago),
+        ));
+    }
+    if rows.is_empty() {
+        lines.push(Line::from("  No saved games yet"));
+    }
+    for (i, row) in rows.into_iter().enumerate() {
+        let selected = i == browser.selected;
+        let text = format!("{} {row}", if selected { ">" } else { " " });
+        let style = if selected {
+            selection
+        } else {
+            Style::default()
+        };
+        lines.push(Line::from(Span::styled(text, style)));
+    }
+
+    lines.push(Line::from(""));
+    if let Some(name) = &browser.name_input {
+        lines.push(Line::from(format!("Name: {name}_")));
+        lines.push(Line::from("Enter save  Esc cancel"));
+    } else {
+        lines.extend(toast_line(state));
+        let confirm = match browser.mode {
+            BrowserMode::Save => "save",
+            BrowserMode::Load => "load",
+        };
+        lines.push(Line::from(format!(
+            "{} {confirm}  {} delete  {} duplicate  {} back",
+            keymap.label_for(Command::SelectDifficulty),
+            keymap.label_for(Command::DeleteSlot),
+            keymap.label_for(Command::DuplicateSlot),
+            keymap.label_for(Command::Back)
+        )));
+    }
 
-    let popup_w = body_w.saturating_add(4);
-    let popup_h = body_h.saturating_add(2);
+    let width = lines.iter().map(|l| l.width()).max().unwrap_or(0) as u16 + 4;
+    let height = lines.len() as u16 + 2;
+    let area = centered_rect_exact(width, height, frame.size());
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(Text::from(lines)).block(Block::default().borders(Borders::ALL)),
+        area,
+    );
 
-    let popup_area = Rect {
-        x: (frame.size().width.saturating_sub(popup_w as u16) / 2),
-        y: (frame.size().height.saturating_sub(popup_h as u16) / 2),
-        width: popup_w as u16,
-        height: popup_h as u16,
+    if state.help_visible {
+        render_help(frame, keymap, frame.size());
+    }
+}
+
+fn theme_preview_lines(theme: &Theme) -> Vec<Line<'static>> {
+    let sample = |text: &str, style: Style, label: &str| {
+        vec![
+            Span::styled(format!(" {text} "), style),
+            Span::raw(format!(" {label:<10}")),
+        ]
     };
 
-    let inner_area = Rect {
-        x: popup_area.x + 2,
-        y: popup_area.y + 1,
-        width: popup_area.width.saturating_sub(4),
-        height: popup_area.height.saturating_sub(2),
+    vec![
+        Line::from(format!("Preview: {}", theme.name)),
+        Line::from(
+            [
+                sample("5", theme.given, "given"),
+                sample("3", theme.user_digit, "entered"),
+                sample("7", theme.wrong, "wrong"),
+                sample("9", theme.conflict, "conflict"),
+            ]
+            .concat(),
+        ),
+        Line::from(
+            [
+                sample("1", theme.selection, "selected"),
+                sample("4", theme.peer, "peer"),
+                sample("3", theme.highlight, "same digit"),
+                sample("8", theme.region, "region"),
+            ]
+            .concat(),
+        ),
+        Line::from(
+            [
+                sample("2 6", theme.notes, "notes"),
+                sample("┼─│", theme.border, "borders"),
+            ]
+            .concat(),
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        history::Move,
+        leaderboard::LeaderboardEntry,
+        puzzle::Constraint as Rule,
+        settings::{CheckMode, Rules},
+        state::{PlayClock, WinAction},
     };
+    use ratatui::{backend::TestBackend, buffer::Buffer, Terminal};
+    use std::{env, fs, path::Path};
+
+    const SIZES: [(u16, u16); 4] = [(40, 12), (60, 20), (80, 24), (120, 40)];
+    const ZOOMS: [UiZoom; 4] = [UiZoom::Small, UiZoom::Medium, UiZoom::Large, UiZoom::XLarge];
+
+    fn buffer_lines(buffer: &Buffer) -> Vec<String> {
+        let area = buffer.area;
+        (0..area.height)
+            .map(|y| {
+                (0..area.width)
+                    .map(|x| buffer.get(x, y).symbol())
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .collect()
+    }
 
-    let max_inner_w = inner_area.width;
+    fn draw(width: u16, height: u16, render: impl FnOnce(&mut Frame)) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(render).unwrap();
+        buffer_lines(terminal.backend().buffer())
+    }
 
-    for (i, line) in stats_lines
-        .iter()
-        .chain(leaderboard_lines.iter())
-        .enumerate()
-    {
-        let text = Text::from(vec![line.clone()]);
-        frame.render_widget(
-            Paragraph::new(text).alignment(Alignment::Left),
-            Rect {
-                x: inner_area.x,
-                y: inner_area.y + i as u16,
-                width: max_inner_w,
-                height: 1,
+    /// Compares the drawn text with `tests/snapshots/<name>.txt`. After an
+    /// intended layout change, run the tests with `UPDATE_SNAPSHOTS=1` to
+    /// rewrite the files and review the diff.
+    fn assert_snapshot(name: &str, lines: &[String]) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/snapshots")
+            .join(format!("{name}.txt"));
+        let actual = lines.join("\n") + "\n";
+        if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, actual).unwrap();
+            return;
+        }
+        let expected = fs::read_to_string(&path).unwrap_or_else(|_| {
+            panic!(
+                "no snapshot at {}; run with UPDATE_SNAPSHOTS=1 to create it",
+                path.display()
+            )
+        });
+        assert_eq!(
+            actual.lines().collect::<Vec<_>>(),
+            expected.lines().collect::<Vec<_>>(),
+            "{name} differs from its snapshot (UPDATE_SNAPSHOTS=1 rewrites it)"
+        );
+    }
+
+    /// A fixed board with entries and notes, stopped at 12:34.
+    fn fixture_game() -> GameState {
+        let mut state = GameState::from_json(include_str!(
+            "../tests/fixtures/migrations/session-v1-baseline.json"
+        ))
+        .unwrap();
+        state.screen = Screen::Playing;
+        state
+    }
+
+    fn won_game() -> GameState {
+        let mut state = fixture_game();
+        state.screen = Screen::Win;
+        state.clock = PlayClock::stopped_at(Duration::from_secs(754));
+        state.mistakes = 1;
+        state.hints_left = 1;
+        state.win.top = Ok(vec![LeaderboardEntry {
+            difficulty: Difficulty::Medium,
+            shape: GridShape::CLASSIC,
+            variant: Variant::Classic,
+            time_seconds: 700,
+            completed_at: "2026-10-01".to_string(),
+            rules: Some(Rules {
+                mistakes_max: 3,
+                hints: 2,
+                check_mode: CheckMode::Solution,
+            }),
+            mode: GameMode::Standard,
+            time_limit_secs: None,
+            solved: 1,
+        }]);
+        state
+    }
+
+    #[test]
+    fn play_screen_snapshots() {
+        let keymap = Keymap::default();
+        for zoom in ZOOMS {
+            for (width, height) in SIZES {
+                let mut state = fixture_game();
+                state.ui_zoom = zoom;
+                let lines = draw(width, height, |frame| render(frame, &state, &keymap));
+                let name = format!("play_{zoom:?}_{width}x{height}").to_lowercase();
+                assert_snapshot(&name, &lines);
+            }
+        }
+    }
+
+    /// The fixture game's session on a patterned grid of another size.
+    fn shaped_game(shape: GridShape) -> GameState {
+        let mut state = fixture_game();
+        let size = shape.size();
+        state.shape = shape;
+        state.solution = (0..size)
+            .map(|r| {
+                (0..size)
+                    .map(|c| {
+                        ((r % shape.box_rows * shape.box_cols + r / shape.box_rows + c) % size + 1)
+                            as u8
+                    })
+                    .collect()
+            })
+            .collect();
+        state.grid = shape.empty_grid();
+        for (r, row) in state.grid.iter_mut().enumerate() {
+            for (c, cell) in row.iter_mut().enumerate() {
+                if (r * 7 + c * 3) % 3 != 0 {
+                    *cell = crate::state::Cell::given(state.solution[r][c]);
+                }
+            }
+        }
+        state.selection = crate::state::Selection { row: 1, col: 2 };
+        state
+    }
+
+    #[test]
+    fn other_grid_size_snapshots() {
+        let keymap = Keymap::default();
+        for shape in [GridShape::ALL[1], GridShape::ALL[4]] {
+            for (width, height) in [(80, 24), (120, 40)] {
+                let state = shaped_game(shape);
+                let lines = draw(width, height, |frame| render(frame, &state, &keymap));
+                assert_snapshot(&format!("play_{shape}_{width}x{height}"), &lines);
+            }
+        }
+    }
+
+    /// The fixture game as a Killer puzzle cut into cages two rows tall,
+    /// which split boxes and cross the border between rows C and D.
+    fn killer_game() -> GameState {
+        let mut state = fixture_game();
+        state.variant = Variant::Killer;
+        let mut cages: Vec<crate::puzzle::Cage> = Vec::new();
+        for row in 0..9 {
+            for col in 0..9 {
+                let index = (row / 2) * 4 + (col + 1) / 3;
+                if cages.len() <= index {
+                    cages.push(crate::puzzle::Cage {
+                        sum: 0,
+                        cells: Vec::new(),
+                    });
+                }
+                cages[index].sum += state.solution[row][col] as u32;
+                cages[index].cells.push((row, col));
+            }
+        }
+        state.cages = cages;
+        state
+    }
+
+    /// The fixture game as a Jigsaw puzzle: the classic boxes with two pairs
+    /// of cells traded across their edges.
+    fn jigsaw_game() -> GameState {
+        let mut state = fixture_game();
+        state.variant = Variant::Jigsaw;
+        let mut boxes = crate::puzzle::standard_boxes(state.shape);
+        for (a, b, from_a, from_b) in [(0, 1, (2, 2), (0, 3)), (4, 7, (5, 4), (6, 3))] {
+            boxes[a].retain(|&cell| cell != from_a);
+            boxes[b].retain(|&cell| cell != from_b);
+            boxes[a].push(from_b);
+            boxes[b].push(from_a);
+        }
+        state.boxes = boxes;
+        state
+    }
+
+    #[test]
+    fn killer_snapshots() {
+        let keymap = Keymap::default();
+        for zoom in [UiZoom::Medium, UiZoom::XLarge] {
+            for (width, height) in [(80, 24), (120, 40)] {
+                let mut state = killer_game();
+                state.ui_zoom = zoom;
+                let lines = draw(width, height, |frame| render(frame, &state, &keymap));
+                let name = format!("play_killer_{zoom:?}_{width}x{height}").to_lowercase();
+                assert_snapshot(&name, &lines);
+            }
+        }
+    }
+
+    #[test]
+    fn jigsaw_snapshots() {
+        let keymap = Keymap::default();
+        for (zoom, width, height) in [(UiZoom::Medium, 80, 24), (UiZoom::XLarge, 120, 40)] {
+            let mut state = jigsaw_game();
+            state.ui_zoom = zoom;
+            let lines = draw(width, height, |frame| render(frame, &state, &keymap));
+            let name = format!("play_jigsaw_{zoom:?}_{width}x{height}").to_lowercase();
+            assert_snapshot(&name, &lines);
+        }
+    }
+
+    #[test]
+    fn jigsaw_walls_follow_the_box_edges() {
+        let state = jigsaw_game();
+        let lines = grid_text(&state, UiZoom::Medium);
+        // No fixed box borders: the header, top border, then one line a row.
+        assert_eq!(lines.len(), 2 + 9 + 1);
+        let walls = |row: usize| -> Vec<usize> {
+            let spans = &lines[2 + row].spans;
+            (0..8)
+                .filter(|col| spans[3 + col * 2].content == "│")
+                .collect()
+        };
+        // Row A's box 0 reaches column 4; row C's box 1 reaches column 3.
+        assert_eq!(walls(0), [3, 5]);
+        assert_eq!(walls(2), [1, 5]);
+        assert_eq!(walls(4), [2, 5]);
+
+        let underlined = |row: usize, col: usize| {
+            let span = &lines[2 + row].spans[2 + col * 2];
+            span.style.add_modifier.contains(Modifier::UNDERLINED)
+        };
+        assert!(underlined(0, 3));
+        assert!(underlined(1, 2));
+        assert!(underlined(2, 0));
+        assert!(!underlined(0, 0));
+        assert!(!underlined(3, 0));
+    }
+
+    fn constraint_game() -> GameState {
+        let mut state = fixture_game();
+        state.title = Some("Warm-up".to_string());
+        state.constraints = vec![
+            Rule::Thermo(vec![(0, 0), (0, 1), (1, 1)]),
+            Rule::Arrow {
+                circle: (4, 4),
+                shaft: vec![(4, 3), (3, 3)],
             },
+            Rule::WhiteDot([(0, 2), (0, 3)]),
+            Rule::X([(6, 6), (7, 6)]),
+        ];
+        state
+    }
+
+    #[test]
+    fn constraint_snapshots() {
+        let keymap = Keymap::default();
+        for (zoom, width, height) in [(UiZoom::Medium, 80, 24), (UiZoom::Large, 120, 40)] {
+            let mut state = constraint_game();
+            state.ui_zoom = zoom;
+            let lines = draw(width, height, |frame| render(frame, &state, &keymap));
+            let name = format!("play_constraints_{zoom:?}_{width}x{height}").to_lowercase();
+            assert_snapshot(&name, &lines);
+        }
+    }
+
+    #[test]
+    fn tutorial_snapshots() {
+        let keymap = Keymap::default();
+        let mut state = fixture_game();
+        state.start_lesson(2);
+        for (zoom, width, height) in [(UiZoom::Medium, 80, 24), (UiZoom::Large, 120, 40)] {
+            state.ui_zoom = zoom;
+            let lines = draw(width, height, |frame| render(frame, &state, &keymap));
+            let name = format!("tutorial_{zoom:?}_{width}x{height}").to_lowercase();
+            assert_snapshot(&name, &lines);
+        }
+
+        state.enter_lesson_digit(4);
+        state.enter_lesson_digit(2);
+        state.ui_zoom = UiZoom::Medium;
+        let lines = draw(80, 24, |frame| render(frame, &state, &keymap));
+        assert_snapshot("tutorial_done_80x24", &lines);
+    }
+
+    #[test]
+    fn tutorial_footers_name_the_keys_of_the_keymap() {
+        let keymap = Keymap::from_preset(Preset::Numpad);
+        let mut state = fixture_game();
+        state.start_lesson(0);
+        let lines = draw(120, 40, |frame| render(frame, &state, &keymap));
+        assert!(lines
+            .iter()
+            .any(|l| l.contains("Arrows Move  1-9 Enter  / Notes  Esc Menu  ? Help")));
+
+        let keymap = Keymap::from_toml("[bindings]\nselect = \"space\"\nquit = \"ctrl+q\"\n");
+        state.tutorial = state.tutorial.map(|mut tutorial| {
+            tutorial.step = tutorial.lesson().steps.len();
+            tutorial
+        });
+        let lines = draw(120, 40, |frame| render(frame, &state, &keymap));
+        assert!(lines
+            .iter()
+            .any(|l| l.contains("Space Next lesson  Esc Menu  Ctrl+q Quit")));
+    }
+
+    #[test]
+    fn lesson_cells_are_highlighted() {
+        let mut state = fixture_game();
+        state.start_lesson(2);
+        let highlight = state.themes.get(&state.settings.theme).highlight;
+        let lines = grid_text(&state, UiZoom::Medium);
+        // Row A holds the 4 in A6 that the first step points at.
+        let a6 = &lines[2].spans[2 + 2 * 5];
+        assert_eq!(a6.content, "4 ");
+        assert_eq!(
+            a6.style,
+            Style::default()
+                .patch(state.themes.get(&state.settings.theme).given)
+                .patch(highlight)
         );
+        let a5 = &lines[2].spans[2 + 2 * 4];
+        assert_ne!(a5.style.bg, highlight.bg);
     }
 
-    let footer_block = Block::default().title("Press Enter to continue");
-    let footer_text = Text::from(footer_lines);
-    frame.render_widget(Clear, popup_area);
-    frame.render_widget(footer_block, popup_area);
-    frame.render_widget(
-        Paragraph::new(footer_text).alignment(Alignment::Center),
-        inner_area,
-    );
+    #[test]
+    fn constraint_glyphs_sit_between_their_cells() {
+        let state = constraint_game();
+        let text = |lines: &[Line], i: usize| -> String {
+            lines[i]
+                .spans
+                .iter()
+                .map(|span| span.content.as_ref())
+                .collect()
+        };
+
+        // Medium has no line between rows, so a vertical glyph takes the
+        // upper cell's spare column.
+        let medium = grid_text(&state, UiZoom::Medium);
+        assert_eq!(text(&medium, 2), "A │ 2 <7∧ 8  ○ 5  1  .  │ 4  3  6  │");
+        assert_eq!(text(&medium, 7), "E │ 1  9  .  │ . ←6  .  │ .  8  .  │");
+
+        let large = grid_text(&state, UiZoom::Large);
+        assert_eq!(
+            text(&large, 3),
+            "A │  2 < 7   8  ○  5   1   .  │  4   3   6  │"
+        );
+        assert_eq!(
+            text(&large, 4),
+            "  │      ∧      │             │             │"
+        );
+        assert_eq!(
+            text(&large, 11),
+            "  │             │  ↑          │             │"
+        );
+        assert_eq!(
+            text(&large, 12),
+            "E │  1   9   .  │  . ← 6   .  │  .   8   .  │"
+        );
+        assert_eq!(
+            text(&large, 18),
+            "  │             │             │  X          │"
+        );
+    }
+
+    #[test]
+    fn killer_cage_walls_are_underlined_between_rows() {
+        let state = killer_game();
+        let underlined = |line: &Line, col: usize| {
+            // The row label and left border, then each cell and its gap.
+            let span = &line.spans[2 + col * 2];
+            span.style.add_modifier.contains(Modifier::UNDERLINED)
+        };
+        let lines = grid_text(&state, UiZoom::Medium);
+        // Rows A and B share cages; B and C don't.
+        assert!(!underlined(&lines[2], 0));
+        assert!(underlined(&lines[3], 0));
+        // After columns 2, 5 and 8, inside each box.
+        let walls = lines[2].spans.iter().filter(|s| s.content == "┊").count();
+        assert_eq!(walls, 3);
+        assert_eq!(value_label(&state, "."), "Value: .  Cage: 32");
+    }
+
+    #[test]
+    fn extra_regions_are_shaded() {
+        let mut state = fixture_game();
+        state.settings.highlight_peers = false;
+        state.selection.row = 4;
+        state.selection.col = 0;
+        let region = state.themes.get(&state.settings.theme).region;
+        let shaded = |state: &GameState, row: usize, col: usize| {
+            // Box borders sit between every third row.
+            let lines = grid_text(state, UiZoom::Medium);
+            lines[2 + row + row / 3].spans[2 + col * 2].style.bg == region.bg
+        };
+        assert!(!shaded(&state, 0, 0));
+
+        state.variant = Variant::SudokuX;
+        assert!(shaded(&state, 0, 0));
+        assert!(shaded(&state, 0, 8));
+        assert!(!shaded(&state, 0, 1));
+
+        state.variant = Variant::Windoku;
+        assert!(!shaded(&state, 0, 0));
+        assert!(shaded(&state, 1, 1));
+        assert!(shaded(&state, 7, 5));
+        assert!(!shaded(&state, 4, 4));
+    }
+
+    #[test]
+    fn grid_size_matches_the_drawn_grid() {
+        let mut states: Vec<GameState> = GridShape::ALL.into_iter().map(shaped_game).collect();
+        states.push(jigsaw_game());
+        for mut state in states {
+            let shape = state.shape;
+            state.settings.highlight_conflicts = true;
+            if shape == GridShape::CLASSIC && state.boxes.is_empty() {
+                state.cages = killer_game().cages;
+            }
+            let edges = BoxEdges::of(&state);
+            for zoom in ZOOMS {
+                let lines = grid_text(&state, zoom);
+                assert_eq!(lines.len(), grid_height(edges, zoom), "{shape} {zoom:?}");
+                for line in &lines[1..] {
+                    assert_eq!(line.width(), grid_width(edges, zoom), "{shape} {zoom:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn help_overlay_snapshots() {
+        let keymap = Keymap::default();
+        for (width, height) in SIZES {
+            let mut state = fixture_game();
+            state.help_visible = true;
+            let lines = draw(width, height, |frame| render(frame, &state, &keymap));
+            assert_snapshot(&format!("help_{width}x{height}"), &lines);
+        }
+    }
+
+    #[test]
+    fn selector_snapshots() {
+        let keymap = Keymap::default();
+        for (width, height) in SIZES {
+            let mut state = fixture_game();
+            state.screen = Screen::LevelSelector;
+            state.last_played_at = SystemTime::now() - Duration::from_secs(2 * 3600 + 60);
+            state.recent_save = Some(SaveSummary::of(&state));
+            state.selector_selection = DifficultyOption::Resume;
+            let lines = draw(width, height, |frame| {
+                render_selector(frame, &state, &keymap)
+            });
+            assert_snapshot(&format!("selector_{width}x{height}"), &lines);
+        }
+    }
+
+    #[test]
+    fn pack_browser_snapshots() {
+        let keymap = Keymap::default();
+        let mut state = fixture_game();
+        state.screen = Screen::Packs;
+        state.packs = crate::packs::PackBrowser::campaign();
+        let solved = [("1-first-steps", 0..6), ("2-warming-up", 1..2)];
+        for (pack, indices) in solved {
+            for index in indices {
+                state.packs.progress.mark_solved(&crate::packs::PackRef {
+                    pack: pack.to_string(),
+                    index,
+                });
+            }
+        }
+        state.packs.selected = 1;
+        let lines = draw(80, 24, |frame| render_packs(frame, &state, &keymap));
+        assert_snapshot("packs_80x24", &lines);
+
+        assert!(state.packs.open_selected());
+        let lines = draw(80, 24, |frame| render_packs(frame, &state, &keymap));
+        assert_snapshot("pack_open_80x24", &lines);
+    }
+
+    #[test]
+    fn analysis_snapshots() {
+        let keymap = Keymap::default();
+        let mut state = won_game();
+        state.win.selected = WinAction::Analysis;
+        state.confirm_win_action().unwrap();
+        let placed: Vec<_> = state
+            .analysis
+            .as_ref()
+            .unwrap()
+            .steps
+            .iter()
+            .filter_map(|step| step.placement)
+            .collect();
+        let mut fill = |((row, col), digit): ((usize, usize), u8), hint: bool| {
+            state.moves.push(Move {
+                row,
+                col,
+                value: Some(digit),
+                at_secs: 0,
+                hint,
+            });
+        };
+        let (wrong_cell, digit) = placed[0];
+        fill((wrong_cell, digit % 9 + 1), false);
+        fill(placed[placed.len() - 1], false);
+        fill(placed[0], false);
+        fill(placed[1], true);
+        state.analysis = None;
+        state.confirm_win_action().unwrap();
+        assert_eq!(state.screen, Screen::Analysis);
+        state.step_analysis(true);
+        state.step_analysis(true);
+
+        for (width, height) in [(80, 24), (120, 40)] {
+            let lines = draw(width, height, |frame| {
+                render_analysis(frame, &state, &keymap)
+            });
+            assert_snapshot(&format!("analysis_{width}x{height}"), &lines);
+        }
+    }
+
+    #[test]
+    fn win_screen_snapshots() {
+        let keymap = Keymap::default();
+        for (width, height) in SIZES {
+            let state = won_game();
+            let lines = draw(width, height, |frame| render_win(frame, &state, &keymap));
+            assert_snapshot(&format!("win_{width}x{height}"), &lines);
+        }
+    }
+
+    #[test]
+    fn win_screen_marks_the_selected_action_and_leaderboard_errors() {
+        let mut state = won_game();
+        state.win.top = Err("disk on fire".to_string());
+        state.move_win_selection(true);
+        state.move_win_selection(true);
+
+        let mut terminal = Terminal::new(TestBackend::new(60, 22)).unwrap();
+        terminal
+            .draw(|frame| render_win(frame, &state, &Keymap::default()))
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        let lines = buffer_lines(buffer);
+
+        let row = lines
+            .iter()
+            .position(|l| l.contains("> View replay"))
+            .unwrap();
+        let col = lines[row].chars().position(|c| c == '>').unwrap() as u16;
+        let selection = state.themes.get(&state.settings.theme).selection;
+        assert!(buffer
+            .get(col, row as u16)
+            .modifier
+            .contains(selection.add_modifier));
+        assert!(lines
+            .iter()
+            .any(|l| l.contains("(unavailable: disk on fire)")));
+        assert!(!lines.iter().any(|l| l.contains("> Next puzzle")));
+    }
+
+    #[test]
+    fn time_up_snapshots() {
+        let keymap = Keymap::default();
+        for mode in [GameMode::Countdown, GameMode::TimeAttack] {
+            let mut state = won_game();
+            state.screen = Screen::TimeUp;
+            state.mode = mode;
+            state.time_limit = Some(Duration::from_secs(if mode == GameMode::Countdown {
+                600
+            } else {
+                900
+            }));
+            state.run_solved = 4;
+            state.win.selected = WinAction::TryAgain;
+            if let Ok(top) = &mut state.win.top {
+                top[0].mode = mode;
+                top[0].time_limit_secs = state.time_limit.map(|limit| limit.as_secs());
+                top[0].solved = 6;
+            }
+            let lines = draw(80, 24, |frame| render_time_up(frame, &state, &keymap));
+            let name = format!("time_up_{mode:?}_80x24").to_lowercase();
+            assert_snapshot(&name, &lines);
+        }
+    }
+
+    #[test]
+    fn footers_name_the_keys_of_the_keymap() {
+        let keymap = Keymap::from_preset(Preset::Numpad);
+        let state = fixture_game();
+        let lines = draw(120, 40, |frame| render(frame, &state, &keymap));
+        assert!(lines
+            .iter()
+            .any(|l| l.contains("Arrows Move  1-9 Enter  / Notes  Ctrl+n New")));
+
+        let keymap = Keymap::from_preset(Preset::Vim);
+        let mut state = fixture_game();
+        state.screen = Screen::LevelSelector;
+        let lines = draw(120, 40, |frame| render_selector(frame, &state, &keymap));
+        assert!(lines
+            .iter()
+            .any(|l| l.contains("Arrows/hjkl to move, Enter to select, q to quit")));
+
+        let keymap = Keymap::from_toml("[bindings]\nquit = \"ctrl+q\"\n");
+        let lines = draw(20, 10, |frame| render(frame, &fixture_game(), &keymap));
+        assert!(lines.iter().any(|l| l.contains("Ctrl+q")));
+    }
+
+    #[test]
+    fn zen_games_hide_the_clock_and_mistakes() {
+        let mut state = fixture_game();
+        state.mode = GameMode::Zen;
+        assert_eq!(header_line(&state), "SUDOKUI  Medium, zen");
+        state.paused = true;
+        assert_eq!(header_line(&state), "SUDOKUI  Medium, zen [PAUSED]");
+        assert_eq!(hints_left(&state), "unlimited");
+    }
+
+    #[test]
+    fn timed_games_count_down_in_the_header() {
+        let mut state = fixture_game();
+        state.mode = GameMode::TimeAttack;
+        state.time_limit = Some(Duration::from_secs(900));
+        state.run_solved = 2;
+        assert_eq!(
+            header_line(&state),
+            "SUDOKUI  Medium, time attack  00:02:26 left  Solved: 2  Mistakes: 1/3"
+        );
+        state.clock = PlayClock::stopped_at(Duration::from_secs(1000));
+        assert!(header_line(&state).contains("00:00:00 left"));
+    }
+
+    #[test]
+    fn replay_header_shows_the_move_being_replayed() {
+        let mut state = won_game();
+        let (row, col) = (0..81)
+            .map(|i| (i / 9, i % 9))
+            .find(|&(r, c)| state.grid[r][c].value.is_none())
+            .unwrap();
+        let before = state.values();
+        state.grid[row][col].value = Some(state.solution[row][col]);
+        state.clock = PlayClock::stopped_at(Duration::from_secs(5));
+        state.log_moves_since(&before, false);
+        state.screen = Screen::Replay;
+        state.replay_step = 1;
+
+        let lines = draw(100, 40, |frame| {
+            render_replay(frame, &state, &Keymap::default())
+        });
+        assert!(lines
+            .iter()
+            .any(|l| l.contains("REPLAY  Medium  00:00:05  Move 1/1")));
+        assert!(lines.iter().any(|l| l.contains("Back to results")));
+    }
 }